@@ -7,6 +7,6 @@
 #cargo run --release -- --render pod.ogg pod.mid
 #cargo run --release -- --render dragonage.ogg dragonage.mid
 
-cargo run --release -- --render ms7.ogg ms72.mid
+cargo run --release -- --render ms7.ogg ms72.mid ms7/ms7.RPP
 
 #rsync -Pvr resources/ ../phantoma/resources/
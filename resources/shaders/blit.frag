@@ -8,10 +8,19 @@ layout(location = 0) out vec4 color;
 
 layout(set = 0, binding = 0) uniform texture2D img;
 layout(set = 0, binding = 1) uniform sampler samp;
-// layout(set = 0, binding = 2) uniform Transform {
-//     mat4 transform;
-// } t;
+layout(set = 0, binding = 2) uniform Transform {
+    mat4 transform;
+    float nearest;
+} t;
 
 void main() {
-    color = texture(sampler2D(img, samp), tex);
+    vec2 uv = (t.transform * vec4(tex - 0.5, 0.0, 1.0)).xy + 0.5;
+
+    // Sample texel centers so the linear sampler doesn't blend neighbours
+    if (t.nearest > 0.5) {
+        vec2 size = vec2(textureSize(sampler2D(img, samp), 0));
+        uv = (floor(uv * size) + 0.5) / size;
+    }
+
+    color = texture(sampler2D(img, samp), uv);
 }
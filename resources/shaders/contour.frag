@@ -1,17 +1,33 @@
 #version 450
 #extension GL_ARB_separate_shader_objects : enable
-#extension GL_EXT_nonuniform_qualifier : require
 
 layout(early_fragment_tests) in;
 
 layout(location = 0) in vec2 tex;
 layout(location = 0) out vec4 color;
 
-layout(set = 0, binding = 0) uniform U {
-    vec3 c;
+layout(set = 0, binding = 0) uniform texture2D img;
+layout(set = 0, binding = 1) uniform sampler samp;
+
+// Same layout as Fx in src/pipeline/fx.rs
+layout(set = 1, binding = 0) uniform Uniforms {
     float t;
-    float min;
-    float amp;
+    float tc;
+    float pause;
+    float glitch;
+    float glitch_mo;
+    float vhs;
+    float red;
+    float flash;
+    float bloom;
+    float invert;
+    float edge;
+    float mega;
+    float shake;
+    float abberation;
+    float discolor;
+    float psych;
+    float contour;
 } u;
 
 // https://www.shadertoy.com/view/lltBWM
@@ -33,11 +49,13 @@ void main() {
 
     float z = wave(st.x, st.y) + 2.0;
     
-    z *= u.amp * sin(1.57 + t / 5.0) + u.min;
+    z *= 2.0 * sin(1.57 + t / 5.0) + 4.0;
     float d = fract(z);
     if(mod(z, 2.0) > 1.) d = 1.-d;
      
     d = d/fwidth(z);
 
-    color = vec4(1.0 - d);
-}
\ No newline at end of file
+    // Contour lines over the image
+    vec3 base = texture(sampler2D(img, samp), tex).rgb;
+    color = vec4(base + clamp(1.0 - d, 0.0, 1.0) * u.contour, 1.0);
+}
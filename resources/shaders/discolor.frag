@@ -8,12 +8,30 @@ layout(location = 0) out vec4 color;
 
 layout(set = 0, binding = 0) uniform texture2D img;
 layout(set = 0, binding = 1) uniform sampler samp;
-layout(set = 0, binding = 2) uniform Uniforms {
+
+// Same layout as Fx in src/pipeline/fx.rs
+layout(set = 1, binding = 0) uniform Uniforms {
     float t;
+    float tc;
+    float pause;
+    float glitch;
+    float glitch_mo;
+    float vhs;
+    float red;
+    float flash;
+    float bloom;
+    float invert;
+    float edge;
+    float mega;
+    float shake;
+    float abberation;
+    float discolor;
+    float psych;
+    float contour;
 } u;
 
 void main() {
     vec4 c = texture(sampler2D(img, samp), tex);
-    c.rg += tex * ((sin(u.t) + 1.0) / 2.0);
-    color = c;
+    c.rg += tex * ((sin(u.t) + 1.0) / 2.0) * u.discolor;
+    color = vec4(c.rgb, 1.0);
 }
@@ -18,10 +18,21 @@ layout(set = 1, binding = 0) uniform Uniforms {
     float vhs;
     float red;
     float flash;
-    float shake;
-    float black;
+    float bloom;
+    float invert;
     float edge;
     float mega;
+    float shake;
+    float abberation;
+    float discolor;
+    float psych;
+    float contour;
+    float simpleglitch;
+    float flip_h;
+    float flip_v;
+    float rotate_cw;
+    float rotate_ccw;
+    vec2 res;
 } u;
 
 float inside(float x, float lo, float hi) {
@@ -63,7 +74,7 @@ float snoise(vec2 p) {
 }
 
 void main() {
-    vec2 res = vec2(640.0, 360.0);
+    vec2 res = u.res;
     vec2 st = tex;
 
     vec3 i = texture(sampler2D(imgs[0], samp), st).rgb;
@@ -6,8 +6,28 @@ layout(early_fragment_tests) in;
 layout(location = 0) in vec2 tex;
 layout(location = 0) out vec4 color;
 
-layout(set = 0, binding = 0) uniform U {
+layout(set = 0, binding = 0) uniform texture2D img;
+layout(set = 0, binding = 1) uniform sampler samp;
+
+// Same layout as Fx in src/pipeline/fx.rs
+layout(set = 1, binding = 0) uniform Uniforms {
     float t;
+    float tc;
+    float pause;
+    float glitch;
+    float glitch_mo;
+    float vhs;
+    float red;
+    float flash;
+    float bloom;
+    float invert;
+    float edge;
+    float mega;
+    float shake;
+    float abberation;
+    float discolor;
+    float psych;
+    float contour;
 } u;
 
 void main() {
@@ -23,7 +43,8 @@ void main() {
     for (int ii = 0; ii <= 7; ii++) {
         float i = 0.5 + float(ii);
         // fractal formula and rotation
-        p = abs(2.*fract(p-.5)-1.) * mat2(cos(.01*(u.t)*i*i + .78*vec4(1,7,3,1)));
+        vec4 r = cos(.01*(u.t)*i*i + .78*vec4(1,7,3,1));
+        p = abs(2.*fract(p-.5)-1.) * mat2(r.x, r.y, r.z, r.w);
         
         // coloration
         c += exp(-abs(p.y)*5.) * (cos(vec4(2,3,1,0)*i)*.5+.5);
@@ -32,5 +53,6 @@ void main() {
     // palette
     c.gb *= .5;
     
-    color = c;
+    vec3 base = texture(sampler2D(img, samp), tex).rgb;
+    color = vec4(mix(base, c.rgb, u.psych), 1.0);
 }
@@ -0,0 +1,60 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+
+// Most corners a polygon region can have
+const int MAX_POINTS = 16;
+
+layout(location = 0) in vec2 tex;
+layout(location = 0) out vec4 color;
+
+layout(set = 0, binding = 0) uniform texture2D img;
+layout(set = 0, binding = 1) uniform sampler samp;
+layout(set = 0, binding = 2) uniform Region {
+    // Center and half size before rotating, in layout pixels
+    vec4 rect;
+    // cos and sin of the clockwise rotation, polygon corners or 0 for a rect, level
+    vec4 shape;
+    // Part of the image the region shows: offset xy, scale zw
+    vec4 source;
+    // Layout size in xy
+    vec4 size;
+    // Polygon corners in xy, in layout pixels before rotating
+    vec4 points[MAX_POINTS];
+} r;
+
+bool inside(vec2 p) {
+    int n = int(r.shape.z);
+    if (n == 0) {
+        vec2 d = abs(p - r.rect.xy);
+        return d.x <= r.rect.z && d.y <= r.rect.w;
+    }
+
+    // Even-odd crossings of a ray to the right
+    bool odd = false;
+    int j = n - 1;
+    for (int i = 0; i < n; i++) {
+        vec2 a = r.points[i].xy;
+        vec2 b = r.points[j].xy;
+        if ((a.y > p.y) != (b.y > p.y) && p.x < (b.x - a.x) * (p.y - a.y) / (b.y - a.y) + a.x) {
+            odd = !odd;
+        }
+        j = i;
+    }
+    return odd;
+}
+
+void main() {
+    // Undo the region's rotation about its center
+    vec2 d = tex * r.size.xy - r.rect.xy;
+    float c = r.shape.x;
+    float s = r.shape.y;
+    vec2 p = r.rect.xy + vec2(c * d.x + s * d.y, c * d.y - s * d.x);
+
+    if (!inside(p)) {
+        discard;
+    }
+
+    vec2 uv = (p - r.rect.xy + r.rect.zw) / (2.0 * r.rect.zw);
+    vec3 rgb = texture(sampler2D(img, samp), r.source.xy + uv * r.source.zw).rgb;
+    color = vec4(rgb * r.shape.w, 1.0);
+}
@@ -0,0 +1,52 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+
+const int MAX_POINTS = 64;
+// Lattice drawn while pinning corners
+const float CORNER_LINES = 8.0;
+
+layout(location = 0) in vec2 tex;
+layout(location = 0) out vec4 color;
+
+layout(set = 0, binding = 0) uniform texture2D img;
+layout(set = 0, binding = 1) uniform sampler samp;
+layout(set = 0, binding = 2) uniform Warp {
+    mat4 keystone;
+    vec4 points[MAX_POINTS];
+    vec4 blend;
+    vec4 grid;
+    vec4 selected;
+} w;
+
+// Falls off towards an edge `x` away, over `width`, such that two
+// projectors' ramps over the same overlap add up to one
+float ramp(float x, float width) {
+    if (width <= 0.0) {
+        return 1.0;
+    }
+    return smoothstep(0.0, 1.0, clamp(x / width, 0.0, 1.0));
+}
+
+void main() {
+    color = texture(sampler2D(img, samp), tex);
+
+    float b = ramp(tex.x, w.blend.x)
+        * ramp(1.0 - tex.x, w.blend.y)
+        * ramp(tex.y, w.blend.z)
+        * ramp(1.0 - tex.y, w.blend.w);
+    // Ramp in light, not in the gamma encoded output
+    color.rgb *= pow(b, 1.0 / w.grid.z);
+
+    if (w.grid.w > 0.5) {
+        vec2 lines = w.grid.w < 1.5 ? vec2(CORNER_LINES) : w.grid.xy - 1.0;
+        vec2 px = fwidth(tex);
+
+        vec2 g = abs(fract(tex * lines + 0.5) - 0.5) / (px * lines);
+        float line = 1.0 - clamp(min(g.x, g.y) - 0.5, 0.0, 1.0);
+        color.rgb = mix(color.rgb, vec3(0.0, 1.0, 0.0), line * 0.6);
+
+        float d = length((tex - w.selected.xy) / px);
+        float ring = 1.0 - clamp(abs(d - 10.0) - 1.5, 0.0, 1.0);
+        color.rgb = mix(color.rgb, vec3(1.0, 0.0, 1.0), ring);
+    }
+}
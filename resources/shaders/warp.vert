@@ -0,0 +1,62 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+
+// Subdivisions of the drawn mesh along each side
+const int GRID = 32;
+// Most control points the bezier grid can have
+const int MAX_POINTS = 64;
+
+layout(location = 0) out vec2 tex;
+
+layout(set = 0, binding = 2) uniform Warp {
+    // Unit square to the keystone quad, as a 3x3 in xyz
+    mat4 keystone;
+    // Control point offsets in xy, cols * rows of them row by row
+    vec4 points[MAX_POINTS];
+    // Soft edge widths: left, right, top, bottom
+    vec4 blend;
+    // cols, rows, gamma, edit mode
+    vec4 grid;
+    // Selected point in xy
+    vec4 selected;
+} w;
+
+const vec2 CORNERS[6] = vec2[6](
+    vec2(0.0, 0.0), vec2(1.0, 0.0), vec2(1.0, 1.0),
+    vec2(0.0, 0.0), vec2(1.0, 1.0), vec2(0.0, 1.0)
+);
+
+float binomial(int n, int k) {
+    float c = 1.0;
+    for (int i = 1; i <= k; i++) {
+        c = c * float(n - k + i) / float(i);
+    }
+    return c;
+}
+
+float bernstein(int n, int i, float t) {
+    return binomial(n, i) * pow(t, float(i)) * pow(1.0 - t, float(n - i));
+}
+
+void main() {
+    int index = int(gl_VertexIndex);
+    int cell = index / 6;
+    vec2 uv = (vec2(float(cell % GRID), float(cell / GRID)) + CORNERS[index % 6]) / float(GRID);
+    tex = uv;
+
+    // Control points sit on an even lattice plus their offsets, so with no
+    // offsets the surface is just uv
+    int cols = int(w.grid.x);
+    int rows = int(w.grid.y);
+    vec2 p = uv;
+    for (int j = 0; j < rows; j++) {
+        float bv = bernstein(rows - 1, j, uv.y);
+        for (int i = 0; i < cols; i++) {
+            p += bernstein(cols - 1, i, uv.x) * bv * w.points[j * cols + i].xy;
+        }
+    }
+
+    // Keep w for perspective correct texture coords across the keystone
+    vec3 q = (w.keystone * vec4(p, 0.0, 1.0)).xyz;
+    gl_Position = vec4(2.0 * q.x - q.z, q.z - 2.0 * q.y, 0.0, q.z);
+}
@@ -0,0 +1,165 @@
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+use crate::demo::{Demo, Event, Id, Names, StageInfo};
+use crate::util::{ModMatrix, Routes};
+use crate::validate::read_cfg;
+
+/// What a stage does with its events.
+#[derive(Default)]
+struct Handlers {
+    /// Ids the stage handles in `event`, and where each goes, if anywhere
+    arms: HashMap<Id, Option<&'static str>>,
+    /// Names the stage handles in `named`
+    named: HashMap<&'static str, Option<&'static str>>,
+    /// Ids routed from the stage's `.cfg`, or read by its modulation matrix
+    routes: HashSet<Id>,
+}
+
+impl Handlers {
+    fn of(info: &StageInfo) -> Self {
+        let mut handlers = Self {
+            arms: info.handles.iter().copied().collect(),
+            named: info.named.iter().copied().collect(),
+            ..Default::default()
+        };
+
+        // Missing or bad configs are reported by `validate`
+        for cfg in info.routes.iter() {
+            if let Ok((_, src)) = read_cfg(cfg) {
+                handlers.routes.extend(Routes::scan(&src).0.into_iter().map(Id::Note));
+            }
+        }
+        for cfg in info.matrices.iter() {
+            if let Ok((_, src)) = read_cfg(cfg) {
+                handlers.routes.extend(ModMatrix::scan(&src).0.into_iter().map(Id::Mod));
+            }
+        }
+
+        handlers
+    }
+
+    /// Whether the stage does something with an event, and where it goes after.
+    fn handle(&self, id: Id, name: Option<&str>) -> Option<Option<&'static str>> {
+        if let Some(go) = self.arms.get(&id) {
+            return Some(*go);
+        }
+        if let Some(go) = name.and_then(|name| self.named.get(name)) {
+            return Some(*go);
+        }
+        self.routes.contains(&id).then_some(None)
+    }
+}
+
+#[derive(Default)]
+struct Visit {
+    t0: f32,
+    t1: f32,
+    /// Events with no handler, with their count and first time
+    unhandled: BTreeMap<Id, (usize, f32)>,
+    seen: HashSet<Id>,
+}
+
+/// Replay a `.dem`'s events through the stage chain and report which events
+/// no stage does anything with, and which events stages expect but never get.
+///
+/// Handlers are what each stage declares in its `StageInfo`, along with the
+/// demo's `.names` file and the routes in stage `.cfg`s.
+pub fn run(file: &str, stage0: Option<&str>) -> Result<()> {
+    let demo = Demo::load(file)?;
+    let names_file = Path::new(file).with_extension("names");
+    let names = Names::load(&names_file.to_string_lossy()).unwrap_or_else(|e| {
+        println!("No event names loaded: {:#}", e);
+        Names::default()
+    });
+
+    let infos = crate::stages::infos();
+    let handlers: HashMap<&str, Handlers> = infos
+        .iter()
+        .map(|(stage, info)| (*stage, Handlers::of(info)))
+        .collect();
+
+    let mut stage = stage0.unwrap_or(infos[0].0).to_owned();
+    let mut visits: Vec<(String, Visit)> = vec![(stage.clone(), Visit::default())];
+
+    for &(t, ev) in demo.events.iter() {
+        let mut next = None;
+
+        if let Event::Region { id } = ev {
+            let label = demo.labels.get(id as usize).map(String::as_str).unwrap_or("");
+            match handlers.contains_key(label) {
+                true => next = Some(label.to_owned()),
+                false => println!("{:8.2}s  region '{}' has no stage", t, label),
+            }
+        }
+
+        let (_, visit) = visits.last_mut().unwrap();
+        visit.t1 = t;
+
+        if let (Some(id), Some(h)) = (Id::of(&ev), handlers.get(stage.as_str())) {
+            visit.seen.insert(id);
+            match h.handle(id, names.get(&stage, &ev)) {
+                Some(go) => next = next.or(go.map(str::to_owned)),
+                None => {
+                    let (n, _) = visit.unhandled.entry(id).or_insert((0, t));
+                    *n += 1;
+                }
+            }
+        }
+
+        if let Some(next) = next {
+            stage = next;
+            visits.push((stage.clone(), Visit { t0: t, t1: t, ..Default::default() }));
+        }
+    }
+
+    println!("Event coverage of {}", file);
+    for (stage, visit) in visits.iter() {
+        println!();
+        println!("{} ({:.2}s - {:.2}s)", stage, visit.t0, visit.t1);
+
+        let h = match handlers.get(stage.as_str()) {
+            Some(h) => h,
+            None => {
+                println!("  has no StageInfo");
+                continue;
+            }
+        };
+
+        let named = names.ids(stage);
+        let show = |id: Id| {
+            let name = named.get(&id).map(|n| format!(" '{}'", n)).unwrap_or_default();
+            match id {
+                Id::Note(id) => format!("note {}{}", id, name),
+                Id::Mod(id) => format!("mod {}{}", id, name),
+            }
+        };
+
+        for (id, (n, t)) in visit.unhandled.iter() {
+            println!("  unhandled  {} x{}, first at {:.2}s", show(*id), n, t);
+        }
+
+        let mut expected: Vec<Id> = h
+            .arms
+            .keys()
+            .chain(h.routes.iter())
+            .copied()
+            .chain(named.iter().filter(|(_, n)| h.named.contains_key(**n)).map(|(id, _)| *id))
+            .filter(|id| !visit.seen.contains(id))
+            .collect();
+        expected.sort();
+        expected.dedup();
+        for id in expected {
+            println!("  missing    {} is handled but never occurs", show(id));
+        }
+    }
+
+    let visited: HashSet<&str> = visits.iter().map(|(stage, _)| stage.as_str()).collect();
+    for (stage, _) in infos.iter().filter(|(stage, _)| !visited.contains(stage)) {
+        println!();
+        println!("{} is never reached", stage);
+    }
+
+    Ok(())
+}
@@ -16,7 +16,7 @@ use rustfft::num_complex::Complex32;
 use rustfft::num_traits::Zero as _;
 use rustfft::FftPlanner;
 
-use super::{Data, Metadata};
+use super::{Data, Metadata, BANDS};
 
 pub struct Stream {
     playing: Arc<AtomicBool>,
@@ -170,6 +170,10 @@ impl Stream {
         self.playing.store(true, Ordering::SeqCst);
     }
 
+    pub fn pause(&self) {
+        self.playing.store(false, Ordering::SeqCst);
+    }
+
     pub fn t(&self) -> f32 {
         let sample = self.sample.load(Ordering::SeqCst);
         sample as f32 / self.sample_rate as f32
@@ -330,9 +334,18 @@ pub fn analyze(audio: &Vec<Vec<f32>>, sample_rate: u32, fft_size: usize) -> Vec<
             max = rms;
         }
 
+        // Each band spans an octave, with the lowest taking every bin below it
+        let mut bands = [0.0; BANDS];
+        for (band, v) in bands.iter_mut().enumerate() {
+            let hi = fft_size >> (BANDS - 1 - band);
+            let lo = if band == 0 { 0 } else { hi / 2 };
+            let sum: f32 = result[lo..hi].iter().map(|s| s.powi(2)).sum();
+            *v = (sum / (hi - lo).max(1) as f32).sqrt();
+        }
+
         let t = (i * fft_size) as f32 / sample_rate as f32;
 
-        data.push((t, Data { rms }));
+        data.push((t, Data { rms, bands }));
     }
 
     data
@@ -1,8 +1,8 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use bincode::{config::Configuration, Decode, Encode};
 use std::{
     fs::File,
-    io::{BufReader, BufWriter}, path::Path,
+    io::BufWriter, path::Path,
 };
 
 #[derive(Encode, Decode, Debug, Clone, Copy)]
@@ -11,6 +11,15 @@ pub enum Event {
     Beat    { id: u8, t: f32 },
     Toggle  { id: u8, state: bool },
     Mod     { id: u8, fr: f32 },
+    Region  { id: u8 },
+    Marker  { id: u8 },
+}
+
+#[derive(Encode, Decode, Debug, Clone, Copy)]
+pub struct Tempo {
+    pub bpm: f32,
+    /// Whether the tempo ramps linearly to the next point, or jumps to it.
+    pub ramp: bool,
 }
 
 #[derive(Encode, Decode, Debug, Clone, Copy)]
@@ -22,6 +31,42 @@ pub struct Metadata {
 #[derive(Encode, Decode, Debug, Clone, Copy)]
 pub struct Data {
     pub rms: f32,
+    /// RMS of log spaced frequency bands, lowest first.
+    pub bands: [f32; BANDS],
+}
+
+/// Number of frequency bands in `Data`.
+pub const BANDS: usize = 8;
+
+/// Starts every `.dem`, ahead of the `Demo` itself.
+const MAGIC: [u8; 4] = *b"MS7D";
+/// Bumped whenever the layout of `Demo` changes.
+pub const VERSION: u32 = 1;
+
+#[derive(Encode, Decode)]
+struct Header {
+    magic: [u8; 4],
+    version: u32,
+}
+
+/// The layout from before `.dem` files had a header: no tempo, labels or
+/// frequency bands.
+mod legacy {
+    use bincode::Decode;
+
+    #[derive(Decode)]
+    pub struct Data {
+        pub rms: f32,
+    }
+
+    #[derive(Decode)]
+    pub struct Demo {
+        pub meta: super::Metadata,
+
+        pub vorbis: Vec<u8>,
+        pub events: Vec<(f32, super::Event)>,
+        pub data: Vec<(f32, Data)>,
+    }
 }
 
 #[derive(Encode, Decode)]
@@ -31,6 +76,10 @@ pub struct Demo {
     pub vorbis: Vec<u8>,
     pub events: Vec<(f32, Event)>,
     pub data: Vec<(f32, Data)>,
+    pub tempo: Vec<(f32, Tempo)>,
+
+    /// Names of `Region` and `Marker` events, indexed by their `id`.
+    pub labels: Vec<String>,
 }
 
 impl Demo {
@@ -45,25 +94,78 @@ impl Demo {
             .open(file)?;
 
         let mut write = BufWriter::new(file);
+        let header = Header {
+            magic: MAGIC,
+            version: VERSION,
+        };
+        bincode::encode_into_std_write(&header, &mut write, bincode::config::standard())?;
         bincode::encode_into_std_write(&self, &mut write, bincode::config::standard())?;
         Ok(())
     }
 
     pub fn load(file: &str) -> Result<Self> {
-        let mut read = BufReader::new(File::open(file)?);
-        Ok(bincode::decode_from_std_read(
-            &mut read,
-            bincode::config::standard(),
-        )?)
+        Self::load_bytes(&std::fs::read(file)?).with_context(|| format!("failed to load {}", file))
     }
 
+    /// Read a `.dem` of this version, or one from before there were versions,
+    /// which plays without tempo, labels or bands. Any other version has to
+    /// be rendered again.
     pub fn load_bytes(bytes: &[u8]) -> Result<Self> {
-        Ok(bincode::decode_from_slice(bytes, bincode::config::standard())?.0)
+        let config = bincode::config::standard();
+        if !bytes.starts_with(&MAGIC) {
+            let (old, _): (legacy::Demo, _) = bincode::decode_from_slice(bytes, config)
+                .context("not a demo, or from an unknown version; re-render it with --render")?;
+            log::warn!("Demo is from before format versions, re-render it with --render for tempo, labels and bands");
+            return Ok(Self {
+                meta: old.meta,
+
+                vorbis: old.vorbis,
+                events: old.events,
+                data: old
+                    .data
+                    .into_iter()
+                    .map(|(t, data)| (t, Data { rms: data.rms, bands: [0.0; BANDS] }))
+                    .collect(),
+                tempo: vec![],
+
+                labels: vec![],
+            });
+        }
+
+        let (header, read): (Header, _) = bincode::decode_from_slice(bytes, config)?;
+        if header.version != VERSION {
+            bail!(
+                "demo is format version {}, but this build reads version {}; re-render it with --render",
+                header.version,
+                VERSION
+            );
+        }
+        Ok(bincode::decode_from_slice(&bytes[read..], config)?.0)
     }
 
-    pub fn new(audio: &str, midi: &str) -> Result<Self> {
+    /// Write the events back out to a MIDI file, e.g. for importing into a DAW.
+    pub fn export_midi(&self, file: &str) -> Result<()> {
+        super::midi::write_events(file, self)
+    }
+
+    pub fn new(audio: &str, midi: &str, project: Option<&str>) -> Result<Self> {
         println!("Parsing MIDI events...");
-        let events = super::midi::parse_events(midi)?;
+        let (mut events, mut tempo) = super::midi::parse_events(midi)?;
+
+        let mut labels = Vec::new();
+        if let Some(project) = project {
+            println!("Parsing Reaper project...");
+            let rpp = super::reaper::parse(&std::fs::read_to_string(project)?)?;
+
+            // The project's tempo envelope is authoritative over the MIDI file's
+            if !rpp.tempo.is_empty() {
+                tempo = rpp.tempo;
+            }
+
+            events.extend(rpp.events);
+            events.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+            labels = rpp.labels;
+        }
 
         println!("Analyzing audio...");
         let vorbis = std::fs::read(audio)?;
@@ -86,6 +188,9 @@ impl Demo {
             vorbis,
             events,
             data,
+            tempo,
+
+            labels,
         })
     }
 }
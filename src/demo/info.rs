@@ -0,0 +1,114 @@
+use super::Id;
+use crate::util::{parse, StageConfig};
+
+/// What a stage declares about itself: the configs, routes and scene names it
+/// reads, and the events it handles. Checked by `validate` before the show
+/// starts, and replayed against a demo by `--coverage`.
+///
+/// ```ignore
+/// StageInfo::default()
+///     .cfg::<LobbyConfig>("lobby.cfg")
+///     .routes("lobby.cfg")
+///     .scene("demo_console.glb")
+///     .materials(&["ButtonMain"])
+///     .notes(&[28, 29])
+///     .go(Id::Note(10), "metalheart")
+/// ```
+#[derive(Default)]
+pub struct StageInfo {
+    /// Config files, with a check of their contents against the stage's `StageConfig`
+    pub cfgs: Vec<(&'static str, fn(&str) -> Vec<String>)>,
+    /// Config files with `route.` lines
+    pub routes: Vec<&'static str>,
+    /// Config files with `matrix.` lines
+    pub matrices: Vec<&'static str>,
+
+    pub scenes: Vec<&'static str>,
+    pub nodes: Vec<&'static str>,
+    pub materials: Vec<&'static str>,
+    pub lights: Vec<&'static str>,
+
+    /// Event ids handled in `event`, and the stage each goes to, if any
+    pub handles: Vec<(Id, Option<&'static str>)>,
+    /// Event names handled in `named`, and the stage each goes to, if any
+    pub named: Vec<(&'static str, Option<&'static str>)>,
+}
+
+fn check<T: StageConfig>(src: &str) -> Vec<String> {
+    parse(&mut T::default(), src)
+}
+
+impl StageInfo {
+    pub fn cfg<T: StageConfig>(mut self, cfg: &'static str) -> Self {
+        self.cfgs.push((cfg, check::<T>));
+        self
+    }
+
+    pub fn routes(mut self, cfg: &'static str) -> Self {
+        self.routes.push(cfg);
+        self
+    }
+
+    pub fn matrix(mut self, cfg: &'static str) -> Self {
+        self.matrices.push(cfg);
+        self
+    }
+
+    pub fn scene(mut self, scene: &'static str) -> Self {
+        self.scenes.push(scene);
+        self
+    }
+
+    pub fn nodes(mut self, nodes: &[&'static str]) -> Self {
+        self.nodes.extend(nodes);
+        self
+    }
+
+    pub fn materials(mut self, materials: &[&'static str]) -> Self {
+        self.materials.extend(materials);
+        self
+    }
+
+    pub fn lights(mut self, lights: &[&'static str]) -> Self {
+        self.lights.extend(lights);
+        self
+    }
+
+    /// Notes handled in `event`.
+    pub fn notes(mut self, ids: &[u8]) -> Self {
+        self.handles.extend(ids.iter().map(|id| (Id::Note(*id), None)));
+        self
+    }
+
+    /// Mod sliders handled in `event`.
+    pub fn mods(mut self, ids: &[u8]) -> Self {
+        self.handles.extend(ids.iter().map(|id| (Id::Mod(*id), None)));
+        self
+    }
+
+    /// Event names handled in `named`.
+    pub fn names(mut self, names: &[&'static str]) -> Self {
+        self.named.extend(names.iter().map(|name| (*name, None)));
+        self
+    }
+
+    /// An event that moves on to another stage.
+    pub fn go(mut self, id: Id, to: &'static str) -> Self {
+        self.handles.push((id, Some(to)));
+        self
+    }
+
+    /// A named event that moves on to another stage.
+    pub fn go_named(mut self, name: &'static str, to: &'static str) -> Self {
+        self.named.push((name, Some(to)));
+        self
+    }
+
+    /// Every stage this one can go to.
+    pub fn goes(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.handles
+            .iter()
+            .filter_map(|(_, to)| *to)
+            .chain(self.named.iter().filter_map(|(_, to)| *to))
+    }
+}
@@ -5,7 +5,7 @@ use apres::MIDIBytes as MidiBytes;
 use apres::MIDIEvent as MidiEvent;
 use apres::MIDI as MidiFile;
 
-use super::format::Event;
+use super::format::{Demo, Event, Tempo};
 
 impl Event {
     // pub fn from_midi(ev: MidiEvent) -> Option<Self> {
@@ -39,7 +39,7 @@ impl Event {
     // }
 }
 
-pub fn parse_events(file: &str) -> Result<Vec<(f32, Event)>> {
+pub fn parse_events(file: &str) -> Result<(Vec<(f32, Event)>, Vec<(f32, Tempo)>)> {
     let file = MidiFile::from_path(file).unwrap();
 
     let mut midi: Vec<(usize, MidiEvent)> = file
@@ -65,6 +65,7 @@ pub fn parse_events(file: &str) -> Result<Vec<(f32, Event)>> {
     };
 
     let mut midis = Vec::new();
+    let mut tempo_map = Vec::new();
     for (tick, midi) in midi.drain(..) {
         let t = last + tick_t(tick, us_per_quarter);
         last = t;
@@ -73,6 +74,7 @@ pub fn parse_events(file: &str) -> Result<Vec<(f32, Event)>> {
             MidiEvent::SetTempo(tempo) => {
                 println!("Tempo: {}", 60.0 / (tempo as f32 / 1_000_000.0));
                 us_per_quarter = tempo as f32;
+                tempo_map.push((t, Tempo { bpm: 60_000_000.0 / us_per_quarter, ramp: false }));
             }
             _ => midis.push((t, midi)),
         }
@@ -123,7 +125,142 @@ pub fn parse_events(file: &str) -> Result<Vec<(f32, Event)>> {
         }
     }
 
-    Ok(events)
+    Ok((events, tempo_map))
+}
+
+const PPQN: u16 = 960;
+const VELOCITY: u8 = 100;
+
+/// Write a demo's events back into a type-1 Standard MIDI File.
+///
+/// Uses the same note ranges as `parse_events`: Triggers are 0-29, Toggles
+/// 30-59, and Beats 60+ with their duration as the note length. Mod becomes
+/// pitch bend on the channel of its id, and Regions and Markers become
+/// marker meta events.
+pub fn write_events(file: &str, demo: &Demo) -> Result<()> {
+    let tempo = TempoMap::new(&demo.tempo);
+
+    let tempo_events: Vec<(u64, Vec<u8>)> = tempo
+        .steps
+        .iter()
+        .map(|(t, Tempo { bpm, .. })| {
+            let us_per_quarter = (60_000_000.0 / bpm).round() as u32;
+            let [_, a, b, c] = us_per_quarter.to_be_bytes();
+            (tempo.tick(*t), vec![0xFF, 0x51, 0x03, a, b, c])
+        })
+        .collect();
+
+    // Track 0 is the conductor track with the tempo map and markers.
+    // `parse_events` only reads the last track, so that needs the tempo map too.
+    let mut conductor = tempo_events.clone();
+    let mut events = tempo_events;
+    for (t, ev) in demo.events.iter() {
+        let tick = tempo.tick(*t);
+
+        match *ev {
+            Event::Trigger { id } => {
+                events.push((tick, vec![0x90, id, VELOCITY]));
+                events.push((tick + PPQN as u64 / 4, vec![0x80, id, 0]));
+            }
+            Event::Toggle { id, state: true } => events.push((tick, vec![0x90, id, VELOCITY])),
+            Event::Toggle { id, state: false } => events.push((tick, vec![0x80, id, 0])),
+            Event::Beat { id, t: dt } => {
+                events.push((tick, vec![0x90, id, VELOCITY]));
+                events.push((tempo.tick(*t + dt).max(tick), vec![0x80, id, 0]));
+            }
+            Event::Mod { id, fr } => {
+                let v = (fr.clamp(0.0, 1.0) * 16383.0).round() as u16;
+                events.push((tick, vec![0xE0 | (id & 0xF), (v & 0x7F) as u8, (v >> 7) as u8]));
+            }
+            Event::Region { id } | Event::Marker { id } => {
+                let label = demo.labels.get(id as usize).map(String::as_str).unwrap_or("");
+                let mut meta = vec![0xFF, 0x06];
+                write_vlq(&mut meta, label.len() as u64);
+                meta.extend_from_slice(label.as_bytes());
+                conductor.push((tick, meta));
+            }
+        }
+    }
+
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(b"MThd");
+    bytes.extend_from_slice(&6u32.to_be_bytes());
+    bytes.extend_from_slice(&1u16.to_be_bytes());
+    bytes.extend_from_slice(&2u16.to_be_bytes());
+    bytes.extend_from_slice(&PPQN.to_be_bytes());
+    write_track(&mut bytes, conductor);
+    write_track(&mut bytes, events);
+
+    std::fs::write(file, bytes)?;
+    Ok(())
+}
+
+fn write_track(bytes: &mut Vec<u8>, mut events: Vec<(u64, Vec<u8>)>) {
+    // Stable sort, so events on the same tick stay in the order they were
+    // added: a note ending as the next one starts is switched off first, but
+    // a zero length note is still switched on before its own off
+    events.sort_by_key(|(tick, _)| *tick);
+
+    let mut track = Vec::new();
+    let mut last = 0;
+    for (tick, ev) in events {
+        write_vlq(&mut track, tick - last);
+        track.extend_from_slice(&ev);
+        last = tick;
+    }
+
+    // End of track
+    track.extend_from_slice(&[0x00, 0xFF, 0x2F, 0x00]);
+
+    bytes.extend_from_slice(b"MTrk");
+    bytes.extend_from_slice(&(track.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(&track);
+}
+
+fn write_vlq(bytes: &mut Vec<u8>, mut v: u64) {
+    let mut buf = [0u8; 10];
+    let mut i = buf.len() - 1;
+    buf[i] = (v & 0x7F) as u8;
+    v >>= 7;
+    while v > 0 {
+        i -= 1;
+        buf[i] = (v & 0x7F) as u8 | 0x80;
+        v >>= 7;
+    }
+    bytes.extend_from_slice(&buf[i..]);
+}
+
+/// Converts song time to MIDI ticks.
+///
+/// MIDI can only change tempo in steps, so ramps are written as a jump to
+/// the tempo at the start of the ramp.
+struct TempoMap {
+    steps: Vec<(f32, Tempo)>,
+}
+
+impl TempoMap {
+    fn new(tempo: &[(f32, Tempo)]) -> Self {
+        let mut steps = tempo.to_vec();
+        if steps.first().map(|(t, _)| *t > 0.0).unwrap_or(true) {
+            steps.insert(0, (0.0, Tempo { bpm: 120.0, ramp: false }));
+        }
+        Self { steps }
+    }
+
+    fn tick(&self, t: f32) -> u64 {
+        let ticks_per_s = |bpm: f32| PPQN as f64 * bpm as f64 / 60.0;
+
+        let mut ticks = 0.0;
+        for (i, (t0, Tempo { bpm, .. })) in self.steps.iter().enumerate() {
+            if t <= *t0 {
+                break;
+            }
+            let t1 = self.steps.get(i + 1).map(|(t1, _)| t.min(*t1)).unwrap_or(t);
+            ticks += (t1 - t0) as f64 * ticks_per_s(*bpm);
+        }
+
+        ticks.round() as u64
+    }
 }
 
 // impl Midi {
@@ -0,0 +1,71 @@
+use anyhow::Result;
+
+use super::midi::{parse_events, write_events};
+use super::{Demo, Event, Metadata, Tempo};
+
+fn demo(events: Vec<(f32, Event)>) -> Demo {
+    Demo {
+        meta: Metadata {
+            sample_rate: 48000,
+            peak_rms: 1.0,
+        },
+        vorbis: vec![],
+        events,
+        data: vec![],
+        tempo: vec![(0.0, Tempo { bpm: 120.0, ramp: false })],
+        labels: vec![],
+    }
+}
+
+/// Ticks aren't exact in `f32` seconds, so compare to the millisecond.
+fn rounded(events: &[(f32, Event)]) -> Vec<(i32, String)> {
+    let ms = |t: f32| (t * 1000.0).round() as i32;
+    events
+        .iter()
+        .map(|(t, ev)| match *ev {
+            Event::Beat { id, t: dt } => (ms(*t), format!("Beat {} {}", id, ms(dt))),
+            ev => (ms(*t), format!("{:?}", ev)),
+        })
+        .collect()
+}
+
+fn round_trip(name: &str, demo: &Demo) -> Result<Vec<(i32, String)>> {
+    let file = std::env::temp_dir().join(format!("{}_{}.mid", name, std::process::id()));
+    let file = file.to_str().unwrap();
+    write_events(file, demo)?;
+    let events = parse_events(file);
+    std::fs::remove_file(file)?;
+
+    Ok(rounded(&events?.0))
+}
+
+#[test]
+fn test_round_trip() -> Result<()> {
+    let events = vec![
+        (0.5, Event::Trigger { id: 5 }),
+        (1.0, Event::Toggle { id: 40, state: true }),
+        (1.0, Event::Beat { id: 64, t: 0.5 }),
+        (1.5, Event::Beat { id: 64, t: 0.25 }),
+        (2.0, Event::Toggle { id: 40, state: false }),
+    ];
+
+    let expected = rounded(&events);
+    assert_eq!(round_trip("test_round_trip", &demo(events))?, expected);
+
+    Ok(())
+}
+
+#[test]
+fn test_zero_length_beat() -> Result<()> {
+    // Each starts on the tick the one before ends
+    let events = vec![
+        (1.0, Event::Beat { id: 64, t: 0.5 }),
+        (1.5, Event::Beat { id: 64, t: 0.0 }),
+        (1.5, Event::Beat { id: 64, t: 0.5 }),
+    ];
+
+    let expected = rounded(&events);
+    assert_eq!(round_trip("test_zero_length_beat", &demo(events))?, expected);
+
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use lib::prelude::*;
 use parking_lot::{Condvar, Mutex};
 use smallvec::SmallVec;
@@ -9,26 +9,44 @@ use std::{
 };
 
 mod format;
-pub use format::{Data, Demo, Event, Metadata};
+pub use format::{Data, Demo, Event, Metadata, Tempo, BANDS};
 
 mod stage;
-pub use stage::{Stage, Stages};
+pub use stage::{Stage, StageFn, Stages};
+
+mod info;
+pub use info::StageInfo;
 
 mod audio;
 use audio::Stream;
 
+use crate::util::Clocks;
+
 mod midi;
+mod reaper;
+
+mod names;
+pub use names::{Id, Names};
 
 #[cfg(test)]
 mod audio_test;
+#[cfg(test)]
+mod midi_test;
+#[cfg(test)]
+mod reaper_test;
 
 pub struct Player {
     stages: Option<Stages>,
+    stage: &'static str,
     stream: Arc<Stream>,
 
     playing: bool,
+    started: bool,
     t: f32,
     rms: f32,
+    bands: [f32; BANDS],
+    sliders: HashMap<u8, f32>,
+    clocks: Clocks,
     next_stage: Option<&'static str>,
 
     meta: Metadata,
@@ -36,6 +54,9 @@ pub struct Player {
     events_i: usize,
     data: Vec<(f32, Data)>,
     data_i: usize,
+    tempo: Vec<(f32, Tempo)>,
+    labels: Vec<String>,
+    names: Names,
 }
 
 impl Player {
@@ -43,24 +64,44 @@ impl Player {
         file: &str,
         t0: f32,
         stage0: &'static str,
-        stages: HashMap<&'static str, Box<dyn Stage + Send>>,
+        stages: Stages,
     ) -> Result<Self> {
         let Demo {
             meta,
             vorbis,
             events,
             data,
-        } = Demo::load_bytes(&lib::resource::read(file))?;
+            tempo,
+            labels,
+        } = Demo::load_bytes(&lib::resource::read(file)).with_context(|| format!("failed to load {}", file))?;
 
         let stream = Arc::new(Stream::new(meta, vorbis, t0)?);
 
+        // Event names live next to the demo, so they can change without a rebuild.
+        // Stages that handle named events can't play without them
+        let names_file = file.replace(".dem", ".names");
+        let named: Vec<&str> = stages
+            .planned()
+            .filter(|stage| stages.info(stage).map_or(false, |info| !info.named.is_empty()))
+            .collect();
+        let names = match named.is_empty() {
+            true => Names::default(),
+            false => Names::load_bytes(&lib::resource::read(&names_file))
+                .with_context(|| format!("failed to load {}, needed by {}", names_file, named.join(", ")))?,
+        };
+
         Ok(Self {
-            stages: Some(Stages::new(stage0, stages)),
+            stages: Some(stages),
+            stage: stage0,
             stream,
 
             playing: false,
+            started: false,
             t: t0,
             rms: 0.0,
+            bands: [0.0; BANDS],
+            sliders: HashMap::new(),
+            clocks: Clocks::default(),
             next_stage: None,
 
             meta,
@@ -68,6 +109,9 @@ impl Player {
             events_i: 0,
             data,
             data_i: 0,
+            tempo,
+            labels,
+            names,
         })
     }
 
@@ -75,9 +119,9 @@ impl Player {
         self.stages = Some(self.stages.take().unwrap().key(self, state, key).await);
     }
 
-    pub async fn update(&mut self, dt: f32) {
+    pub async fn update(&mut self, app: &App, dt: f32) {
         if let Some(next) = self.next_stage.take() {
-            self.stages = Some(self.stages.take().unwrap().go(self, next).await);
+            self.enter(app, next).await;
         }
 
         let t = self.stream.t();
@@ -87,6 +131,7 @@ impl Player {
             // Update data stream
             while self.data_i < self.data.len() && self.data[self.data_i].0 <= t {
                 self.rms = self.data[self.data_i].1.rms;
+                self.bands = self.data[self.data_i].1.bands;
                 self.data_i += 1;
             }
 
@@ -102,29 +147,77 @@ impl Player {
         // Dispatch events
         for (et, ev) in events.into_iter() {
             log::debug!("{:?} et={}, t={}, self.t={}, delta={}", ev, et, t, self.t, self.t - t);
+
+            if let Event::Mod { id, fr } = ev {
+                self.sliders.insert(id, fr);
+            }
+
+            // Regions from the project timeline switch to the stage of the same name
+            if let Event::Region { id } = ev {
+                let label = self.label(id);
+                match self.stages.as_ref().unwrap().find(label) {
+                    Some(stage) => self.next_stage = Some(stage),
+                    None => log::warn!("No stage for region '{}' at t={}", label, et),
+                }
+            }
+
             self.stages = Some(self.stages.take().unwrap().event(self, ev).await);
 
             if let Some(next) = self.next_stage.take() {
-                self.stages = Some(self.stages.take().unwrap().go(self, next).await);
+                self.enter(app, next).await;
             }
         }
 
-        self.stages = Some(self.stages.take().unwrap().update(self, dt).await);
+        self.clocks.update(dt, self.playing, t, self.rms, self.bpm());
+
+        // Paused stages keep drawing, but time stands still
+        let dt = if self.paused() { 0.0 } else { dt };
+        self.stages = Some(self.stages.take().unwrap().update(app, self, dt).await);
     }
 
     pub fn view(&mut self, frame: &mut Frame, view: &wgpu::RawTextureView) {
         self.stages = Some(self.stages.take().unwrap().view(frame, view));
     }
 
-    pub fn play(&mut self) {
+    pub async fn play(&mut self) {
         if !self.playing {
             self.playing = true;
             self.stream.play();
+
+            if self.started {
+                self.stages = Some(self.stages.take().unwrap().resume(self).await);
+            }
+            self.started = true;
+        }
+    }
+
+    /// Pause if playing, or start/resume if not.
+    pub async fn toggle(&mut self) {
+        if self.playing {
+            self.pause().await;
+        } else {
+            self.play().await;
+        }
+    }
+
+    /// Whether playback has started and is currently paused.
+    pub fn paused(&self) -> bool {
+        self.started && !self.playing
+    }
+
+    pub async fn pause(&mut self) {
+        if self.playing {
+            self.playing = false;
+            self.stream.pause();
+            self.stages = Some(self.stages.take().unwrap().pause(self).await);
         }
     }
 
     pub async fn trigger(&mut self, ev: Event) {
         // log::debug!("Trigger: {:?} t={}", ev, self.t);
+        if let Event::Mod { id, fr } = ev {
+            self.sliders.insert(id, fr);
+        }
         self.stages = Some(self.stages.take().unwrap().event(self, ev).await);
     }
 
@@ -132,6 +225,35 @@ impl Player {
         self.next_stage = Some(to);
     }
 
+    /// Move on to the next stage in the setlist, e.g. after a stage crashed.
+    pub async fn next(&mut self) {
+        match self.stages.as_ref().unwrap().next() {
+            Some(next) => self.next_stage = Some(next),
+            None => log::warn!("No stage after '{}' in the setlist", self.stage),
+        }
+    }
+
+    /// Reload the pipelines of the loaded stages using any of the `changed` shaders.
+    pub async fn rebuild(&mut self, app: &App, changed: &[String]) {
+        self.stages = Some(self.stages.take().unwrap().rebuild(app, self, changed).await);
+    }
+
+    async fn enter(&mut self, app: &App, to: &'static str) {
+        self.stage = to;
+        self.stages = Some(self.stages.take().unwrap().go(app, self, to).await);
+        self.stage = self.stages.as_ref().unwrap().name();
+    }
+
+    /// The name of the stage currently playing.
+    pub fn stage(&self) -> &'static str {
+        self.stage
+    }
+
+    /// The name the current stage gives to an event, from the demo's `.names` file.
+    pub fn name(&self, ev: &Event) -> Option<&str> {
+        self.names.get(self.stage, ev)
+    }
+
     pub fn events<'a>(
         &'a self,
         time_range: impl RangeBounds<f32> + 'a,
@@ -155,4 +277,44 @@ impl Player {
     pub fn rms(&self) -> f32 {
         self.rms
     }
+
+    /// RMS of one of the frequency bands, from 0 (lowest) to `BANDS - 1`.
+    pub fn band(&self, band: usize) -> f32 {
+        self.bands.get(band).copied().unwrap_or(0.0)
+    }
+
+    /// The last value of a Mod slider, 0 until it's moved.
+    pub fn slider(&self, id: u8) -> f32 {
+        self.sliders.get(&id).copied().unwrap_or(0.0)
+    }
+
+    /// The time of one of the player's clocks, see `Clocks`.
+    pub fn clock(&self, name: &str) -> f32 {
+        self.clocks.t(name)
+    }
+
+    /// How fast one of the player's clocks is running.
+    pub fn rate(&self, name: &str) -> f32 {
+        self.clocks.rate(name)
+    }
+
+    /// The tempo at the current song time, in beats per minute.
+    pub fn bpm(&self) -> f32 {
+        let t = self.t();
+        let i = self.tempo.partition_point(|(t0, _)| *t0 <= t);
+
+        match (i.checked_sub(1).map(|i| self.tempo[i]), self.tempo.get(i)) {
+            (None, _) => 120.0,
+            (Some((t0, Tempo { bpm: bpm0, ramp: true })), Some((t1, Tempo { bpm: bpm1, .. }))) => {
+                let fr = (t - t0) / (t1 - t0);
+                bpm0 + (bpm1 - bpm0) * fr
+            }
+            (Some((_, Tempo { bpm, .. })), _) => bpm,
+        }
+    }
+
+    /// The name of a `Region` or `Marker` event.
+    pub fn label(&self, id: u8) -> &str {
+        self.labels.get(id as usize).map(String::as_str).unwrap_or("")
+    }
 }
@@ -0,0 +1,101 @@
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+
+use super::Event;
+
+/// Stage section that applies to every stage, unless the stage overrides the id.
+const ALL: &str = "*";
+
+/// What an event is named by: its note, or its Mod slider.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Id {
+    Note(u8),
+    Mod(u8),
+}
+
+impl Id {
+    pub fn of(ev: &Event) -> Option<Self> {
+        match *ev {
+            Event::Trigger { id } | Event::Beat { id, .. } | Event::Toggle { id, .. } => Some(Id::Note(id)),
+            Event::Mod { id, .. } => Some(Id::Mod(id)),
+            _ => None,
+        }
+    }
+}
+
+/// Per-stage names for event ids, e.g. `72 = kick`.
+///
+/// ```text
+/// [*]
+/// 10 = next_stage
+///
+/// [funky_beat]
+/// 60 = kick
+/// 62 = hat
+/// mod 1 = speed
+/// ```
+#[derive(Default)]
+pub struct Names {
+    stages: HashMap<String, HashMap<Id, String>>,
+}
+
+impl Names {
+    pub fn load(file: &str) -> Result<Self> {
+        let src = std::fs::read_to_string(file).with_context(|| format!("failed to read {}", file))?;
+        Self::parse(&src).with_context(|| format!("failed to parse {}", file))
+    }
+
+    pub fn load_bytes(bytes: &[u8]) -> Result<Self> {
+        Self::parse(std::str::from_utf8(bytes)?)
+    }
+
+    pub fn parse(src: &str) -> Result<Self> {
+        let mut stages: HashMap<String, HashMap<Id, String>> = HashMap::new();
+        let mut stage = ALL.to_owned();
+
+        for (i, line) in src.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                stage = name.trim().to_owned();
+                continue;
+            }
+
+            let (key, name) = match line.split_once('=') {
+                Some((key, name)) => (key.trim(), name.trim()),
+                None => bail!("line {}: expected `<id> = <name>`", i + 1),
+            };
+
+            let key = match key.strip_prefix("mod") {
+                Some(id) => Id::Mod(id.trim().parse().with_context(|| format!("line {}: bad mod id", i + 1))?),
+                None => Id::Note(key.parse().with_context(|| format!("line {}: bad note id", i + 1))?),
+            };
+
+            stages.entry(stage.clone()).or_default().insert(key, name.to_owned());
+        }
+
+        Ok(Self { stages })
+    }
+
+    /// The name of an event within a stage, if it has one.
+    pub fn get(&self, stage: &str, ev: &Event) -> Option<&str> {
+        let key = Id::of(ev)?;
+
+        [stage, ALL]
+            .iter()
+            .filter_map(|stage| self.stages.get(*stage))
+            .find_map(|names| names.get(&key).map(String::as_str))
+    }
+
+    /// Every named id within a stage, including the ones from `[*]`.
+    pub fn ids(&self, stage: &str) -> HashMap<Id, &str> {
+        [ALL, stage]
+            .iter()
+            .filter_map(|stage| self.stages.get(*stage))
+            .flat_map(|names| names.iter().map(|(id, name)| (*id, name.as_str())))
+            .collect()
+    }
+}
@@ -0,0 +1,143 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+
+use super::format::{Event, Tempo};
+
+/// Events, labels and tempo map read from a Reaper `.RPP` project.
+#[derive(Debug, Default)]
+pub struct Project {
+    pub events: Vec<(f32, Event)>,
+    pub tempo: Vec<(f32, Tempo)>,
+    pub labels: Vec<String>,
+}
+
+/// Parse the markers, regions and tempo envelope of a Reaper project.
+///
+/// Region starts become `Event::Region`, markers become `Event::Marker`,
+/// and both refer to their name by index into `labels`.
+pub fn parse(rpp: &str) -> Result<Project> {
+    let mut project = Project::default();
+
+    // Reaper numbers markers and regions separately, and writes each
+    // region as two MARKER lines: one for the start and one for the end.
+    let mut regions_open = HashMap::new();
+
+    let mut depth = 0;
+    let mut block = Vec::new();
+    let mut initial_bpm = None;
+
+    for (i, line) in rpp.lines().enumerate() {
+        let line = line.trim();
+        let ctx = || format!("malformed RPP line {}: {}", i + 1, line);
+
+        if let Some(name) = line.strip_prefix('<') {
+            depth += 1;
+            block.push(name.split_whitespace().next().unwrap_or("").to_owned());
+            continue;
+        }
+        if line == ">" {
+            depth -= 1;
+            block.pop();
+            continue;
+        }
+
+        let tokens = tokenize(line);
+        let in_tempo_env = block.last().map(String::as_str) == Some("TEMPOENVEX");
+
+        match tokens.first().map(String::as_str) {
+            // TEMPO <bpm> <num> <denom>
+            Some("TEMPO") if depth == 1 => {
+                initial_bpm = Some(tokens.get(1).with_context(ctx)?.parse::<f32>().with_context(ctx)?);
+            }
+            // PT <t> <bpm> <shape> ...
+            Some("PT") if in_tempo_env => {
+                let t = tokens.get(1).with_context(ctx)?.parse::<f32>().with_context(ctx)?;
+                let bpm = tokens.get(2).with_context(ctx)?.parse::<f32>().with_context(ctx)?;
+                let shape = tokens.get(3).map(|s| s.parse::<u32>().unwrap_or(1)).unwrap_or(1);
+
+                project.tempo.push((t, Tempo { bpm, ramp: shape == 0 }));
+            }
+            // MARKER <idx> <t> <name> <flags> ...
+            Some("MARKER") if depth == 1 => {
+                let idx = tokens.get(1).with_context(ctx)?.parse::<u32>().with_context(ctx)?;
+                let t = tokens.get(2).with_context(ctx)?.parse::<f32>().with_context(ctx)?;
+                let name = tokens.get(3).cloned().unwrap_or_default();
+                let flags = tokens.get(4).map(|s| s.parse::<u32>().unwrap_or(0)).unwrap_or(0);
+                let is_region = flags & 1 != 0;
+
+                if is_region && regions_open.remove(&idx).is_some() {
+                    // Region end, the next region starting takes over the stage
+                    continue;
+                }
+
+                let id = match u8::try_from(project.labels.len()) {
+                    Ok(id) => id,
+                    Err(_) => {
+                        log::warn!("Too many markers, skipping '{}' at t={}", name, t);
+                        continue;
+                    }
+                };
+                project.labels.push(name);
+
+                if is_region {
+                    regions_open.insert(idx, id);
+                    project.events.push((t, Event::Region { id }));
+                } else {
+                    project.events.push((t, Event::Marker { id }));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Projects without a tempo envelope only have their initial tempo
+    if project.tempo.is_empty() {
+        if let Some(bpm) = initial_bpm {
+            project.tempo.push((0.0, Tempo { bpm, ramp: false }));
+        }
+    }
+
+    project.events.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    project.tempo.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+    Ok(project)
+}
+
+/// Split an RPP line into whitespace separated tokens.
+///
+/// Tokens containing whitespace are wrapped in `"`, `'` or `` ` ``, whichever
+/// doesn't appear in the token itself.
+fn tokenize(line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut chars = line.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let mut token = String::new();
+        if c == '"' || c == '\'' || c == '`' {
+            let quote = c;
+            chars.next();
+            for c in chars.by_ref() {
+                if c == quote {
+                    break;
+                }
+                token.push(c);
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                token.push(c);
+                chars.next();
+            }
+        }
+        tokens.push(token);
+    }
+
+    tokens
+}
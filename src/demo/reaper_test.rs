@@ -0,0 +1,63 @@
+use anyhow::Result;
+
+use super::reaper::parse;
+use super::Event;
+
+const RPP: &str = r#"<REAPER_PROJECT 0.1 "6.58/linux-x86_64" 1655603238
+  TEMPO 166 4 4
+  <TEMPOENVEX
+    ACT 1 -1
+    PT 0.000000000000 168.0000000000 1
+    PT 137.142857142612 174.0000000000 0
+    PT 225.418719211767 190.0000000000 1
+  >
+  MARKER 3 0 "lobby" 1 0 1 B {BBC0FA0E-2B65-8E8B-E428-369755C88CD5}
+  MARKER 3 225.41871921165557 "" 1 0 1 B
+  MARKER 1 100.5 "drop it" 0 0 1 B {A71F0DA7-58CD-204D-EA28-27FD798C0BCB}
+  MARKER 1 225.41871921165924 metalheart 1 0 1 B
+  MARKER 1 286.05029815917624 "" 1 0 1 B
+  <TRACK {AE9A0165-427E-2442-CBA1-7C83A9568BEB}
+    NAME "Track 1"
+  >
+>
+"#;
+
+#[test]
+fn test_regions_and_markers() -> Result<()> {
+    let project = parse(RPP)?;
+
+    assert_eq!(project.labels, vec!["lobby", "drop it", "metalheart"]);
+
+    let events: Vec<_> = project.events.iter().map(|(t, ev)| (*t, format!("{:?}", ev))).collect();
+    assert_eq!(events, vec![
+        (0.0, format!("{:?}", Event::Region { id: 0 })),
+        (100.5, format!("{:?}", Event::Marker { id: 1 })),
+        (225.41871921165924, format!("{:?}", Event::Region { id: 2 })),
+    ]);
+
+    Ok(())
+}
+
+#[test]
+fn test_tempo_envelope() -> Result<()> {
+    let project = parse(RPP)?;
+
+    let tempo: Vec<_> = project.tempo.iter().map(|(t, tempo)| (*t, tempo.bpm, tempo.ramp)).collect();
+    assert_eq!(tempo, vec![
+        (0.0, 168.0, false),
+        (137.142857142612, 174.0, true),
+        (225.418719211767, 190.0, false),
+    ]);
+
+    Ok(())
+}
+
+#[test]
+fn test_initial_tempo() -> Result<()> {
+    let project = parse("<REAPER_PROJECT\n  TEMPO 140 4 4\n>\n")?;
+
+    assert_eq!(project.tempo.len(), 1);
+    assert_eq!(project.tempo[0].1.bpm, 140.0);
+
+    Ok(())
+}
@@ -1,9 +1,17 @@
-use std::collections::HashMap;
+use std::any::Any;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::thread;
 
 use async_trait::async_trait;
 use lib::prelude::*;
 
-use super::{Event, Player};
+use super::{Event, Player, StageInfo};
+use crate::pipeline::{record_shaders, shader_named, FxPresets, LoadingPass, Targets};
+use crate::util::{CounterEnv, Routes};
 
 #[async_trait]
 pub trait Stage {
@@ -11,60 +19,563 @@ pub trait Stage {
     async fn update(&mut self, p: &mut Player, dt: f32);
 
     async fn event(&mut self, p: &mut Player, ev: Event);
+    /// Called after `event` for events the stage has a name for.
+    async fn named(&mut self, p: &mut Player, name: &str, ev: Event) {}
+
     async fn key(&mut self, p: &mut Player, state: KeyState, key: Key);
 
+    /// Routes from the stage's `.cfg`, applied to its envs before `event`
+    /// and checked for changes before `update`.
+    fn routes(&mut self) -> Option<(&mut Routes, &mut DecayEnv, &mut CounterEnv)> {
+        None
+    }
+
     fn view(&mut self, frame: &mut Frame, target: &wgpu::RawTextureView);
+
+    /// Called on the outgoing stage by `Stages::go`, before the next stage's `init`.
+    async fn leave(&mut self, p: &mut Player) {}
+
+    /// Called when playback pauses and resumes.
+    async fn pause(&mut self, p: &mut Player) {}
+    async fn resume(&mut self, p: &mut Player) {}
+
+    /// Called before `init` when a loaded stage is entered again, to return to a clean state.
+    async fn reset(&mut self, p: &mut Player) {}
+
+    /// Called when shaders are recompiled, to rebuild the pipelines of the
+    /// passes using any of the `changed` shaders in place. Passes without a
+    /// `reload` of their own, like `lib`'s, pick up the change the next time
+    /// the stage is built.
+    fn reload(&mut self, device: &wgpu::Device, changed: &[String]) {}
+}
+
+/// Builds a stage, with the render targets and fx presets stages share.
+pub type StageFn = dyn Fn(&App, &Targets, &FxPresets) -> Box<dyn Stage + Send>;
+
+enum Slot {
+    Unloaded,
+    Loaded(Box<dyn Stage + Send>),
+    /// Failed to build, or panicked while running. Rebuilt if entered again.
+    Crashed,
 }
 
+/// The registered stages, built on demand.
+///
+/// Entering a stage that isn't loaded shows the loading screen until it's
+/// ready, and the stage after it in the setlist is preloaded on the first
+/// update after the entered stage has drawn a frame. Stages are unloaded when
+/// they're left, and rebuilt if they're revisited.
+///
+/// Stages are built on the main thread, since they need the `App`, so
+/// building one holds up that frame. Preloading waits for the entered stage
+/// to be on screen, so the hitch doesn't add to the wait between the loading
+/// screen and its first frame. Events that arrive while the current stage is
+/// loading are queued, and passed on once it's entered.
+///
+/// A stage that panics is isolated so the show can go on: playback moves to
+/// the fallback stage if there is one, otherwise the stage's last frame is
+/// held until the operator moves on.
 pub struct Stages {
     current: &'static str,
-    scenes: HashMap<&'static str, Box<dyn Stage + Send>>,
-    first_init: bool,
+    entered: bool,
+    visited: HashSet<&'static str>,
+
+    /// Set when the current stage panicked, and only its `view` still runs
+    frozen: bool,
+    /// Set when the current stage panicked in `view`, and the loading screen is shown instead
+    blank: bool,
+    /// A panic in `view`, handled like any other crash on the next `update`
+    view_panic: Option<Box<dyn Any + Send>>,
+    fallback: Option<&'static str>,
+
+    factories: HashMap<&'static str, Box<StageFn>>,
+    infos: HashMap<&'static str, StageInfo>,
+    slots: HashMap<&'static str, Slot>,
+    /// The shaders each stage named the last time it was built
+    shaders: HashMap<&'static str, HashSet<String>>,
+    setlist: Vec<&'static str>,
+    queued: VecDeque<Event>,
+    /// Intermediate targets shared by every stage's `Graph`
+    targets: Targets,
+    /// Shared by every stage's `FxPass`, and reloaded once a frame
+    presets: FxPresets,
+
+    loading: LoadingPass,
+    loading_t: f32,
+    /// Whether the loading screen has been drawn since the current stage was
+    /// chosen, so building it won't leave the previous frame up
+    loading_shown: bool,
+    /// Whether a frame has been drawn since the current stage was entered
+    shown: bool,
+    /// Set when a stage is entered, until the stage after it is preloaded
+    preload_pending: bool,
 }
 
 impl Stages {
-    pub fn new(
-        initial: &'static str,
-        scenes: HashMap<&'static str, Box<dyn Stage + Send>>,
-    ) -> Self {
+    pub fn new(app: &App, initial: &'static str) -> Self {
         Self {
             current: initial,
-            scenes,
-            first_init: false,
+            entered: false,
+            visited: HashSet::new(),
+
+            frozen: false,
+            blank: false,
+            view_panic: None,
+            fallback: None,
+
+            factories: HashMap::new(),
+            infos: HashMap::new(),
+            slots: HashMap::new(),
+            shaders: HashMap::new(),
+            setlist: vec![],
+            queued: VecDeque::new(),
+            targets: Targets::new(),
+            presets: FxPresets::load(),
+
+            loading: LoadingPass::new(&app.device),
+            loading_t: 0.0,
+            loading_shown: false,
+            shown: false,
+            preload_pending: false,
+        }
+    }
+
+    pub fn with<F>(mut self, name: &'static str, stage: F) -> Self
+    where
+        F: Fn(&App, &Targets, &FxPresets) -> Box<dyn Stage + Send> + 'static,
+    {
+        self.factories.insert(name, Box::new(stage));
+        self.slots.insert(name, Slot::Unloaded);
+        self
+    }
+
+    /// The order stages play in, used to decide what to preload.
+    pub fn setlist(mut self, setlist: &[&'static str]) -> Self {
+        self.setlist = setlist.to_vec();
+        self
+    }
+
+    /// What each stage declares about itself, see `StageInfo`.
+    pub fn declare(mut self, infos: Vec<(&'static str, StageInfo)>) -> Self {
+        self.infos.extend(infos);
+        self
+    }
+
+    /// The stage to go to when the current stage panics.
+    ///
+    /// Without one, the crashed stage's last frame is held instead.
+    pub fn fallback(mut self, stage: &'static str) -> Self {
+        self.fallback = Some(stage);
+        self
+    }
+
+    /// The name of the current stage.
+    pub fn name(&self) -> &'static str {
+        self.current
+    }
+
+    /// Look up the registered name of a stage.
+    pub fn find(&self, name: &str) -> Option<&'static str> {
+        self.factories.keys().find(|k| **k == name).copied()
+    }
+
+    /// Every registered stage.
+    pub fn registered(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.factories.keys().copied()
+    }
+
+    /// What a stage declares about itself.
+    pub fn info(&self, name: &str) -> Option<&StageInfo> {
+        self.infos.get(name)
+    }
+
+    /// Every stage with a declaration, registered or not.
+    pub fn declared(&self) -> impl Iterator<Item = (&'static str, &StageInfo)> + '_ {
+        self.infos.iter().map(|(name, info)| (*name, info))
+    }
+
+    /// Every stage the show expects to play: the setlist and fallback.
+    pub fn planned(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.setlist.iter().copied().chain(self.fallback)
+    }
+
+    /// Build a stage, if it isn't already.
+    pub fn preload(&mut self, app: &App, name: &'static str) {
+        let slot = match self.slots.get_mut(name) {
+            Some(slot) => slot,
+            None => {
+                log::error!("No such stage '{}'", name);
+                return;
+            }
+        };
+
+        if let Slot::Unloaded = slot {
+            log::info!("Loading stage '{}'", name);
+
+            let build = &self.factories[name];
+            let (targets, presets) = (&self.targets, &self.presets);
+            let (built, shaders) = record_shaders(|| panic::catch_unwind(AssertUnwindSafe(|| build(app, targets, presets))));
+            self.shaders.insert(name, shaders);
+            *slot = match built {
+                Ok(stage) => {
+                    log::info!("Loaded stage '{}'", name);
+                    Slot::Loaded(stage)
+                }
+                Err(e) => {
+                    log::error!("Stage '{}' panicked while loading: {}", name, message(&e));
+                    Slot::Crashed
+                }
+            };
         }
     }
 
-    fn current(&mut self) -> &mut Box<dyn Stage + Send> {
-        self.scenes.get_mut(self.current).unwrap()
+    /// Drop a stage's resources. It's rebuilt if it's entered again.
+    pub fn unload(&mut self, name: &'static str) {
+        if let Some(slot) = self.slots.get_mut(name) {
+            if let Slot::Loaded(_) = slot {
+                log::info!("Unloading stage '{}'", name);
+                *slot = Slot::Unloaded;
+                self.visited.remove(name);
+            }
+        }
     }
 
-    pub async fn go(mut self, p: &mut Player, to: &'static str) -> Self {
+    /// The stage after the current one in the setlist.
+    pub fn next(&self) -> Option<&'static str> {
+        self.setlist
+            .iter()
+            .position(|s| *s == self.current)
+            .and_then(|i| self.setlist.get(i + 1))
+            .copied()
+    }
+
+    fn preload_next(&mut self, app: &App) {
+        if let Some(next) = self.next() {
+            self.preload(app, next);
+        }
+    }
+
+    /// The current stage, if it's loaded, entered, and hasn't crashed.
+    fn current(&mut self) -> Option<&mut Box<dyn Stage + Send>> {
+        if !self.entered || self.frozen {
+            return None;
+        }
+
+        match self.slots.get_mut(self.current) {
+            Some(Slot::Loaded(stage)) => Some(stage),
+            _ => None,
+        }
+    }
+
+    /// Isolate the current stage after it panicked.
+    async fn crash(&mut self, p: &mut Player, during: &str, e: Box<dyn Any + Send>) {
+        log::error!("Stage '{}' panicked in {}: {}", self.current, during, message(&e));
+        self.frozen = true;
+
+        match self.fallback {
+            Some(fallback) if fallback != self.current => {
+                log::warn!("Falling back to stage '{}'", fallback);
+                p.go(fallback).await;
+            }
+            _ if self.blank => log::warn!("Showing the loading screen in place of '{}'", self.current),
+            _ => log::warn!("Holding the last frame of '{}'", self.current),
+        }
+    }
+
+    pub async fn go(mut self, app: &App, p: &mut Player, to: &'static str) -> Self {
+        if !self.factories.contains_key(to) {
+            log::error!("No such stage '{}', staying on '{}'", to, self.current);
+            return self;
+        }
+
+        if let Some(stage) = self.current() {
+            if let Err(e) = CatchUnwind(stage.leave(p)).await {
+                log::error!("Stage '{}' panicked in leave: {}", self.current, message(&e));
+            }
+        }
+
+        // A stage that panicked can't be trusted to be in a sane state
+        if self.frozen || self.blank {
+            self.slots.insert(self.current, Slot::Crashed);
+            self.visited.remove(self.current);
+        }
+
         self.current = to;
-        self.current().init(p).await;
+        self.entered = false;
+        self.frozen = false;
+        self.blank = false;
+        self.view_panic = None;
+        self.loading_shown = false;
+        self.shown = false;
+
+        // Anything queued was for the stage being left
+        self.queued.clear();
+
+        // Going to a crashed stage is a request to try it again
+        if let Some(Slot::Crashed) = self.slots.get(to) {
+            self.slots.insert(to, Slot::Unloaded);
+        }
+
+        // Keep only the new stage and the one after it
+        let keep = [Some(to), self.next()];
+        let finished: Vec<_> = self
+            .slots
+            .iter()
+            .filter(|(name, slot)| matches!(slot, Slot::Loaded(_)) && !keep.contains(&Some(**name)))
+            .map(|(name, _)| *name)
+            .collect();
+        for name in finished {
+            self.unload(name);
+        }
+
+        self.enter(app, p).await;
         self
     }
 
-    pub async fn update(mut self, p: &mut Player, dt: f32) -> Self {
-        if !self.first_init {
-            self.current().init(p).await;
-            self.first_init = true;
+    /// Reload the pipelines of the loaded stages built with any of the
+    /// `changed` shaders, leaving them where they are. A shader no stage named
+    /// may be loaded inside any pass, so then every loaded stage is reloaded.
+    ///
+    /// Stages that crashed are built again from scratch when they're next
+    /// entered, and the current one is tried again straight away, in case
+    /// the shader was what broke it.
+    pub async fn rebuild(mut self, app: &App, p: &mut Player, changed: &[String]) -> Self {
+        let retry = self.frozen || self.blank || matches!(self.slots.get(self.current), Some(Slot::Crashed));
+        if retry {
+            self.slots.insert(self.current, Slot::Crashed);
+        }
+
+        let everything = changed.iter().any(|name| !shader_named(name));
+        let mut crashed = vec![];
+        for (name, slot) in self.slots.iter_mut() {
+            let stage = match slot {
+                Slot::Loaded(stage) => stage,
+                Slot::Crashed => {
+                    *slot = Slot::Unloaded;
+                    self.visited.remove(name);
+                    continue;
+                }
+                Slot::Unloaded => continue,
+            };
+            let uses = match self.shaders.get(name) {
+                Some(shaders) => everything || changed.iter().any(|c| shaders.contains(c)),
+                None => true,
+            };
+            if !uses {
+                continue;
+            }
+
+            log::info!("Reloading shaders of stage '{}'", name);
+            if let Err(e) = panic::catch_unwind(AssertUnwindSafe(|| stage.reload(&app.device, changed))) {
+                crashed.push((*name, e));
+            }
+        }
+
+        for (name, e) in crashed {
+            if name == self.current {
+                self.crash(p, "reload", e).await;
+            } else {
+                log::error!("Stage '{}' panicked in reload: {}", name, message(&e));
+                self.slots.insert(name, Slot::Crashed);
+            }
+        }
+        if !retry {
+            return self;
+        }
+
+        self.entered = false;
+        self.frozen = false;
+        self.blank = false;
+        self.view_panic = None;
+        self.loading_shown = false;
+        self.shown = false;
+
+        self.enter(app, p).await;
+        self
+    }
+
+    /// Build and initialize the current stage, once the loading screen is up.
+    async fn enter(&mut self, app: &App, p: &mut Player) {
+        if self.entered {
+            return;
+        }
+        if let Some(Slot::Unloaded) = self.slots.get(self.current) {
+            if !self.loading_shown {
+                return;
+            }
+        }
+        self.preload(app, self.current);
+
+        match self.slots.get_mut(self.current) {
+            Some(Slot::Loaded(stage)) => {
+                let revisit = !self.visited.insert(self.current);
+
+                let result = match revisit {
+                    true => CatchUnwind(stage.reset(p)).await,
+                    false => Ok(()),
+                };
+                let result = match result {
+                    Ok(()) => CatchUnwind(stage.init(p)).await,
+                    Err(e) => Err(e),
+                };
+
+                self.entered = true;
+                match result {
+                    Ok(()) => {
+                        while let Some(ev) = self.queued.pop_front() {
+                            self.dispatch(p, ev).await;
+                        }
+                    }
+                    Err(e) => self.crash(p, "init", e).await,
+                }
+            }
+            Some(Slot::Crashed) if !self.frozen => {
+                self.entered = true;
+                self.frozen = true;
+                self.blank = true;
+                self.crash(p, "new", Box::new("failed to load")).await;
+            }
+            _ => {}
+        }
+
+        self.preload_pending = true;
+    }
+
+    pub async fn update(mut self, app: &App, p: &mut Player, dt: f32) -> Self {
+        if let Some(e) = self.view_panic.take() {
+            self.crash(p, "view", e).await;
+        }
+        self.enter(app, p).await;
+        if self.preload_pending && self.shown {
+            self.preload_pending = false;
+            self.preload_next(app);
+        }
+        self.presets.reload();
+
+        match self.current() {
+            Some(stage) => {
+                if let Some((routes, decay, count)) = stage.routes() {
+                    routes.reload(decay, count);
+                }
+                if let Err(e) = CatchUnwind(stage.update(p, dt)).await {
+                    self.crash(p, "update", e).await;
+                }
+            }
+            None => self.loading_t += dt,
         }
-        self.current().update(p, dt).await;
         self
     }
 
     pub async fn event(mut self, p: &mut Player, ev: Event) -> Self {
-        self.current().event(p, ev).await;
+        self.dispatch(p, ev).await;
         self
     }
 
+    async fn dispatch(&mut self, p: &mut Player, ev: Event) {
+        if !self.entered {
+            self.queued.push_back(ev);
+            return;
+        }
+        let stage = match self.current() {
+            Some(stage) => stage,
+            None => {
+                log::debug!("Dropped {:?} for crashed stage '{}'", ev, self.current);
+                return;
+            }
+        };
+
+        let result = CatchUnwind(Box::pin(async {
+            if let Some((routes, decay, count)) = stage.routes() {
+                routes.apply(ev, decay, count);
+            }
+            stage.event(p, ev).await;
+            if let Some(name) = p.name(&ev).map(str::to_owned) {
+                stage.named(p, &name, ev).await;
+            }
+        }))
+        .await;
+
+        if let Err(e) = result {
+            self.crash(p, "event", e).await;
+        }
+    }
+
     pub async fn key(mut self, p: &mut Player, state: KeyState, key: Key) -> Self {
-        self.current().key(p, state, key).await;
+        if let Some(stage) = self.current() {
+            if let Err(e) = CatchUnwind(stage.key(p, state, key)).await {
+                self.crash(p, "key", e).await;
+            }
+        }
+        self
+    }
+
+    pub async fn pause(mut self, p: &mut Player) -> Self {
+        if let Some(stage) = self.current() {
+            if let Err(e) = CatchUnwind(stage.pause(p)).await {
+                self.crash(p, "pause", e).await;
+            }
+        }
+        self
+    }
+
+    pub async fn resume(mut self, p: &mut Player) -> Self {
+        if let Some(stage) = self.current() {
+            if let Err(e) = CatchUnwind(stage.resume(p)).await {
+                self.crash(p, "resume", e).await;
+            }
+        }
         self
     }
 
     pub fn view(mut self, frame: &mut Frame, target: &wgpu::RawTextureView) -> Self {
-        self.current().view(frame, target);
+        self.shown |= self.entered;
+
+        // Frozen stages keep drawing their last state
+        let stage = match self.slots.get_mut(self.current) {
+            Some(Slot::Loaded(stage)) if self.entered && !self.blank => Some(stage),
+            _ => None,
+        };
+
+        match stage {
+            Some(stage) => {
+                if let Err(e) = panic::catch_unwind(AssertUnwindSafe(|| stage.view(frame, target))) {
+                    self.frozen = true;
+                    self.blank = true;
+                    self.view_panic = Some(e);
+                }
+            }
+            None => {
+                self.loading.update(self.loading_t);
+                self.loading.encode(frame, target);
+                self.loading_shown = true;
+            }
+        }
         self
     }
 }
+
+/// Polls a future, catching a panic inside it as an error.
+struct CatchUnwind<F>(F);
+
+impl<F: Future + Unpin> Future for CatchUnwind<F> {
+    type Output = thread::Result<F::Output>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let inner = &mut self.0;
+        match panic::catch_unwind(AssertUnwindSafe(|| Pin::new(&mut *inner).poll(cx))) {
+            Ok(Poll::Pending) => Poll::Pending,
+            Ok(Poll::Ready(v)) => Poll::Ready(Ok(v)),
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+}
+
+fn message(e: &Box<dyn Any + Send>) -> String {
+    if let Some(s) = e.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = e.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown panic".to_owned()
+    }
+}
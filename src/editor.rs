@@ -0,0 +1,426 @@
+use anyhow::Result;
+use std::io::{Stdout, Write};
+
+use crossterm::event::{self as term, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::style::{Attribute, Color, Print, SetAttribute, SetForegroundColor};
+use crossterm::{cursor, execute, queue, terminal};
+
+use crate::demo::{Demo, Event, Tempo};
+
+/// Width of the note labels, as in `{:>3} `
+const GUTTER: u16 = 4;
+const HEADER: u16 = 1;
+const FOOTER: u16 = 1;
+
+/// Nudge step in seconds, and with shift held.
+const NUDGE: f32 = 0.01;
+const NUDGE_FINE: f32 = 0.001;
+
+/// Subdivisions of a beat that quantize snaps to.
+const GRID: f32 = 4.0;
+
+/// Open a `.dem` in a terminal piano-roll for small timing fixes.
+pub fn run(file: &str) -> Result<()> {
+    let demo = Demo::load(file)?;
+    let mut editor = Editor::new(file, demo);
+
+    let mut out = std::io::stdout();
+    let _terminal = RawTerminal::enter(&mut out)?;
+
+    editor.run(&mut out)
+}
+
+/// Raw mode on the alternate screen, put back when dropped, so a panic
+/// doesn't leave the terminal unusable.
+struct RawTerminal;
+
+impl RawTerminal {
+    fn enter(out: &mut Stdout) -> Result<Self> {
+        terminal::enable_raw_mode()?;
+        let guard = RawTerminal;
+        execute!(out, terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(guard)
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let _ = execute!(std::io::stdout(), terminal::LeaveAlternateScreen, cursor::Show);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+struct Editor<'a> {
+    file: &'a str,
+    demo: Demo,
+    dirty: bool,
+    status: String,
+
+    /// Time at the left edge of the roll, and seconds per column
+    t0: f32,
+    zoom: f32,
+    /// Highest note shown at the top of the roll
+    note_top: u8,
+
+    /// Cursor time and note
+    t: f32,
+    note: u8,
+    selected: Option<usize>,
+}
+
+impl<'a> Editor<'a> {
+    fn new(file: &'a str, demo: Demo) -> Self {
+        Self {
+            file,
+            demo,
+            dirty: false,
+            status: String::new(),
+
+            t0: 0.0,
+            zoom: 0.05,
+            note_top: 75,
+
+            t: 0.0,
+            note: 60,
+            selected: None,
+        }
+    }
+
+    fn run(&mut self, out: &mut Stdout) -> Result<()> {
+        loop {
+            self.draw(out)?;
+
+            if let term::Event::Key(key) = term::read()? {
+                if !self.key(key)? {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Handle a key press, returning false to quit.
+    fn key(&mut self, KeyEvent { code, modifiers, .. }: KeyEvent) -> Result<bool> {
+        let shift = modifiers.contains(KeyModifiers::SHIFT);
+        let step = if shift { 10.0 * self.zoom } else { self.zoom };
+        self.status.clear();
+
+        match code {
+            KeyCode::Esc | KeyCode::Char('Q') => {
+                if self.dirty && code == KeyCode::Esc {
+                    self.status = "Unsaved changes, press Q to quit anyway".into();
+                } else {
+                    return Ok(false);
+                }
+            }
+
+            // Cursor
+            KeyCode::Left => self.t = (self.t - step).max(0.0),
+            KeyCode::Right => self.t += step,
+            KeyCode::Up => self.note = self.note.saturating_add(1).min(127),
+            KeyCode::Down => self.note = self.note.saturating_sub(1),
+            KeyCode::PageUp => self.note_top = self.note_top.saturating_add(8).min(127),
+            KeyCode::PageDown => self.note_top = self.note_top.saturating_sub(8),
+            KeyCode::Char('+') | KeyCode::Char('=') => self.zoom = (self.zoom / 2.0).max(0.001),
+            KeyCode::Char('-') => self.zoom = (self.zoom * 2.0).min(2.0),
+
+            // Selection
+            KeyCode::Tab => self.select_next(1),
+            KeyCode::BackTab => self.select_next(-1),
+
+            // Editing
+            KeyCode::Char(',') | KeyCode::Char('<') => self.nudge(if shift { -NUDGE_FINE } else { -NUDGE }),
+            KeyCode::Char('.') | KeyCode::Char('>') => self.nudge(if shift { NUDGE_FINE } else { NUDGE }),
+            KeyCode::Char('q') => self.quantize(),
+            KeyCode::Char('a') => self.add(),
+            KeyCode::Char('d') | KeyCode::Delete => self.delete(),
+            KeyCode::Char('s') => {
+                self.demo.save(self.file)?;
+                self.dirty = false;
+                self.status = format!("Saved {}", self.file);
+            }
+
+            _ => {}
+        }
+
+        self.follow();
+        Ok(true)
+    }
+
+    /// Select the next or previous note event from the cursor, moving the cursor to it.
+    fn select_next(&mut self, dir: i32) {
+        let t = self.t;
+        let current = self.selected;
+
+        let mut candidates = self
+            .demo
+            .events
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, ev))| note(ev).is_some());
+
+        let next = if dir > 0 {
+            candidates.find(|(i, (et, _))| *et > t || (*et == t && Some(*i) > current))
+        } else {
+            candidates.rfind(|(i, (et, _))| *et < t || (*et == t && Some(*i) < current))
+        };
+
+        if let Some((i, (et, ev))) = next {
+            self.selected = Some(i);
+            self.t = *et;
+            self.note = note(ev).unwrap();
+        }
+    }
+
+    fn nudge(&mut self, dt: f32) {
+        if let Some(i) = self.selected {
+            let t = (self.demo.events[i].0 + dt).max(0.0);
+            self.move_selected(t);
+        }
+    }
+
+    fn quantize(&mut self) {
+        if let Some(i) = self.selected {
+            let t = self.quantized(self.demo.events[i].0);
+            self.move_selected(t);
+        }
+    }
+
+    fn add(&mut self) {
+        let t = self.t;
+        let id = self.note;
+        let ev = match id {
+            0..30 => Event::Trigger { id },
+            30..60 => Event::Toggle { id, state: true },
+            60.. => Event::Beat { id, t: 60.0 / self.bpm(t) / GRID },
+        };
+
+        self.demo.events.push((t, ev));
+        let i = self.demo.events.len() - 1;
+
+        if let Event::Toggle { .. } = ev {
+            // Toggles need a matching release to be useful
+            let t1 = t + 60.0 / self.bpm(t);
+            self.demo.events.push((t1, Event::Toggle { id, state: false }));
+        }
+
+        self.sort(Some(i));
+        self.dirty = true;
+        self.status = format!("Added {:?} at {:.3}", ev, t);
+    }
+
+    fn delete(&mut self) {
+        if let Some(i) = self.selected.take() {
+            // Toggles go in pairs, so take the other half with it
+            let pair = self.pair(i);
+            if let Some(j) = pair.filter(|j| *j > i) {
+                self.demo.events.remove(j);
+            }
+            let (t, ev) = self.demo.events.remove(i);
+            if let Some(j) = pair.filter(|j| *j < i) {
+                self.demo.events.remove(j);
+            }
+
+            self.dirty = true;
+            self.status = match pair {
+                Some(_) => format!("Deleted {:?} at {:.3} and its pair", ev, t),
+                None => format!("Deleted {:?} at {:.3}", ev, t),
+            };
+        }
+    }
+
+    /// The release of a Toggle on, or the press of a Toggle off.
+    fn pair(&self, i: usize) -> Option<usize> {
+        let events = &self.demo.events;
+        match events[i].1 {
+            Event::Toggle { id, state: true } => (i + 1..events.len())
+                .find(|j| matches!(events[*j].1, Event::Toggle { id: other, state: false } if other == id)),
+            Event::Toggle { id, state: false } => (0..i)
+                .rev()
+                .find(|j| matches!(events[*j].1, Event::Toggle { id: other, state: true } if other == id)),
+            _ => None,
+        }
+    }
+
+    /// Move the selected event, keeping the event list sorted by time.
+    fn move_selected(&mut self, t: f32) {
+        if let Some(i) = self.selected {
+            self.demo.events[i].0 = t;
+            self.t = t;
+            self.sort(Some(i));
+            self.dirty = true;
+        }
+    }
+
+    fn sort(&mut self, keep: Option<usize>) {
+        let mut events: Vec<_> = self.demo.events.drain(..).enumerate().collect();
+        events.sort_by(|(_, (a, _)), (_, (b, _))| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+        self.selected = keep.and_then(|keep| events.iter().position(|(i, _)| *i == keep));
+        self.demo.events = events.into_iter().map(|(_, ev)| ev).collect();
+    }
+
+    /// The tempo in effect at `t`.
+    fn tempo(&self, t: f32) -> (f32, Tempo) {
+        let i = self.demo.tempo.partition_point(|(t0, _)| *t0 <= t);
+        i.checked_sub(1)
+            .map(|i| self.demo.tempo[i])
+            .unwrap_or((0.0, Tempo { bpm: 120.0, ramp: false }))
+    }
+
+    fn bpm(&self, t: f32) -> f32 {
+        self.tempo(t).1.bpm
+    }
+
+    /// Snap to the beat grid, counted from the last tempo change.
+    fn quantized(&self, t: f32) -> f32 {
+        let (t0, Tempo { bpm, .. }) = self.tempo(t);
+        let step = 60.0 / bpm / GRID;
+        t0 + ((t - t0) / step).round() * step
+    }
+
+    /// Scroll so the cursor stays in view.
+    fn follow(&mut self) {
+        let (w, h) = terminal::size().unwrap_or((80, 24));
+        let cols = w.saturating_sub(GUTTER) as f32;
+        let rows = h.saturating_sub(HEADER + FOOTER);
+
+        if self.t < self.t0 {
+            self.t0 = self.t;
+        } else if self.t >= self.t0 + cols * self.zoom {
+            self.t0 = self.t - (cols - 1.0) * self.zoom;
+        }
+
+        // The bottom row shown is `note_top - rows + 1`
+        let (note, note_top) = (self.note as u16, self.note_top as u16);
+        if note > note_top {
+            self.note_top = self.note;
+        } else if rows > 0 && note.saturating_add(rows) <= note_top {
+            self.note_top = (note + rows - 1) as u8;
+        }
+    }
+
+    fn col(&self, t: f32) -> i32 {
+        ((t - self.t0) / self.zoom).floor() as i32
+    }
+
+    fn draw(&self, out: &mut Stdout) -> Result<()> {
+        let (w, h) = terminal::size()?;
+        let cols = w.saturating_sub(GUTTER) as usize;
+        let rows = h.saturating_sub(HEADER + FOOTER) as usize;
+
+        // Build the grid of cells, starting with the RMS curve as the background
+        let mut grid = vec![vec![(' ', Color::Reset); cols]; rows];
+
+        let peak = self.demo.meta.peak_rms.max(f32::EPSILON);
+        for (x, col) in (0..cols).map(|x| (x, self.t0 + x as f32 * self.zoom)) {
+            let i = self.demo.data.partition_point(|(t, _)| *t <= col);
+            let rms = i.checked_sub(1).map(|i| self.demo.data[i].1.rms).unwrap_or(0.0);
+            let height = ((rms / peak) * rows as f32).round() as usize;
+
+            for row in grid.iter_mut().skip(rows.saturating_sub(height)) {
+                row[x] = ('.', Color::DarkGrey);
+            }
+        }
+
+        // Cursor column
+        let cursor_x = self.col(self.t);
+        if cursor_x >= 0 && (cursor_x as usize) < cols {
+            for row in grid.iter_mut() {
+                row[cursor_x as usize] = (':', Color::DarkYellow);
+            }
+        }
+
+        // Events
+        let t1 = self.t0 + cols as f32 * self.zoom;
+        let put = |grid: &mut Vec<Vec<(char, Color)>>, t: f32, id: u8, c: char, color: Color| {
+            let x = self.col(t);
+            let y = self.note_top as i32 - id as i32;
+            if x >= 0 && (x as usize) < cols && y >= 0 && (y as usize) < rows {
+                grid[y as usize][x as usize] = (c, color);
+            }
+        };
+
+        for (i, (t, ev)) in self.demo.events.iter().enumerate() {
+            let color = if Some(i) == self.selected { Color::Magenta } else { Color::White };
+            let end = match ev {
+                Event::Beat { t: dt, .. } => t + dt,
+                _ => *t,
+            };
+            if end < self.t0 || *t > t1 {
+                continue;
+            }
+
+            match *ev {
+                Event::Trigger { id } => put(&mut grid, *t, id, '|', color),
+                Event::Toggle { id, state: true } => put(&mut grid, *t, id, '[', color),
+                Event::Toggle { id, state: false } => put(&mut grid, *t, id, ']', color),
+                Event::Beat { id, t: dt } => {
+                    let n = (dt / self.zoom).ceil() as i32;
+                    for x in 1..n {
+                        put(&mut grid, t + x as f32 * self.zoom, id, '=', color);
+                    }
+                    put(&mut grid, *t, id, '#', color);
+                }
+                _ => {}
+            }
+        }
+
+        queue!(out, terminal::Clear(terminal::ClearType::All), cursor::MoveTo(0, 0))?;
+
+        // Header
+        let selected = self
+            .selected
+            .map(|i| format!("{:?} @ {:.3}", self.demo.events[i].1, self.demo.events[i].0))
+            .unwrap_or_else(|| "-".into());
+        let header = format!(
+            "{}{} | t={:.3} note={} bpm={:.1} | {:.0}ms/col | {} {}",
+            self.file,
+            if self.dirty { "*" } else { "" },
+            self.t,
+            self.note,
+            self.bpm(self.t),
+            self.zoom * 1000.0,
+            selected,
+            self.status,
+        );
+        queue!(out, SetAttribute(Attribute::Reverse), Print(fit(&header, w as usize)), SetAttribute(Attribute::Reset))?;
+
+        // Roll
+        for (y, row) in grid.iter().enumerate() {
+            let id = self.note_top as i32 - y as i32;
+            let label = if id >= 0 { format!("{:>3} ", id) } else { "    ".into() };
+            let label_color = if id == self.note as i32 { Color::DarkYellow } else { Color::DarkGrey };
+
+            queue!(out, cursor::MoveTo(0, HEADER + y as u16), SetForegroundColor(label_color), Print(label))?;
+            for (c, color) in row.iter() {
+                queue!(out, SetForegroundColor(*color), Print(c))?;
+            }
+        }
+
+        // Footer
+        let help = "arrows move  tab select  ,/. nudge  q quantize  a add  d delete  +/- zoom  s save  esc quit";
+        queue!(
+            out,
+            cursor::MoveTo(0, h.saturating_sub(1)),
+            SetForegroundColor(Color::Reset),
+            SetAttribute(Attribute::Reverse),
+            Print(fit(help, w as usize)),
+            SetAttribute(Attribute::Reset),
+        )?;
+
+        out.flush()?;
+        Ok(())
+    }
+}
+
+/// The note row an event is drawn on, if any.
+fn note(ev: &Event) -> Option<u8> {
+    match *ev {
+        Event::Trigger { id } | Event::Toggle { id, .. } | Event::Beat { id, .. } => Some(id),
+        _ => None,
+    }
+}
+
+fn fit(s: &str, w: usize) -> String {
+    format!("{:<w$.w$}", s, w = w)
+}
@@ -14,17 +14,28 @@ mod stages;
 mod util;
 
 mod demo;
+mod coverage;
+mod editor;
+mod validate;
 use demo::{Demo, Player, Stage, Stages};
 
 fn main() -> Result<()> {
     let args: Vec<String> = std::env::args().collect();
-    match args.iter().find(|arg| arg.starts_with("--render")) {
+    match args.iter().find(|arg| arg.starts_with("--render") || arg.starts_with("--export") || arg.starts_with("--edit") || arg.starts_with("--coverage")) {
         None => lib::app::run(window, model, input, update, view)?,
+        Some(arg) if arg.starts_with("--edit") => editor::run(&args[2])?,
+        Some(arg) if arg.starts_with("--coverage") => coverage::run(&args[2], args.get(3).map(String::as_str))?,
+        Some(arg) if arg.starts_with("--export") => {
+            let demo_file = &args[2];
+            let midi_file = &args[3];
+            Demo::load(demo_file)?.export_midi(midi_file)?;
+        }
         Some(_) => {
             let audio_file = &args[2];
             let midi_file = &args[3];
+            let project_file = args.get(4).map(String::as_str);
             let demo_file = "resources/demos/ms7.dem";
-            Demo::new(audio_file, midi_file)?.save(demo_file)?;
+            Demo::new(audio_file, midi_file, project_file)?.save(demo_file)?;
         }
     }
 
@@ -33,6 +44,9 @@ fn main() -> Result<()> {
 
 pub struct Model {
     player: Player,
+    warp: pipeline::WarpPass,
+    capture: pipeline::Capture,
+    shaders: pipeline::ShaderWatcher,
     midi: Option<Midi<WorldeEasyControl9>>,
 }
 
@@ -59,52 +73,83 @@ async fn model(app: &App) -> Model {
         None => 0.0,
     };
 
-    let mut stages: HashMap<&'static str, Box<dyn Stage + Send>> = HashMap::new();
-
-    // DONE
-    stages.insert("lobby", Box::new(stages::Lobby::new(app)));
-
-    // DONE
-    stages.insert("metalheart", Box::new(stages::Metalheart::new(app)));
-
-    // DONE
-    stages.insert("cyber_grind", Box::new(stages::CyberGrind::new(app)));
-
-    // DONE
-    stages.insert("halo", Box::new(stages::Halo::new(app)));
-
-    // TODO
-    stages.insert("aqua", Box::new(stages::Aqua::new(app)));
-
-    // DONE
-    stages.insert("reality", Box::new(stages::Reality::new(app)));
-
-    // DONE
-    stages.insert("pod", Box::new(stages::Pod::new(app)));
-
-    // TODO
-    stages.insert("chaostheory", Box::new(stages::Chaos::new(app)));
-
-    // INPROGRESS
-    stages.insert("dragonage", Box::new(stages::Dragon::new(app)));
-
-    // TODO
-    stages.insert("yume", Box::new(stages::Yume::new(app)));
-    stages.insert("resolve", Box::new(stages::Resolve::new(app)));
-
-    // DONE
-    stages.insert("funky_beat", Box::new(stages::FunkyBeat::new(app)));
-
-    // TODO
-    stages.insert("thanks", Box::new(stages::Thanks::new(app)));
-
     // let scene0 = "lobby";
     let scene0 = "lobby";
-    let player = Player::new("ms7.dem", t0, scene0, stages).expect("failed to load demo");
+
+    let stages = Stages::new(app, scene0)
+        .fallback("thanks")
+        .setlist(&[
+            "lobby",
+            "metalheart",
+            "cyber_grind",
+            "halo",
+            "aqua",
+            "reality",
+            "pod",
+            "chaostheory",
+            "dragonage",
+            "yume",
+            "resolve",
+            "funky_beat",
+            "thanks",
+        ])
+        .declare(stages::infos())
+
+        // DONE
+        .with("lobby", |app, targets, presets| Box::new(stages::Lobby::new(app, targets, presets)))
+
+        // DONE
+        .with("metalheart", |app, targets, presets| Box::new(stages::Metalheart::new(app, targets, presets)))
+
+        // DONE
+        .with("cyber_grind", |app, targets, presets| Box::new(stages::CyberGrind::new(app, targets, presets)))
+
+        // DONE
+        .with("halo", |app, targets, presets| Box::new(stages::Halo::new(app, targets, presets)))
+
+        // TODO
+        .with("aqua", |app, targets, presets| Box::new(stages::Aqua::new(app, targets, presets)))
+
+        // DONE
+        .with("reality", |app, targets, presets| Box::new(stages::Reality::new(app, targets, presets)))
+
+        // DONE
+        .with("pod", |app, targets, presets| Box::new(stages::Pod::new(app, targets, presets)))
+
+        // TODO
+        .with("chaostheory", |app, targets, presets| Box::new(stages::Chaos::new(app, targets, presets)))
+
+        // INPROGRESS
+        .with("dragonage", |app, targets, presets| Box::new(stages::Dragon::new(app, targets, presets)))
+
+        // TODO
+        .with("yume", |app, targets, presets| Box::new(stages::Yume::new(app, targets, presets)))
+        .with("resolve", |app, targets, presets| Box::new(stages::Resolve::new(app, targets, presets)))
+
+        // DONE
+        .with("funky_beat", |app, targets, presets| Box::new(stages::FunkyBeat::new(app, targets, presets)))
+
+        // TODO
+        .with("thanks", |app, _, _| Box::new(stages::Thanks::new(app)));
+
+    let problems = validate::validate(&stages);
+    for problem in problems.iter() {
+        log::error!("{}", problem);
+    }
+    match problems.len() {
+        0 => log::info!("Validated stages and resources"),
+        n => log::error!("Validation found {} problems", n),
+    }
+
+    let player = Player::new("ms7.dem", t0, scene0, stages).unwrap_or_else(|e| panic!("failed to load demo: {:#}", e));
 
     let midi = Midi::<WorldeEasyControl9>::maybe_open("WORLDE easy control", "WORLDE easy control");
 
-    Model { player, midi }
+    let warp = pipeline::WarpPass::new(device, pipeline::output_size());
+    let capture = pipeline::Capture::new(device, pipeline::output_size());
+    let shaders = pipeline::ShaderWatcher::new();
+
+    Model { player, warp, capture, shaders, midi }
 }
 
 async fn input(app: &App, m: &mut Model, state: KeyState, key: Key) {
@@ -113,8 +158,12 @@ async fn input(app: &App, m: &mut Model, state: KeyState, key: Key) {
     }
 
     match key {
-        Key::Space => m.player.play(),
+        Key::Space => m.player.toggle().await,
+        Key::N => m.player.next().await,
         Key::Q => app.exit(),
+        _ if pipeline::output_key(key) => {}
+        _ if m.warp.key(key) => {}
+        _ if m.capture.key(key, m.player.stage()) => {}
         _ => m.player.key(state, key).await,
     }
 }
@@ -129,9 +178,41 @@ async fn update(app: &App, m: &mut Model, dt: f32) {
         }
     }
 
-    m.player.update(dt).await;
+    pipeline::reload_output();
+    m.warp.update();
+    m.capture.update(&app.device);
+    let changed = m.shaders.poll(dt);
+    if !changed.is_empty() {
+        m.warp.reload(&app.device, &changed);
+        m.capture.reload(&app.device, &changed);
+        m.player.rebuild(app, &changed).await;
+    }
+    m.player.update(app, dt).await;
 }
 
-fn view(_app: &App, m: &mut Model, frame: &mut Frame, target: &wgpu::RawTextureView) {
-    m.player.view(frame, target);
+fn view(app: &App, m: &mut Model, frame: &mut Frame, target: &wgpu::RawTextureView) {
+    let Model { player, warp, capture, .. } = m;
+    let [w, h] = frame.texture_size();
+    let size = (w as usize, h as usize);
+    pipeline::set_output_size(size);
+
+    let out = match capture.active() {
+        true => {
+            capture.resize(&app.device, size);
+            capture.view()
+        }
+        false => target,
+    };
+
+    if warp.active() {
+        warp.resize(&app.device, size);
+        player.view(frame, warp.view());
+        warp.encode(frame, out);
+    } else {
+        player.view(frame, out);
+    }
+
+    if capture.active() {
+        capture.encode(frame, target, player.stage(), player.t());
+    }
 }
@@ -7,6 +7,8 @@ use lib::gfx::mesh::{Vertex, VertexExt as _};
 use lib::gfx::scene::Scene;
 use lib::gfx::wgpu;
 
+use crate::pipeline::shader;
+
 mod material;
 pub use material::{Material, MaterialDesc};
 
@@ -23,8 +25,8 @@ pub struct Animated {
 
 impl Animated {
     pub fn new(app: &App, scene: &Scene, descs: &[MaterialDesc]) -> Self {
-        let vs = lib::resource::read_shader(&app.device, "phong.vert.spv");
-        let fs = lib::resource::read_shader(&app.device, "phong_anim.frag.spv");
+        let vs = lib::resource::read_shader(&app.device, shader("phong.vert.spv"));
+        let fs = lib::resource::read_shader(&app.device, shader("phong_anim.frag.spv"));
 
         let layout = wgpu::util::BindGroupLayoutBuilder::new("phong")
             .array_tex(wgpu::ShaderStages::FRAGMENT)
@@ -1,19 +1,85 @@
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
 use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
 
 use lib::gfx::frame::Frame;
-// use lib::gfx::uniform::UniformStorage;
+use lib::gfx::uniform::UniformStorage;
 use lib::gfx::wgpu;
-// use lib::math::{Matrix4, SquareMatrix};
+use lib::math::{Matrix4, SquareMatrix};
+
+use super::{output, output_size, shader, Resolution, Scale, Targets};
+
+type View = wgpu::RawTextureView;
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct Transform {
+    /// From centered output coords to the texture's
+    transform: Matrix4,
+    nearest: f32,
+    _pad: [f32; 3],
+}
+
+impl Transform {
+    fn new(transform: Matrix4, nearest: bool) -> Self {
+        Self {
+            transform,
+            nearest: if nearest { 1.0 } else { 0.0 },
+            _pad: [0.0; 3],
+        }
+    }
+}
+
+/// How the blit is drawn over its target, kept to rebuild the pipeline.
+#[derive(Clone, Copy)]
+struct Blend {
+    color: Option<wgpu::BlendComponent>,
+    alpha: Option<wgpu::BlendComponent>,
+    write_mask: Option<wgpu::ColorWrites>,
+}
+
+const SHADERS: [&str; 2] = ["billboard.vert.spv", "blit.frag.spv"];
+
+fn pipeline(device: &wgpu::Device, name: &str, layout: &wgpu::BindGroupLayout, blend: Blend) -> wgpu::RenderPipeline {
+    let vs = lib::resource::read_shader(device, shader(SHADERS[0]));
+    let fs = lib::resource::read_shader(device, shader(SHADERS[1]));
+    let mut pipeline = wgpu::util::PipelineBuilder::new(name)
+        .with_layout(layout)
+        .render(&vs)
+        .fragment(&fs);
+
+    if let Some(blend) = blend.color {
+        pipeline = pipeline.color_blend(blend);
+    }
+    if let Some(blend) = blend.alpha {
+        pipeline = pipeline.alpha_blend(blend);
+    }
+    if let Some(mask) = blend.write_mask {
+        pipeline = pipeline.write_mask(mask);
+    }
+
+    pipeline.build(device)
+}
 
 pub struct BlitPass {
+    name: String,
     pipeline: wgpu::RenderPipeline,
+    layout: wgpu::BindGroupLayout,
+    blend: Blend,
     group: wgpu::BindGroup,
 
-    view: wgpu::TextureView,
+    /// None when drawing from a pooled target
+    texture: Option<wgpu::Texture>,
+    view: Arc<View>,
     sampler: wgpu::Sampler,
-    // uniform: UniformStorage<Matrix4>,
+    uniform: RefCell<UniformStorage<Transform>>,
+    clear: Option<wgpu::Color>,
+    /// Set for the final blit onto the output, which follows the shared
+    /// output settings and is letterboxed to fit
+    fit: Option<Resolution>,
 
+    /// Which output settings are uploaded
+    generation: Cell<Option<usize>>,
     dirty: Cell<bool>,
 }
 
@@ -23,8 +89,10 @@ impl BlitPass {
             name,
 
             texture: None,
+            pool: None,
             sampler: None,
-            // transform: None,
+            transform: None,
+            fit: None,
 
             clear: None,
             color_blend: None,
@@ -37,16 +105,54 @@ impl BlitPass {
         &self.view
     }
 
+    /// The texture behind `view`, for copying out of. Pooled blits don't have one.
+    pub fn texture(&self) -> &wgpu::Texture {
+        self.texture.as_ref().expect("a pooled blit has no texture of its own")
+    }
+
+    /// Rebuild the pipeline if any of the `changed` shaders are its own.
+    pub fn reload(&mut self, device: &wgpu::Device, changed: &[String]) {
+        if changed.iter().any(|c| SHADERS.contains(&c.as_str())) {
+            self.pipeline = pipeline(device, &self.name, &self.layout, self.blend);
+        }
+    }
+
+    /// Replace the transform of a blit that isn't fit to the output.
+    pub fn set_transform(&mut self, transform: Matrix4, nearest: bool) {
+        **self.uniform.get_mut() = Transform::new(transform, nearest);
+        self.dirty.set(true);
+    }
+
     pub fn encode(&self, frame: &mut Frame, target: &wgpu::RawTextureView) {
-        // if self.dirty.get() {
-        //     self.uniform.upload(frame);
-        //     self.dirty.set(false);
-        // }
+        let mut viewport = None;
+        let mut clear = self.clear;
+        if let Some(res) = self.fit {
+            let (out, generation) = output();
+            viewport = res.viewport(output_size(), out.rotate.sideways(), out.scale == Scale::Integer);
+            clear = Some(out.border());
+
+            if self.generation.get() != Some(generation) {
+                **self.uniform.borrow_mut() = Transform::new(out.transform(), out.nearest());
+                self.generation.set(Some(generation));
+                self.dirty.set(true);
+            }
+        }
+
+        if self.dirty.get() {
+            self.uniform.borrow().upload(frame);
+            self.dirty.set(false);
+        }
 
         let mut pass = wgpu::util::RenderPassBuilder::new()
-            .color_attachment(target, |a| a)
+            .color_attachment(target, |a| match clear {
+                Some(color) => a.color(|op| op.clear(color)),
+                None => a,
+            })
             .begin(frame);
 
+        if let Some([x, y, w, h]) = viewport {
+            pass.set_viewport(x, y, w, h, 0.0, 1.0);
+        }
         pass.set_pipeline(&self.pipeline);
         pass.set_bind_group(0, &self.group, &[]);
         pass.draw(0..3, 0..1);
@@ -57,8 +163,10 @@ pub struct BlitPassBuilder<'a> {
     name: &'a str,
 
     texture: Option<wgpu::util::TextureBuilder<'a>>,
+    pool: Option<(&'a Targets, (usize, usize))>,
     sampler: Option<wgpu::util::SamplerBuilder<'a>>,
-    // transform: Option<Matrix4>,
+    transform: Option<Matrix4>,
+    fit: Option<Resolution>,
 
     clear: Option<wgpu::Color>,
     color_blend: Option<wgpu::BlendComponent>,
@@ -76,6 +184,13 @@ impl<'a> BlitPassBuilder<'a> {
         self
     }
 
+    /// Draw from a target of `size` in the shared pool, instead of a texture
+    /// of its own. A blit that's copied out of needs its own.
+    pub fn pooled(mut self, targets: &'a Targets, size: (usize, usize)) -> Self {
+        self.pool = Some((targets, size));
+        self
+    }
+
     pub fn sampler<F>(mut self, name: &'a str, sampler: F) -> Self
     where
         F: FnOnce(wgpu::util::SamplerBuilder) -> wgpu::util::SamplerBuilder,
@@ -85,10 +200,25 @@ impl<'a> BlitPassBuilder<'a> {
         self
     }
 
-    // pub fn transform(mut self, matrix: Matrix4) -> Self {
-    //     self.transform = Some(matrix);
-    //     self
-    // }
+    /// Transform the texture coords, centered on 0. Ignored by blits fit to
+    /// the output, which follow the output settings instead.
+    pub fn transform(mut self, matrix: Matrix4) -> Self {
+        self.transform = Some(matrix);
+        self
+    }
+
+    /// Make this the stage's blit onto the output: letterbox or pillarbox a
+    /// stage rendered at `res` to fit, and scale, flip and rotate it as
+    /// `output.cfg` and the F5-F8 keys say.
+    pub fn fit(mut self, res: Resolution) -> Self {
+        self.fit = Some(res);
+        self
+    }
+
+    pub fn clear(mut self, color: wgpu::Color) -> Self {
+        self.clear = Some(color);
+        self
+    }
 
     pub fn color_blend(mut self, blend: wgpu::BlendComponent) -> Self {
         self.color_blend = Some(blend);
@@ -108,62 +238,62 @@ impl<'a> BlitPassBuilder<'a> {
     pub fn build(self, device: &wgpu::Device) -> BlitPass {
         let name = format!("blit_{}", self.name);
 
-        let texture = self
-            .texture
-            .unwrap_or_else(|| wgpu::util::TextureBuilder::new(&name))
-            .label(&name)
-            .usage(wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING)
-            .build(device)
-            .view()
-            .build();
+        let (texture, view) = match self.pool {
+            Some((targets, size)) => (None, targets.get(device, "blit", size, 0)),
+            None => {
+                let texture = self
+                    .texture
+                    .unwrap_or_else(|| wgpu::util::TextureBuilder::new(&name))
+                    .label(&name)
+                    .usage(wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_SRC)
+                    .build(device);
+                let view = Arc::new(texture.view().build().into_raw());
+                (Some(texture), view)
+            }
+        };
 
         let sampler = self
             .sampler
             .unwrap_or_else(|| wgpu::util::SamplerBuilder::new(&name))
             .build(device);
 
-        // let transform = self.transform.unwrap_or_else(|| Matrix4::identity());
-        // let uniform = UniformStorage::new(device, &format!("{}_transform", name), transform);
+        let transform = self.transform.unwrap_or_else(|| Matrix4::identity());
+        let uniform = UniformStorage::new(device, &format!("{}_transform", name), Transform::new(transform, false));
 
         let layout = wgpu::util::BindGroupLayoutBuilder::new(&name)
             .tex(wgpu::ShaderStages::FRAGMENT)
             .sampler(wgpu::ShaderStages::FRAGMENT)
-            // .uniform(wgpu::ShaderStages::FRAGMENT)
+            .uniform(wgpu::ShaderStages::FRAGMENT)
             .build(device);
 
         let group = wgpu::util::BindGroupBuilder::new(&name)
-            .texture(&texture)
+            .texture(&*view)
             .sampler(&sampler)
-            // .uniform(&uniform.uniform)
+            .uniform(&uniform.uniform)
             .build(device, &layout);
 
-        let vs = lib::resource::read_shader(&device, "billboard.vert.spv");
-        let fs = lib::resource::read_shader(&device, "blit.frag.spv");
-        let mut pipeline = wgpu::util::PipelineBuilder::new(&name)
-            .with_layout(&layout)
-            .render(&vs)
-            .fragment(&fs);
-
-        if let Some(blend) = self.color_blend {
-            pipeline = pipeline.color_blend(blend);
-        }
-        if let Some(blend) = self.alpha_blend {
-            pipeline = pipeline.alpha_blend(blend);
-        }
-        if let Some(mask) = self.write_mask {
-            pipeline = pipeline.write_mask(mask);
-        }
-
-        let pipeline = pipeline.build(device);
+        let blend = Blend {
+            color: self.color_blend,
+            alpha: self.alpha_blend,
+            write_mask: self.write_mask,
+        };
+        let pipeline = pipeline(device, &name, &layout, blend);
 
         BlitPass {
+            name,
             pipeline,
+            layout,
+            blend,
             group,
 
-            view: texture,
+            texture,
+            view,
             sampler,
-            // uniform,
+            uniform: RefCell::new(uniform),
+            clear: self.clear,
+            fit: self.fit,
 
+            generation: Cell::new(None),
             dirty: Cell::new(false),
         }
     }
@@ -0,0 +1,289 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use lib::gfx::frame::Frame;
+use lib::gfx::wgpu;
+use lib::prelude::*;
+
+use super::{record_shaders, BlitPass};
+
+const CAPTURE_DIR: &str = "captures";
+/// Frames waiting to be written before a clip starts dropping them
+const MAX_QUEUED: usize = 8;
+/// Frames that can be copied out of the GPU at once, waiting to map
+const READBACKS: usize = 3;
+
+struct Image {
+    path: PathBuf,
+    width: u32,
+    height: u32,
+    rgba: Vec<u8>,
+}
+
+/// A buffer to copy frames out of the GPU into, reused once its frame is
+/// handed to the writer.
+struct Readback {
+    buffer: wgpu::Buffer,
+    /// Where the frame copied into it goes, while it's in use
+    path: Option<PathBuf>,
+    /// Whether the frame it was copied in has been submitted, so it can be mapped
+    submitted: bool,
+    mapped: Arc<AtomicBool>,
+}
+
+impl Readback {
+    /// Rows are copied padded out to the alignment.
+    fn row((w, _): (u32, u32)) -> u32 {
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        (w * 4 + align - 1) / align * align
+    }
+
+    fn new(device: &wgpu::Device, size: (u32, u32)) -> Self {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("capture"),
+            size: (Self::row(size) * size.1) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            buffer,
+            path: None,
+            submitted: false,
+            mapped: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
+
+struct Clip {
+    dir: PathBuf,
+    frame: usize,
+}
+
+/// Stills and clips of the final frame, written as PNGs under `captures/`.
+///
+/// F11 saves the next frame. F12 starts saving every frame into a new
+/// directory, until it's pressed again. Files are named with the stage and
+/// the song time they show, like `captures/1760827200123_aqua_61.250.png`.
+///
+/// While capturing, the frame is drawn into a texture the size of the
+/// surface, copied out into one of a few reused buffers, and blitted to the
+/// surface. Images are encoded on their own thread.
+pub struct Capture {
+    blit: BlitPass,
+    /// What `blit` was built with
+    shaders: HashSet<String>,
+    size: (u32, u32),
+
+    shot: bool,
+    clip: Option<Clip>,
+    readbacks: Vec<Readback>,
+    writer: mpsc::SyncSender<Image>,
+}
+
+fn millis() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0)
+}
+
+fn blit(device: &wgpu::Device, (w, h): (u32, u32)) -> BlitPass {
+    BlitPass::new("capture")
+        .texture(|t| t.size([w, h, 1u32]))
+        .build(device)
+}
+
+impl Capture {
+    pub fn new(device: &wgpu::Device, (w, h): (usize, usize)) -> Self {
+        let size = (w as u32, h as u32);
+        let (blit, shaders) = record_shaders(|| blit(device, size));
+
+        let (writer, images) = mpsc::sync_channel::<Image>(MAX_QUEUED);
+        std::thread::spawn(move || {
+            for image in images {
+                if let Some(dir) = image.path.parent() {
+                    let _ = std::fs::create_dir_all(dir);
+                }
+                match image::save_buffer(&image.path, &image.rgba, image.width, image.height, image::ColorType::Rgba8) {
+                    Ok(()) => log::debug!("Saved {:?}", image.path),
+                    Err(e) => log::error!("Failed to save {:?}: {}", image.path, e),
+                }
+            }
+        });
+
+        Self {
+            blit,
+            shaders,
+            size,
+
+            shot: false,
+            clip: None,
+            readbacks: (0..READBACKS).map(|_| Readback::new(device, size)).collect(),
+            writer,
+        }
+    }
+
+    /// Match the texture and buffers to the surface's size, before the frame
+    /// is drawn into `view`. Frames still being copied out are dropped.
+    pub fn resize(&mut self, device: &wgpu::Device, (w, h): (usize, usize)) {
+        let size = (w as u32, h as u32);
+        if size == self.size {
+            return;
+        }
+        log::debug!("Resizing the capture target to {}x{}", w, h);
+
+        let dropped = self.readbacks.iter().filter(|r| r.path.is_some()).count();
+        if dropped > 0 {
+            log::warn!("Capture resized, dropped {} frames", dropped);
+        }
+
+        self.size = size;
+        self.blit = blit(device, size);
+        self.readbacks = (0..READBACKS).map(|_| Readback::new(device, size)).collect();
+    }
+
+    /// Rebuild the blit if any of the `changed` shaders are its own.
+    pub fn reload(&mut self, device: &wgpu::Device, changed: &[String]) {
+        if changed.iter().any(|c| self.shaders.contains(c)) {
+            self.blit = blit(device, self.size);
+        }
+    }
+
+    /// Whether this frame is captured, so should be drawn into `view`.
+    pub fn active(&self) -> bool {
+        self.shot || self.clip.is_some()
+    }
+
+    /// Where to draw the frame while capturing.
+    pub fn view(&self) -> &wgpu::RawTextureView {
+        self.blit.view()
+    }
+
+    /// F11 for a still, F12 to start or stop a clip. Returns whether the key
+    /// was one of them.
+    pub fn key(&mut self, key: Key, stage: &str) -> bool {
+        match key {
+            Key::F11 => self.shot = true,
+            Key::F12 => {
+                self.clip = match self.clip.take() {
+                    Some(clip) => {
+                        log::info!("Captured {} frames to {:?}", clip.frame, clip.dir);
+                        None
+                    }
+                    None => {
+                        let dir = PathBuf::from(CAPTURE_DIR).join(format!("{}_{}", millis(), stage));
+                        log::info!("Capturing to {:?}", dir);
+                        Some(Clip { dir, frame: 0 })
+                    }
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Copy the frame drawn into `view` out, and show it on `target`.
+    pub fn encode(&mut self, frame: &mut Frame, target: &wgpu::RawTextureView, stage: &str, t: f32) {
+        self.blit.encode(frame, target);
+
+        let path = match self.clip.as_mut() {
+            Some(clip) => {
+                clip.frame += 1;
+                clip.dir.join(format!("{:06}_{}_{:.3}.png", clip.frame, stage, t))
+            }
+            None => PathBuf::from(CAPTURE_DIR).join(format!("{}_{}_{:.3}.png", millis(), stage, t)),
+        };
+        if self.shot {
+            log::info!("Saving {:?}", path);
+            self.shot = false;
+        }
+
+        let readback = match self.readbacks.iter_mut().find(|r| r.path.is_none()) {
+            Some(readback) => readback,
+            None => {
+                log::warn!("Capture can't keep up, dropped {:?}", path);
+                return;
+            }
+        };
+
+        let (w, h) = self.size;
+        let row = Readback::row(self.size);
+        frame.encoder().copy_texture_to_buffer(
+            self.blit.texture().as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &readback.buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(row),
+                    rows_per_image: Some(h),
+                },
+            },
+            wgpu::Extent3d {
+                width: w,
+                height: h,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        readback.path = Some(path);
+    }
+
+    /// Map the frames copied out last frame, and hand the mapped ones to the writer.
+    pub fn update(&mut self, device: &wgpu::Device) {
+        if self.readbacks.iter().all(|r| r.path.is_none()) {
+            return;
+        }
+
+        for readback in self.readbacks.iter_mut().filter(|r| r.path.is_some() && !r.submitted) {
+            let mapped = readback.mapped.clone();
+            readback.buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+                if result.is_ok() {
+                    mapped.store(true, Ordering::Release);
+                }
+            });
+            readback.submitted = true;
+        }
+        device.poll(wgpu::Maintain::Poll);
+
+        let (w, h) = self.size;
+        let bgra = matches!(
+            self.blit.texture().format(),
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        );
+
+        for readback in self.readbacks.iter_mut().filter(|r| r.mapped.load(Ordering::Acquire)) {
+            let rgba = {
+                let data = readback.buffer.slice(..).get_mapped_range();
+                let row = data.len() / h as usize;
+                let mut rgba = Vec::with_capacity((w * h * 4) as usize);
+                for y in 0..h as usize {
+                    rgba.extend_from_slice(&data[y * row..y * row + w as usize * 4]);
+                }
+                if bgra {
+                    for px in rgba.chunks_exact_mut(4) {
+                        px.swap(0, 2);
+                    }
+                }
+                rgba
+            };
+            readback.buffer.unmap();
+            readback.submitted = false;
+            readback.mapped.store(false, Ordering::Release);
+
+            let image = Image {
+                path: readback.path.take().unwrap_or_default(),
+                width: w,
+                height: h,
+                rgba,
+            };
+            if let Err(mpsc::TrySendError::Full(image)) = self.writer.try_send(image) {
+                log::warn!("Capture can't keep up, dropped {:?}", image.path);
+            }
+        }
+    }
+}
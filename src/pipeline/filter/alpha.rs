@@ -5,6 +5,8 @@ use lib::gfx::pass::FilterPass;
 use lib::gfx::uniform::UniformStorage;
 use lib::gfx::wgpu;
 
+use crate::pipeline::shader;
+
 pub struct AlphaPass {
     filter: FilterPass,
     uniform: UniformStorage<f32>,
@@ -13,7 +15,7 @@ pub struct AlphaPass {
 impl AlphaPass {
     pub fn new(device: &wgpu::Device) -> Self {
         let uniform = UniformStorage::new(device, "alpha", 1.0);
-        let filter = FilterPass::new(device, "alpha", "alpha.frag.spv", Some(&uniform.uniform));
+        let filter = FilterPass::new(device, "alpha", shader("alpha.frag.spv"), Some(&uniform.uniform));
 
         Self {
             filter,
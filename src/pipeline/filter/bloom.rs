@@ -5,6 +5,8 @@ use lib::gfx::pass::FilterPass;
 use lib::gfx::uniform::UniformStorage;
 use lib::gfx::wgpu;
 
+use crate::pipeline::shader;
+
 pub struct BloomPass {
     passthrough: FilterPass,
 
@@ -17,13 +19,13 @@ pub struct BloomPass {
 
 impl BloomPass {
     pub fn new(device: &wgpu::Device, size: (usize, usize), amount: f32) -> Self {
-        let passthrough = FilterPass::new_sized::<()>(device, "passthrough", "passthrough.frag.spv", None, size);
+        let passthrough = FilterPass::new_sized::<()>(device, "passthrough", shader("passthrough.frag.spv"), None, size);
 
         let bloom_u = UniformStorage::new(device, "bloom", (size.0 as f32, size.1 as f32));
-        let bloom = FilterPass::new_sized(device, "bloom", "bloom.frag.spv", Some(&bloom_u.uniform), size);
+        let bloom = FilterPass::new_sized(device, "bloom", shader("bloom.frag.spv"), Some(&bloom_u.uniform), size);
 
         let tile_u = UniformStorage::new(device, "bloom_tile", amount);
-        let tile = FilterPass::new_composite_sized(device, "bloom_tile", 2, Some("bloom_tile.frag.spv"), Some(&tile_u.uniform), size);
+        let tile = FilterPass::new_composite_sized(device, "bloom_tile", 2, Some(shader("bloom_tile.frag.spv")), Some(&tile_u.uniform), size);
 
         Self {
             passthrough,
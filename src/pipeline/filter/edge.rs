@@ -5,6 +5,8 @@ use lib::gfx::pass::FilterPass;
 use lib::gfx::uniform::UniformStorage;
 use lib::gfx::wgpu;
 
+use crate::pipeline::shader;
+
 pub struct EdgePass {
     filter: FilterPass,
     uniform: UniformStorage<(f32, f32, f32)>,
@@ -13,7 +15,7 @@ pub struct EdgePass {
 impl EdgePass {
     pub fn new(device: &wgpu::Device, size: (usize,  usize), fr: f32) -> Self {
         let uniform = UniformStorage::new(device, "justedge", (size.0 as f32, size.1 as f32, fr));
-        let filter = FilterPass::new(device, "justedge", "justedge.frag.spv", Some(&uniform.uniform));
+        let filter = FilterPass::new(device, "justedge", shader("justedge.frag.spv"), Some(&uniform.uniform));
 
         Self {
             filter,
@@ -5,6 +5,8 @@ use lib::gfx::pass::FilterPass;
 use lib::gfx::uniform::UniformStorage;
 use lib::gfx::wgpu;
 
+use crate::pipeline::shader;
+
 pub struct InvertPass {
     filter: FilterPass,
     uniform: UniformStorage<f32>,
@@ -13,7 +15,7 @@ pub struct InvertPass {
 impl InvertPass {
     pub fn new(device: &wgpu::Device) -> Self {
         let uniform = UniformStorage::new(device, "invert", 1.0);
-        let filter = FilterPass::new(device, "invert", "invert.frag.spv", Some(&uniform.uniform));
+        let filter = FilterPass::new(device, "invert", shader("invert.frag.spv"), Some(&uniform.uniform));
 
         Self {
             filter,
@@ -5,6 +5,8 @@ use lib::gfx::pass::FilterPass;
 use lib::gfx::uniform::UniformStorage;
 use lib::gfx::wgpu;
 
+use crate::pipeline::shader;
+
 
 #[repr(C)]
 #[derive(Clone, Copy, Debug)]
@@ -26,7 +28,7 @@ impl ShakePass {
             speed,
             amount
         });
-        let filter = FilterPass::new(device, "shake", "justshake.frag.spv", Some(&uniform.uniform));
+        let filter = FilterPass::new(device, "shake", shader("justshake.frag.spv"), Some(&uniform.uniform));
 
         Self {
             filter,
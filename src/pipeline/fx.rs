@@ -1,9 +1,14 @@
+use std::sync::Arc;
+
 use lib::gfx::frame::Frame;
-use lib::gfx::pass::FilterPass;
-use lib::gfx::uniform::UniformStorage;
+use lib::gfx::uniform::{Uniform, UniformStorage};
 use lib::gfx::wgpu;
+use parking_lot::Mutex;
+
+use crate::pipeline::{FilterShader, Targets};
+use crate::util::CfgFile;
 
-use crate::pipeline::{AlphaPass, BloomPass};
+type View = wgpu::RawTextureView;
 
 #[derive(Default, Clone, Copy)]
 #[repr(C)]
@@ -21,59 +26,452 @@ pub struct Fx {
     pub edge: f32,
     pub mega: f32,
     pub shake: f32,
+    pub abberation: f32,
+    pub discolor: f32,
+    pub psych: f32,
+    pub contour: f32,
+    pub simpleglitch: f32,
+    pub flip_h: f32,
+    pub flip_v: f32,
+    pub rotate_cw: f32,
+    pub rotate_ccw: f32,
+    /// Size of the chain's targets in pixels, set by `FxPass`
+    pub res: [f32; 2],
 }
 
-pub struct FxPass {
-    pub state: UniformStorage<Fx>,
+impl Fx {
+    /// A field by name, for setting from config.
+    pub fn field(&mut self, name: &str) -> Option<&mut f32> {
+        Some(match name {
+            "pause" => &mut self.pause,
+            "glitch" => &mut self.glitch,
+            "glitch_mo" => &mut self.glitch_mo,
+            "vhs" => &mut self.vhs,
+            "red" => &mut self.red,
+            "flash" => &mut self.flash,
+            "bloom" => &mut self.bloom,
+            "invert" => &mut self.invert,
+            "edge" => &mut self.edge,
+            "mega" => &mut self.mega,
+            "shake" => &mut self.shake,
+            "abberation" => &mut self.abberation,
+            "discolor" => &mut self.discolor,
+            "psych" => &mut self.psych,
+            "contour" => &mut self.contour,
+            "simpleglitch" => &mut self.simpleglitch,
+            "flip_h" => &mut self.flip_h,
+            "flip_v" => &mut self.flip_v,
+            "rotate_cw" => &mut self.rotate_cw,
+            "rotate_ccw" => &mut self.rotate_ccw,
+            _ => return None,
+        })
+    }
+}
+
+/// A pass in an `FxPass` chain.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Effect {
+    Edge,
+    Bloom,
+    /// Shake, flash, red and mega
+    Shake,
+    Glitch,
+    Vhs,
+    Pause,
+    Invert,
+    Abberation,
+    Discolor,
+    Psych,
+    Contour,
+    SimpleGlitch,
+    FlipH,
+    FlipV,
+    RotateCw,
+    RotateCcw,
+}
+
+crate::util::cfg_enum!(Effect {
+    Edge = "edge",
+    Bloom = "bloom",
+    Shake = "shake",
+    Glitch = "glitch",
+    Vhs = "vhs",
+    Pause = "pause",
+    Invert = "invert",
+    Abberation = "abberation",
+    Discolor = "discolor",
+    Psych = "psych",
+    Contour = "contour",
+    SimpleGlitch = "simpleglitch",
+    FlipH = "flip_h",
+    FlipV = "flip_v",
+    RotateCw = "rotate_cw",
+    RotateCcw = "rotate_ccw",
+});
+
+/// The chain every stage had before chains were configurable.
+pub const DEFAULT_CHAIN: &[Effect] = &[
+    Effect::Edge,
+    Effect::Bloom,
+    Effect::Shake,
+    Effect::Glitch,
+    Effect::Vhs,
+    Effect::Pause,
+    Effect::Invert,
+];
+
+impl Effect {
+    /// Whether the pass would change anything. Flips, rotations and the
+    /// simple glitch are on for any amount above zero.
+    fn active(self, fx: &Fx) -> bool {
+        match self {
+            Effect::Edge => fx.edge + fx.mega > 0.0,
+            Effect::Bloom => fx.bloom > 0.0,
+            Effect::Shake => fx.shake > 0.0 || fx.flash > 0.0 || fx.red > 0.0 || fx.mega > 0.0,
+            Effect::Glitch => fx.glitch > 0.0 || fx.glitch_mo > 0.0 || fx.mega > 0.0,
+            Effect::Vhs => fx.vhs > 0.0,
+            Effect::Pause => fx.pause > 0.0,
+            Effect::Invert => fx.invert > 0.0,
+            Effect::Abberation => fx.abberation > 0.0,
+            Effect::Discolor => fx.discolor > 0.0,
+            Effect::Psych => fx.psych > 0.0,
+            Effect::Contour => fx.contour > 0.0,
+            Effect::SimpleGlitch => fx.simpleglitch > 0.0,
+            Effect::FlipH => fx.flip_h > 0.0,
+            Effect::FlipV => fx.flip_v > 0.0,
+            Effect::RotateCw => fx.rotate_cw > 0.0,
+            Effect::RotateCcw => fx.rotate_ccw > 0.0,
+        }
+    }
+}
+
+/// One shader of the chain, drawn from either of its two targets.
+struct Step {
+    filter: FilterShader,
+    /// Reading the first or second target, then any extra inputs
+    images: [wgpu::BindGroup; 2],
+}
+
+impl Step {
+    fn new(
+        device: &wgpu::Device,
+        name: &str,
+        shader: &str,
+        uniform: Option<&Uniform>,
+        targets: &[Arc<View>],
+        extra: Option<&View>,
+    ) -> Self {
+        let inputs = if extra.is_some() { 2 } else { 1 };
+        let filter = FilterShader::new(device, &format!("fx_{}", name), shader, inputs, uniform);
+        let images = [0, 1].map(|i| {
+            let mut views = vec![&*targets[i]];
+            views.extend(extra);
+            filter.images(device, &views)
+        });
+
+        Self { filter, images }
+    }
+
+    fn encode(&self, frame: &mut Frame, from: usize, view: &View) {
+        self.filter.encode(frame, &self.images[from], view);
+    }
+}
+
+enum Node {
+    /// Reads the shared `Fx` uniform, or nothing
+    Filter(Step),
+    /// Has its own uniform, filled in from `Fx` on update
+    Own(Step, UniformStorage<(f32, f32)>),
+    /// Blurs into the spare target, then tiles the blur over its input
+    Bloom {
+        blur: Step,
+        blur_u: UniformStorage<(f32, f32)>,
+        tile: Step,
+        tile_u: UniformStorage<f32>,
+    },
+}
+
+impl Node {
+    fn new(device: &wgpu::Device, effect: Effect, state: &UniformStorage<Fx>, targets: &[Arc<View>], size: (usize, usize)) -> Self {
+        let shared = |name: &str, shader: &str| Node::Filter(Step::new(device, name, shader, Some(state.as_ref()), targets, None));
+        let own = |name: &str, shader: &str| {
+            let uniform = UniformStorage::new(device, name, (0.0, 0.0));
+            Node::Own(Step::new(device, name, shader, Some(&uniform.uniform), targets, None), uniform)
+        };
+        let plain = |name: &str, shader: &str| Node::Filter(Step::new(device, name, shader, None, targets, None));
+
+        match effect {
+            Effect::Edge => shared("edge", "edge.frag.spv"),
+            Effect::Bloom => {
+                let blur_u = UniformStorage::new(device, "bloom", (size.0 as f32, size.1 as f32));
+                let blur = Step::new(device, "bloom", "bloom.frag.spv", Some(&blur_u.uniform), targets, None);
+                let tile_u = UniformStorage::new(device, "bloom_tile", 0.0);
+                let tile = Step::new(device, "bloom_tile", "bloom_tile.frag.spv", Some(&tile_u.uniform), targets, Some(&*targets[2]));
+                Node::Bloom { blur, blur_u, tile, tile_u }
+            }
+            Effect::Shake => shared("shake", "shake.frag.spv"),
+            Effect::Glitch => shared("glitch", "glitch.frag.spv"),
+            Effect::Vhs => shared("vhs", "vhs.frag.spv"),
+            Effect::Pause => shared("pause", "pause.frag.spv"),
+            Effect::Invert => own("invert", "invert.frag.spv"),
+            Effect::Abberation => own("abberation", "abberation.frag.spv"),
+            Effect::Discolor => shared("discolor", "discolor.frag.spv"),
+            Effect::Psych => shared("psych", "psych.frag.spv"),
+            Effect::Contour => shared("contour", "contour.frag.spv"),
+            Effect::SimpleGlitch => own("simpleglitch", "simpleglitch.frag.spv"),
+            Effect::FlipH => plain("flip_h", "flip_h.frag.spv"),
+            Effect::FlipV => plain("flip_v", "flip_v.frag.spv"),
+            Effect::RotateCw => plain("rotate_cw", "rotate_cw.frag.spv"),
+            Effect::RotateCcw => plain("rotate_ccw", "rotate_ccw.frag.spv"),
+        }
+    }
+
+    fn reload(&mut self, device: &wgpu::Device, changed: &[String]) {
+        match self {
+            Node::Filter(step) | Node::Own(step, _) => step.filter.reload(device, changed),
+            Node::Bloom { blur, tile, .. } => {
+                blur.filter.reload(device, changed);
+                tile.filter.reload(device, changed);
+            }
+        }
+    }
 
-    edge: FilterPass,
-    shake: FilterPass,
-    glitch: FilterPass,
-    vhs: FilterPass,
-    pause: FilterPass,
+    fn upload(&self, frame: &mut Frame) {
+        match self {
+            Node::Filter(_) => {}
+            Node::Own(_, u) => u.upload(frame),
+            Node::Bloom { tile_u, .. } => tile_u.upload(frame),
+        }
+    }
 
-    invert: FilterPass,
-    invert_u: UniformStorage<f32>,
+    /// Draw from target `from` into the other one.
+    fn encode(&self, frame: &mut Frame, from: usize, targets: &[Arc<View>]) {
+        let to = &targets[1 - from];
+        match self {
+            Node::Filter(step) | Node::Own(step, _) => step.encode(frame, from, to),
+            Node::Bloom { blur, tile, .. } => {
+                blur.encode(frame, from, &targets[2]);
+                tile.encode(frame, from, to);
+            }
+        }
+    }
+}
 
+/// Named sets of `Fx` fields, read from `resources/config/fx.presets`.
+///
+/// ```text
+/// glitch_mid=glitch 0.2; vhs 0.4
+/// negative=invert 1.0
+/// ```
+///
+/// Fields a preset doesn't mention are left alone.
+///
+/// Owned by `Stages`, which re-reads the file once a frame when it changes,
+/// and handed to each stage's `FxPass`es as they're built.
+#[derive(Clone)]
+pub struct FxPresets {
+    presets: Arc<Mutex<Presets>>,
+}
 
-    bloom: BloomPass,
-    pub alpha: AlphaPass,
+struct Presets {
+    file: CfgFile,
+    presets: Vec<(String, Vec<(String, f32)>)>,
+}
+
+impl FxPresets {
+    pub fn load() -> Self {
+        let presets = Self {
+            presets: Arc::new(Mutex::new(Presets {
+                file: CfgFile::new("fx.presets"),
+                presets: vec![],
+            })),
+        };
+        presets.reload();
+        presets
+    }
+
+    /// Re-read the file if it changed.
+    pub fn reload(&self) {
+        let mut presets = self.presets.lock();
+        let src = match presets.file.changed() {
+            Some(src) => src,
+            None => return,
+        };
+        let path = presets.file.path().display().to_string();
+
+        let mut check = Fx::default();
+        let mut loaded = vec![];
+        for (i, line) in src.lines().enumerate() {
+            let (name, value) = match line.split_once('=') {
+                Some((name, value)) => (name.trim(), value.trim()),
+                None => continue,
+            };
+
+            let mut fields = vec![];
+            for field in value.split(';').map(str::trim).filter(|f| !f.is_empty()) {
+                let parsed = field
+                    .split_once(' ')
+                    .and_then(|(k, v)| Some((k.trim(), v.trim().parse::<f32>().ok()?)));
+                match parsed {
+                    Some((k, v)) if check.field(k).is_some() => fields.push((k.to_owned(), v)),
+                    _ => log::warn!("{}:{}: bad fx preset field '{}'", path, i + 1, field),
+                }
+            }
+            loaded.push((name.to_owned(), fields));
+        }
+
+        log::debug!("Loaded {} fx presets from {}", loaded.len(), path);
+        presets.presets = loaded;
+    }
+
+    fn get(&self, name: &str) -> Option<Vec<(String, f32)>> {
+        self.presets
+            .lock()
+            .presets
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, fields)| fields.clone())
+    }
+}
+
+/// A move from the current fields to a preset's.
+struct Morph {
+    fields: Vec<(String, f32, f32)>,
+    start: f32,
+    len: f32,
+}
+
+/// Post processing, run as a chain of effects. Effects whose amounts are
+/// all zero, or that are bypassed, are skipped.
+///
+/// The chain draws back and forth between two targets from the stage's
+/// `Targets`, with a third for bloom, and its last step draws into the view
+/// passed to `encode`. Every `FxPass` of a size shares them, so a pass's
+/// input only lasts until the next `FxPass` of that size is encoded.
+pub struct FxPass {
+    pub state: UniformStorage<Fx>,
+    /// Fades the result, applied last
+    pub alpha: UniformStorage<f32>,
+
+    presets: FxPresets,
+    morph: Option<Morph>,
+
+    targets: Vec<Arc<View>>,
+    chain: Vec<(Effect, Node)>,
+    bypass: Vec<Effect>,
+    fade: Step,
 }
 
 impl FxPass {
-    pub fn new(device: &wgpu::Device, size: (usize, usize)) -> Self {
-        let state = UniformStorage::new(device, "fx", Fx::default());
+    pub fn new(device: &wgpu::Device, size: (usize, usize), pool: &Targets, presets: &FxPresets) -> Self {
+        Self::with_chain(device, size, pool, presets, DEFAULT_CHAIN)
+    }
 
-        let edge = FilterPass::new_sized(device, "edge", "edge.frag.spv", Some(state.as_ref()), size);
-        let shake = FilterPass::new_sized(device, "shake", "shake.frag.spv", Some(state.as_ref()), size);
-        let glitch = FilterPass::new_sized(device, "glitch", "glitch.frag.spv", Some(state.as_ref()), size);
-        let vhs = FilterPass::new_sized(device, "vhs", "vhs.frag.spv", Some(state.as_ref()), size);
-        let pause = FilterPass::new_sized(device, "pause", "pause.frag.spv", Some(state.as_ref()), size);
+    pub fn with_chain(
+        device: &wgpu::Device,
+        size: (usize, usize),
+        pool: &Targets,
+        presets: &FxPresets,
+        chain: &[Effect],
+    ) -> Self {
+        let state = UniformStorage::new(device, "fx", Fx {
+            res: [size.0 as f32, size.1 as f32],
+            ..Default::default()
+        });
+        let count = if chain.contains(&Effect::Bloom) { 3 } else { 2 };
+        let targets = (0..count).map(|i| pool.get(device, "fx", size, i)).collect::<Vec<_>>();
+        let chain = chain
+            .iter()
+            .map(|effect| (*effect, Node::new(device, *effect, &state, &targets, size)))
+            .collect();
 
-        let invert_u = UniformStorage::new(device, "invert", 0.0);
-        let invert = FilterPass::new_sized(device, "invert", "invert.frag.spv", Some(&invert_u.uniform), size);
+        let alpha = UniformStorage::new(device, "alpha", 1.0);
+        let fade = Step::new(device, "alpha", "alpha.frag.spv", Some(&alpha.uniform), &targets, None);
 
         Self {
             state,
-            edge,
-            shake,
-            glitch,
-            vhs,
-            pause,
+            alpha,
+
+            presets: presets.clone(),
+            morph: None,
 
-            invert,
-            invert_u,
+            targets,
+            chain,
+            bypass: vec![],
+            fade,
+        }
+    }
 
-            bloom: BloomPass::new(device, size, 0.0),
-            alpha: AlphaPass::new(device),
+    /// Rebuild the pipelines of the effects using any of the `changed` shaders.
+    pub fn reload(&mut self, device: &wgpu::Device, changed: &[String]) {
+        for (_, node) in self.chain.iter_mut() {
+            node.reload(device, changed);
+        }
+        self.fade.filter.reload(device, changed);
+    }
+
+    /// Skip an effect whatever its amount.
+    pub fn bypass(&mut self, effect: Effect, bypass: bool) {
+        self.bypass.retain(|e| *e != effect);
+        if bypass {
+            self.bypass.push(effect);
         }
     }
 
     pub fn update(&mut self, tc: f32, t: f32) {
         self.state.tc = tc;
         self.state.t = t;
-        *self.invert_u = self.state.invert;
-        *self.bloom = self.state.bloom;
+
+        if let Some(morph) = &self.morph {
+            let fr = if morph.len > 0.0 {
+                ((tc - morph.start) / morph.len).clamp(0.0, 1.0)
+            } else {
+                1.0
+            };
+            for (name, from, to) in &morph.fields {
+                if let Some(f) = self.state.field(name) {
+                    *f = from + (to - from) * fr;
+                }
+            }
+            if fr >= 1.0 {
+                self.morph = None;
+            }
+        }
+
+        for (effect, node) in self.chain.iter_mut() {
+            match (effect, node) {
+                (Effect::Bloom, Node::Bloom { tile_u, .. }) => **tile_u = self.state.bloom,
+                (Effect::Invert, Node::Own(_, u)) => **u = (self.state.invert, 0.0),
+                (Effect::Abberation, Node::Own(_, u)) => **u = (tc, self.state.abberation),
+                (Effect::SimpleGlitch, Node::Own(_, u)) => **u = (tc, 0.0),
+                _ => {}
+            }
+        }
+    }
+
+    /// Move from the current fields to a preset's over `morph` seconds of
+    /// song time, or straight away for 0.
+    pub fn recall(&mut self, name: &str, morph: f32) {
+        let fields = match self.presets.get(name) {
+            Some(fields) => fields,
+            None => {
+                log::warn!("No such fx preset '{}'", name);
+                return;
+            }
+        };
+
+        let fields = fields
+            .iter()
+            .map(|(k, to)| {
+                let from = self.state.field(k).copied().unwrap_or(*to);
+                (k.clone(), from, *to)
+            })
+            .collect();
+        self.morph = Some(Morph {
+            fields,
+            start: self.state.tc,
+            len: morph,
+        });
     }
 
     // pub fn ctrl(&mut self, input: Input) {
@@ -89,36 +487,25 @@ impl FxPass {
     // }
 
     pub fn view(&self) -> &wgpu::RawTextureView {
-        self.edge.view(0)
+        &self.targets[0]
     }
 
     pub fn upload(&self, frame: &mut Frame) {
         self.state.upload(frame);
-        self.invert_u.upload(frame);
+        self.alpha.upload(frame);
+        for (_, node) in &self.chain {
+            node.upload(frame);
+        }
     }
 
     pub fn encode(&self, frame: &mut Frame, view: &wgpu::RawTextureView) {
-        self.edge.encode(frame, self.bloom.view());
-        self.bloom.encode(frame, self.shake.view(0));
-        self.shake.encode(frame, self.glitch.view(0));
-        self.glitch.encode(frame, self.vhs.view(0));
-        self.vhs.encode(frame, self.pause.view(0));
-        self.pause.encode(frame, self.invert.view(0));
-        self.invert.encode(frame, self.alpha.view());
-        self.alpha.encode(frame, view);
-    }
-}
-
-impl std::ops::Deref for FxPass {
-    type Target = Fx;
-
-    fn deref(&self) -> &Self::Target {
-        &self.state
-    }
-}
-
-impl std::ops::DerefMut for FxPass {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.state
+        let mut from = 0;
+        for (effect, node) in &self.chain {
+            if effect.active(&self.state) && !self.bypass.contains(effect) {
+                node.encode(frame, from, &self.targets);
+                from = 1 - from;
+            }
+        }
+        self.fade.encode(frame, from, view);
     }
 }
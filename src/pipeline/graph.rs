@@ -0,0 +1,397 @@
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+
+use lib::gfx::frame::Frame;
+use lib::gfx::uniform::Uniform;
+use lib::gfx::wgpu;
+
+const BILLBOARD_SHADER: &str = "billboard.vert.spv";
+
+type View = wgpu::RawTextureView;
+
+/// Intermediate render targets by size, shared by the graphs, `FxPass`es and
+/// `BlitPass`es of every stage.
+///
+/// Owned by `Stages` and handed to each stage as it's built. Passes only draw
+/// into them while encoding and never read what a previous frame left behind,
+/// so stages can share them as long as they don't encode at the same time.
+///
+/// Each kind of pass takes its targets from a set of its own, since a text
+/// `FxPass` is drawn inside a graph source while the graph's other targets
+/// are still waiting to be read.
+#[derive(Clone, Default)]
+pub struct Targets {
+    pool: Arc<Mutex<Vec<((&'static str, (usize, usize)), Vec<Arc<View>>)>>>,
+}
+
+impl Targets {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The `i`th target of a size from one set: `"graph"`, `"fx"` or `"blit"`.
+    pub(crate) fn get(&self, device: &wgpu::Device, set: &'static str, size: (usize, usize), i: usize) -> Arc<View> {
+        let mut pool = self.pool.lock();
+        let views = match pool.iter().position(|(k, _)| *k == (set, size)) {
+            Some(j) => &mut pool[j].1,
+            None => {
+                pool.push(((set, size), vec![]));
+                &mut pool.last_mut().unwrap().1
+            }
+        };
+
+        while views.len() <= i {
+            let view = wgpu::util::TextureBuilder::new(&format!("{}_{}x{}_{}", set, size.0, size.1, views.len()))
+                .size([size.0 as u32, size.1 as u32, 1u32])
+                .usage(wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING)
+                .build(device)
+                .view()
+                .build()
+                .into_raw();
+            views.push(Arc::new(view));
+        }
+        views[i].clone()
+    }
+}
+
+enum Desc<'a> {
+    Source,
+    Filter {
+        shader: &'a str,
+        inputs: Vec<&'a str>,
+        uniform: Option<&'a Uniform>,
+    },
+}
+
+enum Kind {
+    /// Drawn by the stage
+    Source,
+    /// A shader over the node's inputs
+    Filter {
+        filter: FilterShader,
+        images: wgpu::BindGroup,
+    },
+}
+
+/// A full screen shader over other targets, with the same layout as a
+/// composite `FilterPass`: `imgs[]`, `samp` and the number of inputs in set
+/// 0, and the uniform in set 1.
+///
+/// The inputs are bound separately with `images`, so the same shader can
+/// read from whichever targets are free.
+pub(crate) struct FilterShader {
+    label: String,
+    shader: String,
+    pipeline: wgpu::RenderPipeline,
+    image_layout: wgpu::BindGroupLayout,
+    uniform_layout: Option<wgpu::BindGroupLayout>,
+    sampler: wgpu::Sampler,
+    uniform: Option<wgpu::BindGroup>,
+    /// Read by composite shaders as `u.n`
+    count: Uniform<u32>,
+}
+
+impl FilterShader {
+    pub(crate) fn new(device: &wgpu::Device, label: &str, shader: &str, inputs: usize, uniform: Option<&Uniform>) -> Self {
+        let count = Uniform::new(device, label, Some(&(inputs as u32)));
+        let image_layout = wgpu::util::BindGroupLayoutBuilder::new(label)
+            .textures(wgpu::ShaderStages::FRAGMENT, inputs)
+            .sampler(wgpu::ShaderStages::FRAGMENT)
+            .uniform(wgpu::ShaderStages::FRAGMENT)
+            .build(device);
+        let sampler = wgpu::util::SamplerBuilder::new(label).build(device);
+
+        let uniform_layout = uniform.map(|_| {
+            wgpu::util::BindGroupLayoutBuilder::new(label)
+                .uniform(wgpu::ShaderStages::FRAGMENT)
+                .build(device)
+        });
+        let uniform = uniform.zip(uniform_layout.as_ref()).map(|(uniform, layout)| {
+            wgpu::util::BindGroupBuilder::new(label)
+                .uniform(uniform)
+                .build(device, layout)
+        });
+
+        let pipeline = filter_pipeline(device, label, shader, &image_layout, uniform_layout.as_ref());
+
+        Self {
+            label: label.to_owned(),
+            shader: shader.to_owned(),
+            pipeline,
+            image_layout,
+            uniform_layout,
+            sampler,
+            uniform,
+            count,
+        }
+    }
+
+    /// Rebuild the pipeline if any of the `changed` shaders are its own.
+    pub(crate) fn reload(&mut self, device: &wgpu::Device, changed: &[String]) {
+        if changed.iter().any(|c| *c == self.shader || c == BILLBOARD_SHADER) {
+            self.pipeline = filter_pipeline(device, &self.label, &self.shader, &self.image_layout, self.uniform_layout.as_ref());
+        }
+    }
+
+    /// Bind the targets the shader reads, in order.
+    pub(crate) fn images(&self, device: &wgpu::Device, views: &[&View]) -> wgpu::BindGroup {
+        wgpu::util::BindGroupBuilder::new(&self.label)
+            .textures(views)
+            .sampler(&self.sampler)
+            .uniform(&self.count)
+            .build(device, &self.image_layout)
+    }
+
+    pub(crate) fn encode(&self, frame: &mut Frame, images: &wgpu::BindGroup, target: &View) {
+        let mut pass = wgpu::util::RenderPassBuilder::new()
+            .color_attachment(target, |a| a)
+            .begin(frame);
+
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, images, &[]);
+        if let Some(uniform) = &self.uniform {
+            pass.set_bind_group(1, uniform, &[]);
+        }
+        pass.draw(0..3, 0..1);
+    }
+}
+
+struct Node {
+    name: String,
+    kind: Kind,
+    /// Pooled target, or none for the output
+    target: Option<Arc<View>>,
+}
+
+/// Passes wired up by name, with intermediate targets taken from a pool.
+///
+/// Sources are drawn by the stage when the graph asks for them, and filters
+/// are shaders over other nodes. Only nodes the output depends on are drawn,
+/// in dependency order. Targets are reused once every node reading them has
+/// been drawn.
+///
+/// Targets come from the `Targets` the stage was built with, and may hold
+/// another stage's drawing, so sources are cleared to transparent before
+/// they're drawn. Sources the stage doesn't draw are left clear.
+///
+/// ```ignore
+/// let graph = Graph::new("aqua", (640, 360))
+///     .source("scene")
+///     .source("text")
+///     .filter("composite", "composite_add.frag.spv", &["scene", "text"], None)
+///     .output("composite")
+///     .build(device, targets);
+///
+/// graph.encode(frame, self.fx.view(), |frame, node, target| match node {
+///     "scene" => self.scene.encode(frame, target),
+///     "text" => self.text.encode(frame, target),
+///     _ => {}
+/// });
+/// ```
+fn filter_pipeline(
+    device: &wgpu::Device,
+    label: &str,
+    shader: &str,
+    image_layout: &wgpu::BindGroupLayout,
+    uniform_layout: Option<&wgpu::BindGroupLayout>,
+) -> wgpu::RenderPipeline {
+    let vs = lib::resource::read_shader(device, super::shader(BILLBOARD_SHADER));
+    let fs = lib::resource::read_shader(device, super::shader(shader));
+    let mut pipeline = wgpu::util::PipelineBuilder::new(label).with_layout(image_layout);
+    if let Some(layout) = uniform_layout {
+        pipeline = pipeline.with_layout(layout);
+    }
+    pipeline.render(&vs).fragment(&fs).build(device)
+}
+
+pub struct Graph {
+    nodes: Vec<Node>,
+}
+
+pub struct GraphBuilder<'a> {
+    name: &'a str,
+    size: (usize, usize),
+    nodes: Vec<(&'a str, Desc<'a>)>,
+    output: Option<&'a str>,
+}
+
+impl Graph {
+    pub fn new(name: &str, size: (usize, usize)) -> GraphBuilder {
+        GraphBuilder {
+            name,
+            size,
+            nodes: vec![],
+            output: None,
+        }
+    }
+
+    /// Rebuild the pipelines of the filters using any of the `changed` shaders.
+    pub fn reload(&mut self, device: &wgpu::Device, changed: &[String]) {
+        for node in self.nodes.iter_mut() {
+            if let Kind::Filter { filter, .. } = &mut node.kind {
+                filter.reload(device, changed);
+            }
+        }
+    }
+
+    /// Draw every node, asking `source` to draw the sources into the targets it's given.
+    pub fn encode<F>(&self, frame: &mut Frame, view: &View, mut source: F)
+    where
+        F: FnMut(&mut Frame, &str, &View),
+    {
+        for node in &self.nodes {
+            let target = node.target.as_deref().unwrap_or(view);
+            match &node.kind {
+                Kind::Source => {
+                    wgpu::util::RenderPassBuilder::new()
+                        .color_attachment(target, |a| a.color(|op| op.clear(wgpu::Color::TRANSPARENT)))
+                        .begin(frame);
+                    source(frame, &node.name, target);
+                }
+                Kind::Filter { filter, images } => filter.encode(frame, images, target),
+            }
+        }
+    }
+}
+
+impl<'a> GraphBuilder<'a> {
+    /// A node the stage draws itself, like a scene, synth or text pass.
+    pub fn source(mut self, name: &'a str) -> Self {
+        self.nodes.push((name, Desc::Source));
+        self
+    }
+
+    /// A shader over other nodes, with an optional uniform the stage uploads.
+    pub fn filter(mut self, name: &'a str, shader: &'a str, inputs: &[&'a str], uniform: Option<&'a Uniform>) -> Self {
+        self.nodes.push((
+            name,
+            Desc::Filter {
+                shader,
+                inputs: inputs.to_vec(),
+                uniform,
+            },
+        ));
+        self
+    }
+
+    /// The node drawn into the view passed to `encode`.
+    pub fn output(mut self, name: &'a str) -> Self {
+        self.output = Some(name);
+        self
+    }
+
+    fn index(&self, name: &str) -> usize {
+        self.nodes
+            .iter()
+            .position(|(n, _)| *n == name)
+            .unwrap_or_else(|| panic!("graph {}: no such node {}", self.name, name))
+    }
+
+    fn inputs(&self, i: usize) -> &[&'a str] {
+        match &self.nodes[i].1 {
+            Desc::Source => &[],
+            Desc::Filter { inputs, .. } => inputs,
+        }
+    }
+
+    /// Add `i` to `order` after everything it depends on.
+    fn visit(&self, i: usize, stack: &mut Vec<usize>, order: &mut Vec<usize>) {
+        if order.contains(&i) {
+            return;
+        }
+        if stack.contains(&i) {
+            panic!("graph {}: {} depends on itself", self.name, self.nodes[i].0);
+        }
+
+        stack.push(i);
+        for input in self.inputs(i) {
+            self.visit(self.index(input), stack, order);
+        }
+        stack.pop();
+        order.push(i);
+    }
+
+    pub fn build(self, device: &wgpu::Device, pool: &Targets) -> Graph {
+        let output = self
+            .output
+            .unwrap_or_else(|| panic!("graph {}: no output", self.name));
+        let output = self.index(output);
+
+        // Only what the output depends on, each node after its inputs
+        let mut order = vec![];
+        self.visit(output, &mut vec![], &mut order);
+
+        // Where each node is last read, so its target can be handed on after that
+        let mut last_read = vec![0; self.nodes.len()];
+        for (step, &i) in order.iter().enumerate() {
+            for input in self.inputs(i) {
+                last_read[self.index(input)] = step;
+            }
+        }
+
+        let mut slots: Vec<Option<usize>> = vec![None; self.nodes.len()];
+        let mut free: Vec<bool> = vec![];
+        for (step, &i) in order.iter().enumerate() {
+            if i != output {
+                let slot = match free.iter().position(|f| *f) {
+                    Some(slot) => slot,
+                    None => {
+                        free.push(true);
+                        free.len() - 1
+                    }
+                };
+                free[slot] = false;
+                slots[i] = Some(slot);
+            }
+            for input in self.inputs(i) {
+                let j = self.index(input);
+                if last_read[j] == step {
+                    if let Some(slot) = slots[j] {
+                        free[slot] = true;
+                    }
+                }
+            }
+        }
+        let targets = slots
+            .iter()
+            .map(|slot| slot.map(|slot| pool.get(device, "graph", self.size, slot)))
+            .collect::<Vec<_>>();
+        log::debug!(
+            "Graph {} draws {} nodes with {} pooled targets",
+            self.name,
+            order.len(),
+            free.len()
+        );
+
+        let nodes = order
+            .iter()
+            .map(|&i| {
+                let (name, desc) = &self.nodes[i];
+                let label = format!("graph_{}_{}", self.name, name);
+
+                let kind = match desc {
+                    Desc::Source => Kind::Source,
+                    Desc::Filter { shader, inputs, uniform } => {
+                        let views = inputs
+                            .iter()
+                            .map(|input| targets[self.index(input)].as_deref().expect("the output is never an input"))
+                            .collect::<Vec<_>>();
+
+                        let filter = FilterShader::new(device, &label, shader, views.len(), *uniform);
+                        let images = filter.images(device, &views);
+                        Kind::Filter { filter, images }
+                    }
+                };
+
+                Node {
+                    name: name.to_string(),
+                    kind,
+                    target: targets[i].clone(),
+                }
+            })
+            .collect();
+
+        Graph { nodes }
+    }
+}
@@ -2,6 +2,12 @@ mod phong; pub use phong::Phong;
 mod animated; pub use animated::{Animated, Material as AnimatedMaterial, MaterialDesc as AnimatedMaterialDesc};
 
 mod fx; pub use fx::*;
+mod graph; pub use graph::*;
+mod resolution; pub use resolution::*;
+mod output; pub use output::*;
+mod warp; pub use warp::*;
+mod capture; pub use capture::*;
+mod shaders; pub use shaders::*;
 
 mod stencil; pub use stencil::*;
 mod scroll; pub use scroll::*;
@@ -11,3 +17,6 @@ mod typography; pub use typography::*;
 mod synth; pub use synth::*;
 mod filter; pub use filter::*;
 mod blit; pub use blit::*;
+
+#[cfg(test)]
+mod stencil_test;
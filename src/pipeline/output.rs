@@ -0,0 +1,189 @@
+use parking_lot::Mutex;
+
+use lib::math::{Matrix4, Vector4};
+use lib::prelude::*;
+
+use crate::util::{cfg_enum, stage_config, StageCfg};
+
+const OUTPUT_CFG: &str = "output.cfg";
+
+/// How a stage's image is scaled up to the output.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scale {
+    /// Blocky pixels, scaled to fill as much of the output as fits
+    Nearest,
+    /// Blocky pixels, all the same size, scaled by a whole number
+    Integer,
+    /// Smooth
+    Bilinear,
+}
+
+cfg_enum!(Scale {
+    Nearest = "nearest",
+    Integer = "integer",
+    Bilinear = "bilinear",
+});
+
+impl Scale {
+    fn next(self) -> Self {
+        match self {
+            Scale::Nearest => Scale::Integer,
+            Scale::Integer => Scale::Bilinear,
+            Scale::Bilinear => Scale::Nearest,
+        }
+    }
+}
+
+/// Clockwise rotation of the whole image.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rotation {
+    None,
+    Cw90,
+    Cw180,
+    Cw270,
+}
+
+cfg_enum!(Rotation {
+    None = "0",
+    Cw90 = "90",
+    Cw180 = "180",
+    Cw270 = "270",
+});
+
+impl Rotation {
+    fn next(self) -> Self {
+        match self {
+            Rotation::None => Rotation::Cw90,
+            Rotation::Cw90 => Rotation::Cw180,
+            Rotation::Cw180 => Rotation::Cw270,
+            Rotation::Cw270 => Rotation::None,
+        }
+    }
+
+    fn degrees(self) -> f32 {
+        match self {
+            Rotation::None => 0.0,
+            Rotation::Cw90 => 90.0,
+            Rotation::Cw180 => 180.0,
+            Rotation::Cw270 => 270.0,
+        }
+    }
+
+    /// Whether the image is on its side.
+    pub fn sideways(self) -> bool {
+        matches!(self, Rotation::Cw90 | Rotation::Cw270)
+    }
+}
+
+stage_config!(
+    #[derive(Clone)]
+    OutputConfig {
+        scale: Scale = Scale::Bilinear,
+        /// For rear projection
+        flip_h: bool = false,
+        flip_v: bool = false,
+        /// For portrait LED walls
+        rotate: Rotation = Rotation::None,
+        /// Letterbox and pillarbox bars
+        border: Vector4 = v4(0.0, 0.0, 0.0, 1.0),
+    }
+);
+
+impl OutputConfig {
+    /// Maps the output's texture coords, centered on 0, to where they sample
+    /// the stage's image.
+    pub fn transform(&self) -> Matrix4 {
+        let flip = Matrix4::from_nonuniform_scale(
+            if self.flip_h { -1.0 } else { 1.0 },
+            if self.flip_v { -1.0 } else { 1.0 },
+            1.0,
+        );
+        // Texture coords point down, so this turns the other way
+        let rotate = Matrix4::from_angle_z(Rad(-self.rotate.degrees().to_radians()));
+        rotate * flip
+    }
+
+    pub fn nearest(&self) -> bool {
+        self.scale != Scale::Bilinear
+    }
+
+    pub fn border(&self) -> wgpu::Color {
+        wgpu::Color {
+            r: self.border.x as f64,
+            g: self.border.y as f64,
+            b: self.border.z as f64,
+            a: self.border.w as f64,
+        }
+    }
+}
+
+struct Shared {
+    cfg: Option<StageCfg<OutputConfig>>,
+    /// Bumped on every change, so blits know when to re-upload
+    generation: usize,
+    /// Size of the surface, in pixels
+    size: (usize, usize),
+}
+
+/// How every stage's final blit is put on the output, shared so it survives
+/// stage changes. Read from `resources/config/output.cfg`.
+static OUTPUT: Mutex<Shared> = parking_lot::const_mutex(Shared {
+    cfg: None,
+    generation: 0,
+    size: (1920, 1080),
+});
+
+fn with<R>(f: impl FnOnce(&mut StageCfg<OutputConfig>, &mut usize) -> R) -> R {
+    let mut shared = OUTPUT.lock();
+    let Shared { cfg, generation, .. } = &mut *shared;
+    let cfg = cfg.get_or_insert_with(|| StageCfg::load(OUTPUT_CFG));
+    f(cfg, generation)
+}
+
+/// The current output settings, and a number that changes whenever they do.
+pub fn output() -> (OutputConfig, usize) {
+    with(|cfg, generation| ((**cfg).clone(), *generation))
+}
+
+/// The size of the surface the show is drawn on, in pixels, which stages are
+/// letterboxed against.
+pub fn output_size() -> (usize, usize) {
+    OUTPUT.lock().size
+}
+
+/// Set every frame from the surface being drawn to, before any stage draws.
+pub fn set_output_size(size: (usize, usize)) {
+    OUTPUT.lock().size = size;
+}
+
+/// Pick up changes to `output.cfg`.
+pub fn reload_output() {
+    with(|cfg, generation| {
+        if cfg.reload() {
+            *generation += 1;
+        }
+    });
+}
+
+/// F5 cycles the scaling, F6 and F7 flip, F8 rotates. Returns whether the key
+/// was used.
+pub fn output_key(key: Key) -> bool {
+    with(|cfg, generation| {
+        match key {
+            Key::F5 => cfg.scale = cfg.scale.next(),
+            Key::F6 => cfg.flip_h = !cfg.flip_h,
+            Key::F7 => cfg.flip_v = !cfg.flip_v,
+            Key::F8 => cfg.rotate = cfg.rotate.next(),
+            _ => return false,
+        }
+        *generation += 1;
+        log::info!(
+            "Output: scale {:?}, flip {}/{}, rotate {:?}",
+            cfg.scale,
+            cfg.flip_h,
+            cfg.flip_v,
+            cfg.rotate
+        );
+        true
+    })
+}
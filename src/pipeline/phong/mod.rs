@@ -8,6 +8,8 @@ use lib::gfx::mesh::{Vertex, VertexExt as _};
 use lib::gfx::scene::{Scene, Node};
 use lib::gfx::wgpu;
 
+use crate::pipeline::shader;
+
 mod material;
 use material::Material;
 
@@ -32,8 +34,8 @@ impl Phong {
     {
         let scene = lib::resource::read_scene(&app.device, scene);
 
-        let vs = lib::resource::read_shader(&app.device, "phong.vert.spv");
-        let fs = lib::resource::read_shader(&app.device, "phong.frag.spv");
+        let vs = lib::resource::read_shader(&app.device, shader("phong.vert.spv"));
+        let fs = lib::resource::read_shader(&app.device, shader("phong.frag.spv"));
 
         let mat_layout = wgpu::util::BindGroupLayoutBuilder::new("phong")
             .tex(wgpu::ShaderStages::FRAGMENT)
@@ -116,6 +118,11 @@ impl Phong {
         &mut self.mats[self.mat_names[name]]
     }
 
+    pub fn try_material(&mut self, name: &str) -> Option<&mut Material> {
+        let i = *self.mat_names.get(name)?;
+        Some(&mut self.mats[i])
+    }
+
     pub fn light(&mut self, name: &str) -> &mut LightUniform {
         self.scene.light_mut(name)
     }
@@ -0,0 +1,104 @@
+use std::path::Path;
+
+const RENDER_CFG: &str = "resources/config/render.cfg";
+const DEFAULT_SIZE: (usize, usize) = (640, 360);
+
+/// A stage's internal render size.
+///
+/// Read from `resources/config/render.cfg`:
+///
+/// ```text
+/// size=640,360
+/// stage.reality=960,540
+/// ```
+///
+/// `size` is every stage's render size unless a `stage.<name>` line
+/// overrides it. Stages are letterboxed against the size of the target
+/// they're actually drawn on, see `output_size`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Resolution {
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Resolution {
+    pub fn of(stage: &str) -> Self {
+        let mut size = DEFAULT_SIZE;
+        let mut stage_size = None;
+
+        let path = Path::new(RENDER_CFG);
+        let src = match std::fs::read_to_string(path) {
+            Ok(src) => src,
+            Err(e) => {
+                log::warn!("Failed to read render settings from {:?}: {}", path, e);
+                String::new()
+            }
+        };
+
+        for (i, line) in src.lines().enumerate() {
+            if line.trim_start().starts_with('#') {
+                continue;
+            }
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => continue,
+            };
+            let parsed = match value.split_once(',').map(|(w, h)| (w.trim().parse(), h.trim().parse())) {
+                Some((Ok(w), Ok(h))) if w > 0 && h > 0 => (w, h),
+                _ => {
+                    log::warn!("{}:{}: expected a size like 640,360, got '{}'", path.display(), i + 1, value);
+                    continue;
+                }
+            };
+            match key {
+                "size" => size = parsed,
+                _ if key.strip_prefix("stage.") == Some(stage) => stage_size = Some(parsed),
+                _ if key.starts_with("stage.") => {}
+                _ => log::warn!("{}:{}: unknown key '{}'", path.display(), i + 1, key),
+            }
+        }
+
+        let (width, height) = stage_size.unwrap_or(size);
+        Self { width, height }
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// Width over height.
+    pub fn aspect(&self) -> f32 {
+        self.width as f32 / self.height as f32
+    }
+
+    /// The largest rect in a `target` sized output with this aspect,
+    /// centered, as `[x, y, w, h]` in output pixels. `None` if it fills the
+    /// output.
+    pub fn letterbox(&self, target: (usize, usize)) -> Option<[f32; 4]> {
+        self.viewport(target, false, false)
+    }
+
+    /// Like `letterbox`, but for the image turned on its side when
+    /// `rotated`, and only scaled by whole numbers when `integer` so every
+    /// pixel is the same size.
+    pub fn viewport(&self, target: (usize, usize), rotated: bool, integer: bool) -> Option<[f32; 4]> {
+        let (ow, oh) = (target.0 as f32, target.1 as f32);
+        let (iw, ih) = match rotated {
+            true => (self.height as f32, self.width as f32),
+            false => (self.width as f32, self.height as f32),
+        };
+
+        let scale = (ow / iw).min(oh / ih);
+        // Smaller than the stage still has to fit, whole number or not
+        let scale = match integer && scale >= 1.0 {
+            true => scale.floor(),
+            false => scale,
+        };
+        let (w, h) = (iw * scale, ih * scale);
+
+        if (w - ow).abs() < 1.0 && (h - oh).abs() < 1.0 {
+            return None;
+        }
+        Some([((ow - w) / 2.0).floor(), ((oh - h) / 2.0).floor(), w, h])
+    }
+}
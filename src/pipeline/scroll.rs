@@ -3,6 +3,8 @@ use lib::gfx::wgpu;
 use lib::gfx::pass::{FilterPass};
 use lib::gfx::uniform::UniformStorage;
 
+use crate::pipeline::shader;
+
 pub struct ScrollPass {
     input: FilterPass,
     composite: FilterPass,
@@ -46,7 +48,7 @@ impl ScrollPass {
         });
 
         let input = FilterPass::new_passthrough_sized(device, size);
-        let composite = FilterPass::new_composite(device, "scroll_composite", 2, Some("scroll2.frag.spv"), Some(&uniform.uniform));
+        let composite = FilterPass::new_composite(device, "scroll_composite", 2, Some(shader("scroll2.frag.spv")), Some(&uniform.uniform));
 
         Self {
             input,
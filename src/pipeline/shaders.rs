@@ -0,0 +1,303 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use parking_lot::Mutex;
+
+const SHADER_DIR: &str = "resources/shaders";
+/// Where the watcher writes what it compiles, inside `SHADER_DIR` so the
+/// copies can be loaded by name like any other shader. Ignored by git, so
+/// the `.spv` files in the repo are left alone.
+const CACHE_DIR: &str = ".cache";
+/// Seconds between looking for changes
+const POLL_INTERVAL: f32 = 0.5;
+/// Deepest chain of includes before giving up on a cycle
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// GLSL with its includes pasted in, and where each of its lines came from.
+struct Source {
+    text: String,
+    lines: Vec<(PathBuf, usize)>,
+}
+
+impl Source {
+    /// Read `path`, replacing each `#include "name"` with the file next to it.
+    fn read(path: &Path, depth: usize) -> Result<Self, String> {
+        if depth > MAX_INCLUDE_DEPTH {
+            return Err(format!("{}: includes nest too deep", path.display()));
+        }
+        let src = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        let mut source = Source {
+            text: String::new(),
+            lines: vec![],
+        };
+        for (i, line) in src.lines().enumerate() {
+            let include = line
+                .trim()
+                .strip_prefix("#include")
+                .map(|rest| rest.trim().trim_matches(|c| c == '"' || c == '<' || c == '>'));
+
+            match include {
+                Some(name) => {
+                    let included = Source::read(&path.with_file_name(name), depth + 1)
+                        .map_err(|e| format!("{}:{}: {}", path.display(), i + 1, e))?;
+                    source.text.push_str(&included.text);
+                    source.lines.extend(included.lines);
+                }
+                None => {
+                    source.text.push_str(line);
+                    source.text.push('\n');
+                    source.lines.push((path.to_owned(), i + 1));
+                }
+            }
+        }
+        Ok(source)
+    }
+
+    /// `file:line` of a byte offset into the pasted together text.
+    fn at(&self, offset: usize) -> String {
+        let line = self.text[..offset.min(self.text.len())].matches('\n').count();
+        match self.lines.get(line) {
+            Some((path, line)) => format!("{}:{}", path.display(), line),
+            None => "?".to_owned(),
+        }
+    }
+}
+
+/// Compile a `.vert` or `.frag` file to SPIR-V, resolving its includes.
+/// Errors are `file:line: message`, pointing into the included file if
+/// that's where the problem is.
+pub fn compile(path: &Path) -> Result<Vec<u8>, String> {
+    let stage = match path.extension().and_then(|e| e.to_str()) {
+        Some("vert") => naga::ShaderStage::Vertex,
+        Some("frag") => naga::ShaderStage::Fragment,
+        Some("comp") => naga::ShaderStage::Compute,
+        _ => return Err(format!("{}: not a shader stage", path.display())),
+    };
+    let source = Source::read(path, 0)?;
+
+    let module = naga::front::glsl::Frontend::default()
+        .parse(&naga::front::glsl::Options::from(stage), &source.text)
+        .map_err(|errors| {
+            errors
+                .iter()
+                .map(|e| format!("{}: {}", source.at(e.meta.to_range().map_or(0, |r| r.start)), e.kind))
+                .collect::<Vec<_>>()
+                .join("\n")
+        })?;
+
+    let info = naga::valid::Validator::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::all())
+        .validate(&module)
+        .map_err(|e| {
+            let at = e
+                .spans()
+                .next()
+                .and_then(|(span, _)| span.to_range())
+                .map_or_else(|| path.display().to_string(), |r| source.at(r.start));
+            format!("{}: {}", at, e.as_inner())
+        })?;
+
+    let words = naga::back::spv::write_vec(&module, &info, &naga::back::spv::Options::default(), None)
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(words.iter().flat_map(|w| w.to_le_bytes()).collect())
+}
+
+/// Every shader named with `shader` so far
+static NAMED: Mutex<Vec<String>> = parking_lot::const_mutex(Vec::new());
+
+/// Shaders the watcher compiled this run, and the names of their copies in
+/// `CACHE_DIR`
+static CACHED: Mutex<Vec<(String, &'static str)>> = parking_lot::const_mutex(Vec::new());
+
+thread_local! {
+    /// The shaders named while `record_shaders` runs
+    static RECORDING: RefCell<Option<HashSet<String>>> = const { RefCell::new(None) };
+}
+
+/// A shader to load, by its `.spv` name. Noted down so that when it changes,
+/// only what was built with it is built again.
+///
+/// Returns the watcher's copy instead if it compiled one this run, so
+/// passes built after a shader is saved load the new one.
+pub fn shader(name: &str) -> &str {
+    let name = name
+        .strip_prefix(CACHE_DIR)
+        .and_then(|n| n.strip_prefix('/'))
+        .unwrap_or(name);
+
+    let mut named = NAMED.lock();
+    if !named.iter().any(|n| n == name) {
+        named.push(name.to_owned());
+    }
+    RECORDING.with(|recording| {
+        if let Some(used) = recording.borrow_mut().as_mut() {
+            used.insert(name.to_owned());
+        }
+    });
+
+    let cached = CACHED.lock();
+    match cached.iter().find(|(n, _)| n == name) {
+        Some((_, copy)) => *copy,
+        None => name,
+    }
+}
+
+/// Run `build`, returning what it built and the shaders it named. Shaders
+/// named inside a nested recording count for the outer one too.
+pub fn record_shaders<T>(build: impl FnOnce() -> T) -> (T, HashSet<String>) {
+    let outer = RECORDING.with(|recording| recording.replace(Some(HashSet::new())));
+    let built = build();
+    let used = RECORDING.with(|recording| {
+        let used = recording.replace(outer).unwrap_or_default();
+        if let Some(outer) = recording.borrow_mut().as_mut() {
+            outer.extend(used.iter().cloned());
+        }
+        used
+    });
+    (built, used)
+}
+
+/// Whether anything built so far named `name`. Shaders that nothing named
+/// are loaded by `lib`'s own passes, so could be used by anything.
+pub fn shader_named(name: &str) -> bool {
+    NAMED.lock().iter().any(|n| n == name)
+}
+
+/// Compiles shaders in `resources/shaders` as they're saved, writing the
+/// `.spv` into `resources/shaders/.cache`, where `shader` finds it for passes
+/// built afterwards.
+///
+/// Includes are followed, so saving `include.glsl` rebuilds everything that
+/// includes it. A shader that fails to compile keeps its last `.spv` and logs
+/// why. Only saves seen while running are compiled; at startup the `.spv`
+/// files in the repo are used as they are, and copies left in the cache by
+/// an earlier run are ignored. The repo's `.spv` files are still built with
+/// `make` in `resources/shaders`.
+pub struct ShaderWatcher {
+    modified: HashMap<PathBuf, SystemTime>,
+    wait: f32,
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn sources() -> Vec<PathBuf> {
+    let entries = match std::fs::read_dir(SHADER_DIR) {
+        Ok(entries) => entries,
+        Err(e) => {
+            log::warn!("Failed to watch {}: {}", SHADER_DIR, e);
+            return vec![];
+        }
+    };
+    entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| matches!(p.extension().and_then(|e| e.to_str()), Some("vert" | "frag" | "comp" | "glsl")))
+        .collect()
+}
+
+/// The name of the `.spv` compiled from a source.
+fn spv(path: &Path) -> Option<String> {
+    path.file_name().and_then(|n| n.to_str()).map(|n| format!("{}.spv", n))
+}
+
+impl Default for ShaderWatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ShaderWatcher {
+    pub fn new() -> Self {
+        let modified = sources()
+            .into_iter()
+            .filter_map(|path| self::modified(&path).map(|t| (path, t)))
+            .collect();
+        Self { modified, wait: 0.0 }
+    }
+
+    /// Compile each of `paths` into the cache, returning the names of the
+    /// `.spv` files that changed.
+    fn rebuild(&self, paths: Vec<PathBuf>) -> Vec<String> {
+        let cache = Path::new(SHADER_DIR).join(CACHE_DIR);
+        if let Err(e) = std::fs::create_dir_all(&cache) {
+            log::error!("Failed to create {}: {}", cache.display(), e);
+            return vec![];
+        }
+
+        let mut rebuilt = vec![];
+        for path in paths {
+            let name = match spv(&path) {
+                Some(name) => name,
+                None => continue,
+            };
+            match compile(&path) {
+                Ok(bytes) => match std::fs::write(cache.join(&name), bytes) {
+                    Ok(()) => {
+                        log::info!("Compiled {}", path.display());
+                        let mut cached = CACHED.lock();
+                        if !cached.iter().any(|(n, _)| *n == name) {
+                            let copy = Box::leak(format!("{}/{}", CACHE_DIR, name).into_boxed_str());
+                            cached.push((name.clone(), copy));
+                        }
+                        rebuilt.push(name);
+                    }
+                    Err(e) => log::error!("Failed to write {}: {}", cache.join(&name).display(), e),
+                },
+                Err(e) => log::error!("Failed to compile {}, keeping the old one:\n{}", path.display(), e),
+            }
+        }
+        rebuilt
+    }
+
+    /// Look for saved shaders every so often, and compile them along with
+    /// anything that includes them. Returns the names of the rebuilt `.spv`
+    /// files.
+    pub fn poll(&mut self, dt: f32) -> Vec<String> {
+        self.wait -= dt;
+        if self.wait > 0.0 {
+            return vec![];
+        }
+        self.wait = POLL_INTERVAL;
+
+        let all = sources();
+        let changed = all
+            .iter()
+            .filter(|path| {
+                let t = modified(path);
+                t.is_some() && t != self.modified.get(*path).copied()
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        if changed.is_empty() {
+            return vec![];
+        }
+        for path in changed.iter() {
+            if let Some(t) = modified(path) {
+                self.modified.insert(path.clone(), t);
+            }
+        }
+
+        let changed_names = changed
+            .iter()
+            .filter_map(|p| p.file_name().and_then(|n| n.to_str()).map(str::to_owned))
+            .collect::<Vec<_>>();
+        let includes = |path: &Path| match Source::read(path, 0) {
+            Ok(source) => source
+                .lines
+                .iter()
+                .any(|(file, _)| file.file_name().and_then(|n| n.to_str()).map_or(false, |n| changed_names.iter().any(|c| c == n))),
+            // Compile it anyway to report the problem
+            Err(_) => changed.iter().any(|c| c == path),
+        };
+
+        let stale = all
+            .into_iter()
+            .filter(|path| path.extension().map_or(false, |e| e != "glsl"))
+            .filter(|path| changed.contains(path) || includes(path))
+            .collect();
+        self.rebuild(stale)
+    }
+}
@@ -1,225 +1,433 @@
-use std::collections::HashMap;
+use std::path::Path;
 
 use lib::gfx::frame::Frame;
 use lib::gfx::wgpu;
-use lib::gfx::uniform::{Uniform, UniformArray};
-use lib::gfx::pass::{SynthPass, FilterPass};
+use lib::gfx::uniform::UniformStorage;
+use lib::gfx::pass::FilterPass;
 use lib::app::App;
+use lib::math::Vector2;
+use lib::prelude::*;
 
-use super::ClearPass;
+use crate::util::{cfg_enum, CfgValue};
+
+use super::{shader, ClearPass};
 
 const BILLBOARD_SHADER: &str = "billboard.vert.spv";
 const COMPOSITE_SHADER: &str = "composite_add.frag.spv";
-const CLEAR_SHADER: &str = "black.frag.spv";
-const STENCIL_SHADER: &str = "abyss_stencil.frag.spv";
+const STENCIL_SHADER: &str = "stencil.frag.spv";
+/// Most corners a polygon region can have, as in `stencil.frag`
+const MAX_POINTS: usize = 16;
 
 type View = wgpu::RawTextureView;
 
-struct Stencil {
-    width: usize,
-    height: usize,
-    view: View,
+pub(super) enum Shape {
+    /// Top left and size
+    Rect(Vector2, Vector2),
+    Poly(Vec<Vector2>),
 }
 
-impl Stencil {
-    fn view(&self) -> &View {
-        &self.view
+pub(super) struct RegionDesc {
+    pub name: String,
+    pub shape: Shape,
+    /// Clockwise, in degrees
+    pub rot: f32,
+}
+
+impl RegionDesc {
+    /// Center and half size of the rect, or of the polygon's bounds.
+    fn bounds(&self) -> (Vector2, Vector2) {
+        let (min, max) = match &self.shape {
+            Shape::Rect(pos, size) => (*pos, pos + size),
+            Shape::Poly(points) => points.iter().fold(
+                (v2(f32::MAX, f32::MAX), v2(f32::MIN, f32::MIN)),
+                |(min, max), p| (v2(min.x.min(p.x), min.y.min(p.y)), v2(max.x.max(p.x), max.y.max(p.y))),
+            ),
+        };
+        ((min + max) / 2.0, (max - min) / 2.0)
     }
 }
 
-pub struct StencilPass {
-    stencils: HashMap<String, Stencil>,
+/// `name=rect x,y w,h [rot deg]` or `name=poly x,y; x,y; x,y [rot deg]`
+pub(super) fn parse_region(name: &str, value: &str) -> Result<RegionDesc, String> {
+    let (value, rot) = match value.split_once(" rot ") {
+        Some((value, rot)) => (value.trim(), f32::parse_cfg(rot.trim())?),
+        None => (value, 0.0),
+    };
+
+    let shape = match value.split_once(' ') {
+        Some(("rect", rest)) => match rest.split_whitespace().collect::<Vec<_>>()[..] {
+            [pos, size] => Shape::Rect(Vector2::parse_cfg(pos)?, Vector2::parse_cfg(size)?),
+            _ => return Err(format!("expected rect x,y w,h, got '{}'", value)),
+        },
+        Some(("poly", rest)) => {
+            let points = Vec::<Vector2>::parse_cfg(rest)?;
+            if points.len() < 3 || points.len() > MAX_POINTS {
+                return Err(format!("a polygon needs 3 to {} corners, got {}", MAX_POINTS, points.len()));
+            }
+            Shape::Poly(points)
+        }
+        _ => return Err(format!("expected rect or poly, got '{}'", value)),
+    };
+
+    Ok(RegionDesc {
+        name: name.to_owned(),
+        shape,
+        rot,
+    })
+}
 
-    image_group: wgpu::BindGroup,
-    pipeline: wgpu::RenderPipeline,
+/// Read a layout, returning its size, its regions in order, and
+/// `line: message` for each problem.
+pub(super) fn parse_layout(src: &str) -> (Vector2, Vec<RegionDesc>, Vec<String>) {
+    let mut size = v2(1920.0, 1080.0);
+    let mut regions: Vec<RegionDesc> = vec![];
+    let mut problems = vec![];
+
+    for (i, line) in src.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => {
+                problems.push(format!("{}: expected name=shape, got '{}'", i + 1, line));
+                continue;
+            }
+        };
 
-    base: FilterPass,
-    composite: FilterPass,
-    clear: ClearPass,
+        let result = match key {
+            "size" => Vector2::parse_cfg(value).map(|s| size = s),
+            _ if regions.iter().any(|r| r.name == key) => Err("region is already defined".to_owned()),
+            _ => parse_region(key, value).map(|r| regions.push(r)),
+        };
+        if let Err(e) = result {
+            problems.push(format!("{}: {}: {}", i + 1, key, e));
+        }
+    }
+
+    (size, regions, problems)
 }
 
-impl StencilPass {
-    pub const N: usize = 29;
-
-    pub fn new(
-        device: &wgpu::Device,
-    ) -> Self {
-        let vs = lib::resource::read_shader(device, BILLBOARD_SHADER);
-        let fs = lib::resource::read_shader(device, STENCIL_SHADER);
-
-        let mut names = Vec::new();
-        let mut views = Vec::new();
-        let mut sizes = Vec::new();
-
-        let mut transforms = Vec::new();
-
-        let mut stencil = |name: &str, pos: (usize, usize), size: (usize, usize)| {
-            let view = wgpu::util::TextureBuilder::new(&format!("stencil_input_{}", name))
-                .size([size.0 as u32, size.1 as u32, 1u32])
-                .usage(wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING)
-                .build(device)
-                .view()
-                .build()
-                .into_raw();
-
-            names.push(name.to_owned());
-            views.push(view);
-            sizes.push(size);
-
-            transforms.push(Transform {
-                x: pos.0 as f32 / 1920.0,
-                y: pos.1 as f32 / 1080.0,
-                w: size.0 as f32 / 1920.0,
-                h: size.1 as f32 / 1080.0,
-            });
-        };
+/// Which way a chase steps through its regions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Pattern {
+    LeftToRight,
+    RightToLeft,
+    /// From both sides in to the middle, mirrored
+    Mirrored,
+    /// From the middle out to both sides
+    InsideOut,
+    /// Everything at once
+    All,
+}
 
-        stencil("lights",    (602, 30),   (715, 15));
+cfg_enum!(Pattern {
+    LeftToRight = "left_to_right",
+    RightToLeft = "right_to_left",
+    Mirrored = "mirrored",
+    InsideOut = "inside_out",
+    All = "all",
+});
+
+struct Chase {
+    /// Regions lit by each step
+    steps: Vec<Vec<usize>>,
+    step: usize,
+    /// How fast lit regions fade back out, per second
+    decay: f32,
+}
 
-        stencil("center",    (625, 345),  (670, 390));
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Region {
+    rect: [f32; 4],
+    shape: [f32; 4],
+    source: [f32; 4],
+    size: [f32; 4],
+    points: [[f32; 4]; MAX_POINTS],
+}
 
-        stencil("dj",        (800,  824), (320, 162));
+/// Where a region's image comes from.
+pub enum Source<'a> {
+    /// Its own texture, from `view`
+    Own,
+    /// All of another pass's image
+    Fill(&'a View),
+    /// The part of another pass's image under the region, so one pass can
+    /// span every region routed to it
+    Span(&'a View),
+}
 
-        stencil("djr_top",   (960,  760), (320, 64));
-        stencil("djr_mid",   (1120, 865), (320, 80));
-        stencil("djr_bot",   (960,  986), (320, 64));
-        stencil("djr_pod",   (1280, 986), (520, 80));
+struct Stencil {
+    name: String,
+    width: usize,
+    height: usize,
+    view: View,
+    /// In layout pixels
+    center: Vector2,
 
-        stencil("djl_top",   (640, 760),  (320, 64));
-        stencil("djl_mid",   (480, 865),  (320, 80));
-        stencil("djl_bot",   (640, 986),  (320, 64));
-        stencil("djl_pod",   (120, 986),  (520, 80));
+    uniform: UniformStorage<Region>,
+    group: wgpu::BindGroup,
+}
+
+/// Stage set projection: the image is cut into named regions, each showing
+/// its own pass, over a base image.
+///
+/// Regions are read from a layout in `resources/config`, like
+/// `abyss.stencil`, as rects or polygons, optionally rotated. Each region
+/// draws its own texture from `view`, or is routed to part or all of
+/// another pass with `route`. Chases light regions in turn, a step per
+/// `beat`.
+pub struct StencilPass {
+    stencils: Vec<Stencil>,
+    size: Vector2,
+    chase: Option<Chase>,
 
-        stencil("sr_pipe1",  (1680, 0),   (240, 180));
-        stencil("sr_pipe2",  (1680, 212), (240, 180));
-        stencil("sr_pipe3",  (1680, 420), (240, 180));
-        stencil("sr_pipe4",  (1680, 630), (240, 180));
-        stencil("sr_inner1", (1569, 150), (111, 180));
-        stencil("sr_inner2", (1569, 420), (111, 180));
-        stencil("sr_inner3", (1569, 685), (111, 180));
-        stencil("sr_top",    (1330, 0),   (220, 212));
-        stencil("sr_wing",   (1315, 225), (250, 630));
+    sampler: wgpu::Sampler,
+    layout: wgpu::BindGroupLayout,
+    pipeline: wgpu::RenderPipeline,
 
-        stencil("sl_pipe1",  (0, 0),      (240, 180));
-        stencil("sl_pipe2",  (0, 212),    (240, 180));
-        stencil("sl_pipe3",  (0, 420),    (240, 180));
-        stencil("sl_pipe4",  (0, 630),    (240, 180));
-        stencil("sl_inner1", (240, 150),  (111, 180));
-        stencil("sl_inner2", (240, 420),  (111, 180));
-        stencil("sl_inner3", (240, 685),  (111, 180));
-        stencil("sl_top",    (370, 0),    (220, 212));
-        stencil("sl_wing",   (355, 225),  (250, 630));
+    base: FilterPass,
+    composite: FilterPass,
+    clear: ClearPass,
+}
 
-        let n = views.len();
+impl StencilPass {
+    pub fn new(device: &wgpu::Device, layout: &str) -> Self {
+        let path = Path::new("resources/config").join(layout);
+        let src = std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("failed to read stencil layout {:?}: {}", path, e));
+        let (size, regions, problems) = parse_layout(&src);
+        for problem in problems {
+            log::warn!("{}:{}", path.display(), problem);
+        }
 
-        let count = Uniform::new(device, "stencil_count", Some(&n));
-        let transforms = UniformArray::new(device, "stencil_transforms", n, Some(&transforms));
+        let vs = lib::resource::read_shader(device, shader(BILLBOARD_SHADER));
+        let fs = lib::resource::read_shader(device, shader(STENCIL_SHADER));
 
         let sampler = wgpu::util::SamplerBuilder::new("stencil_sampler")
             .build(device);
 
-        let image_layout =
+        let layout =
             wgpu::util::BindGroupLayoutBuilder::new("stencil_image")
-                .textures(wgpu::ShaderStages::FRAGMENT, n as usize)
+                .tex(wgpu::ShaderStages::FRAGMENT)
                 .sampler(wgpu::ShaderStages::FRAGMENT)
                 .uniform(wgpu::ShaderStages::FRAGMENT)
-                .uniform(wgpu::ShaderStages::FRAGMENT)
                 .build(device);
 
-        let view_refs = views.iter().collect::<Vec<_>>();
-        let image_group = wgpu::util::BindGroupBuilder::new("stencil_image")
-            .textures(&view_refs)
-            .sampler(&sampler)
-            .uniform(&count)
-            .uniform_array(&transforms)
-            .build(device, &image_layout);
-
         let pipeline = wgpu::util::PipelineBuilder::new("stencil")
-            .with_layout(&image_layout)
+            .with_layout(&layout)
             .render(&vs)
             .fragment(&fs)
             .build(device);
-        
-        let mut stencils = HashMap::new();
-        for (name, view, (width, height)) in itertools::multizip((names, views, sizes)) {
-            stencils.insert(name, Stencil {
-                width,
-                height,
-                view,
-            });
-        }
+
+        let stencils = regions
+            .iter()
+            .map(|desc| {
+                let (center, half) = desc.bounds();
+                let (width, height) = ((half.x * 2.0).round() as usize, (half.y * 2.0).round() as usize);
+
+                let view = wgpu::util::TextureBuilder::new(&format!("stencil_input_{}", desc.name))
+                    .size([width.max(1) as u32, height.max(1) as u32, 1u32])
+                    .usage(wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING)
+                    .build(device)
+                    .view()
+                    .build()
+                    .into_raw();
+
+                let rot = desc.rot.to_radians();
+                let mut points = [[0.0; 4]; MAX_POINTS];
+                let n = match &desc.shape {
+                    Shape::Rect(..) => 0,
+                    Shape::Poly(ps) => {
+                        for (p, q) in points.iter_mut().zip(ps) {
+                            *p = [q.x, q.y, 0.0, 0.0];
+                        }
+                        ps.len()
+                    }
+                };
+
+                let uniform = UniformStorage::new(device, &format!("stencil_{}", desc.name), Region {
+                    rect: [center.x, center.y, half.x, half.y],
+                    shape: [rot.cos(), rot.sin(), n as f32, 1.0],
+                    source: [0.0, 0.0, 1.0, 1.0],
+                    size: [size.x, size.y, 0.0, 0.0],
+                    points,
+                });
+
+                let group = wgpu::util::BindGroupBuilder::new(&format!("stencil_{}", desc.name))
+                    .texture(&view)
+                    .sampler(&sampler)
+                    .uniform(&uniform.uniform)
+                    .build(device, &layout);
+
+                Stencil {
+                    name: desc.name.clone(),
+                    width,
+                    height,
+                    view,
+                    center,
+
+                    uniform,
+                    group,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        log::debug!("Loaded {} stencil regions from {:?}", stencils.len(), path);
 
         Self {
             stencils,
+            size,
+            chase: None,
 
-            image_group,
+            sampler,
+            layout,
             pipeline,
 
             base: FilterPass::new_passthrough(device),
-            composite: FilterPass::new_composite::<()>(device, "stencil_composite", 2, Some(COMPOSITE_SHADER), None),
+            composite: FilterPass::new_composite::<()>(device, "stencil_composite", 2, Some(shader(COMPOSITE_SHADER)), None),
             clear: ClearPass::new(device, wgpu::Color::BLACK),
         }
     }
 
+    fn index(&self, name: &str) -> usize {
+        self.stencils
+            .iter()
+            .position(|s| s.name == name)
+            .unwrap_or_else(|| panic!("no stencil region {}", name))
+    }
+
     pub fn view(&self, name: &str) -> &View {
-        self.stencils[name].view()
+        &self.stencils[self.index(name)].view
     }
 
     pub fn views<'a, I>(&self, names: I) -> impl Iterator<Item = &View>
     where
         I: IntoIterator<Item = &'a str>
     {
-        names.into_iter()
-            .map(str::to_owned)
-            .map(move |n| self.view(&n))
+        names.into_iter().map(move |n| self.view(n))
     }
 
     pub fn all_views(&self) -> impl Iterator<Item = &View> {
-        self.stencils.values().map(|s| s.view())
+        self.stencils.iter().map(|s| &s.view)
     }
 
-    pub fn keys<'a>(&'a self) -> impl Iterator<Item = &'a String> {
-        self.stencils.keys()
+    /// Region names, in the order of the layout.
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.stencils.iter().map(|s| &s.name)
     }
 
-    pub fn base(&self) -> &View { self.base.view(0) }
-
-    pub fn lights(&self) -> &View { self.view("lights") }
-
-    pub fn center(&self) -> &View { self.view("center") }
+    /// Region names starting with `prefix`, like `sl_pipe`.
+    pub fn group<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = &'a str> {
+        self.keys().filter(move |k| k.starts_with(prefix)).map(String::as_str)
+    }
 
-    pub fn dj(&self) -> &View { self.view("dj") }
+    pub fn base(&self) -> &View { self.base.view(0) }
 
-    pub fn djr_top(&self) -> &View { self.view("djr_top") }
-    pub fn djr_mid(&self) -> &View { self.view("djr_mid") }
-    pub fn djr_bot(&self) -> &View { self.view("djr_bot") }
-    pub fn djr_pod(&self) -> &View { self.view("djr_pod") }
+    pub fn size(&self, name: &str) -> (usize, usize) {
+        let s = &self.stencils[self.index(name)];
+        (s.width, s.height)
+    }
 
-    pub fn djl_top(&self) -> &View { self.view("djl_mid") }
-    pub fn djl_mid(&self) -> &View { self.view("djl_mid") }
-    pub fn djl_bot(&self) -> &View { self.view("djl_bot") }
-    pub fn djl_pod(&self) -> &View { self.view("djl_pod") }
+    /// Show `source` in a region instead of what it showed before.
+    pub fn route(&mut self, device: &wgpu::Device, name: &str, source: Source) {
+        let size = self.size;
+        let i = self.index(name);
+        let s = &mut self.stencils[i];
+
+        let [x, y, hw, hh] = s.uniform.rect;
+        let (view, rect) = match source {
+            Source::Own => (&s.view, [0.0, 0.0, 1.0, 1.0]),
+            Source::Fill(view) => (view, [0.0, 0.0, 1.0, 1.0]),
+            Source::Span(view) => (view, [(x - hw) / size.x, (y - hh) / size.y, 2.0 * hw / size.x, 2.0 * hh / size.y]),
+        };
 
-    pub fn dj_all(&self) -> impl Iterator<Item = &View> { self.views(["dj", "djr_top", "djr_mid", "djr_bot", "djr_pod", "djl_top", "djl_mid", "djl_bot", "djl_pod"]) }
+        s.group = wgpu::util::BindGroupBuilder::new(&format!("stencil_{}", s.name))
+            .texture(view)
+            .sampler(&self.sampler)
+            .uniform(&s.uniform.uniform)
+            .build(device, &self.layout);
+        s.uniform.source = rect;
+    }
 
-    pub fn sl_top(&self) -> &View { self.view("sr_top") }
-    pub fn sl_pipes(&self) -> impl Iterator<Item = &View> { self.views(["sl_pipe1", "sl_pipe2", "sl_pipe3", "sl_pipe4"]) }
-    pub fn sl_inner(&self) -> impl Iterator<Item = &View> { self.views(["sl_inner1", "sl_inner2", "sl_inner3"]) }
+    /// Light regions in turn, a step per `beat`, fading out at `decay` per
+    /// second between. All regions when `names` is empty. The rest stay lit.
+    /// A layout without regions has nothing to chase, and stops instead.
+    pub fn chase(&mut self, pattern: Pattern, names: &[&str], decay: f32) {
+        let mut regions = match names {
+            [] => (0..self.stencils.len()).collect::<Vec<_>>(),
+            _ => names.iter().map(|n| self.index(n)).collect(),
+        };
+        if regions.is_empty() {
+            log::warn!("No stencil regions to chase");
+            self.stop_chase();
+            return;
+        }
+        let x = |i: &usize| self.stencils[*i].center.x;
+        regions.sort_by(|a, b| x(a).total_cmp(&x(b)));
+
+        // Regions about as far from the middle step together
+        let mid = self.size.x / 2.0;
+        let mut mirrored: Vec<Vec<usize>> = vec![];
+        for &i in regions.iter() {
+            let d = (x(&i) - mid).abs();
+            match mirrored.iter_mut().find(|step| ((x(&step[0]) - mid).abs() - d).abs() < self.size.x / 100.0) {
+                Some(step) => step.push(i),
+                None => mirrored.push(vec![i]),
+            }
+        }
+        mirrored.sort_by(|a, b| (x(&b[0]) - mid).abs().total_cmp(&(x(&a[0]) - mid).abs()));
+
+        let steps = match pattern {
+            Pattern::LeftToRight => regions.iter().map(|&i| vec![i]).collect(),
+            Pattern::RightToLeft => regions.iter().rev().map(|&i| vec![i]).collect(),
+            Pattern::Mirrored => mirrored,
+            Pattern::InsideOut => mirrored.into_iter().rev().collect(),
+            Pattern::All => vec![regions.clone()],
+        };
 
-    pub fn sr_top(&self) -> &View { self.view("sr_top") }
-    pub fn sr_pipes(&self) -> impl Iterator<Item = &View> { self.views(["sr_pipe1", "sr_pipe2", "sr_pipe3", "sr_pipe4"]) }
-    pub fn sr_inner(&self) -> impl Iterator<Item = &View> { self.views(["sr_inner1", "sr_inner2", "sr_inner3"]) }
+        for s in self.stencils.iter_mut() {
+            s.uniform.shape[3] = 1.0;
+        }
+        for &i in regions.iter() {
+            self.stencils[i].uniform.shape[3] = 0.0;
+        }
+        self.chase = Some(Chase {
+            steps,
+            step: 0,
+            decay,
+        });
+    }
 
-    pub fn sl_wing(&self) -> &View { self.view("sl_wing") }
-    pub fn sr_wing(&self) -> &View { self.view("sr_wing") }
+    /// Stop chasing and light every region.
+    pub fn stop_chase(&mut self) {
+        self.chase = None;
+        for s in self.stencils.iter_mut() {
+            s.uniform.shape[3] = 1.0;
+        }
+    }
 
-    pub fn pipes(&self) -> impl Iterator<Item = &View> { self.sl_pipes().chain(self.sr_pipes()) }
-    pub fn top(&self) -> impl Iterator<Item = &View> { self.views(["sl_top", "sr_top"]) }
-    pub fn inner(&self) -> impl Iterator<Item = &View> { self.sl_inner().chain(self.sr_inner()) }
+    /// Light the chase's next step.
+    pub fn beat(&mut self) {
+        let chase = match self.chase.as_mut() {
+            Some(chase) if !chase.steps.is_empty() => chase,
+            _ => return,
+        };
+        for &i in chase.steps[chase.step].iter() {
+            self.stencils[i].uniform.shape[3] = 1.0;
+        }
+        chase.step = (chase.step + 1) % chase.steps.len();
+    }
 
-    pub fn size(&self, name: &str) -> (usize, usize) {
-        let s = &self.stencils[name];
-        (s.width, s.height)
+    pub fn update(&mut self, dt: f32) {
+        if let Some(chase) = self.chase.as_ref() {
+            let fade = (-chase.decay * dt).exp();
+            for &i in chase.steps.iter().flatten() {
+                self.stencils[i].uniform.shape[3] *= fade;
+            }
+        }
     }
 
     pub async fn clear(&self, app: &App) {
@@ -232,13 +440,19 @@ impl StencilPass {
     }
 
     pub fn encode(&self, frame: &mut Frame, view: &View) {
+        for s in self.stencils.iter() {
+            s.uniform.upload(frame);
+        }
+
         {
             let mut pass = wgpu::util::RenderPassBuilder::new()
-                .color_attachment(self.composite.view(0), |b| b)
+                .color_attachment(self.composite.view(0), |b| b.color(|op| op.clear(wgpu::Color::BLACK)))
                 .begin(frame);
             pass.set_pipeline(&self.pipeline);
-            pass.set_bind_group(0, &self.image_group, &[]);
-            pass.draw(0..3, 0..1);
+            for s in self.stencils.iter() {
+                pass.set_bind_group(0, &s.group, &[]);
+                pass.draw(0..3, 0..1);
+            }
         }
 
         self.base.encode(frame, self.composite.view(1));
@@ -246,13 +460,3 @@ impl StencilPass {
         self.composite.encode(frame, view);
     }
 }
-
-#[repr(C)]
-#[derive(Clone, Copy, Debug)]
-struct Transform {
-    pub x: f32,
-    pub y: f32,
-
-    pub w: f32,
-    pub h: f32,
-}
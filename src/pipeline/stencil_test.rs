@@ -0,0 +1,40 @@
+use lib::prelude::*;
+
+use super::stencil::{parse_layout, parse_region, Shape};
+
+const LAYOUT: &str = "
+# The back wall
+size=1280,720
+left=rect 0,0 320,720
+middle=poly 640,0; 960,360; 640,720; 320,360 rot 45
+left=rect 0,0 10,10
+right=circle 1120,360 160
+";
+
+#[test]
+fn test_layout() {
+    let (size, regions, problems) = parse_layout(LAYOUT);
+
+    assert_eq!(size, v2(1280.0, 720.0));
+    let names: Vec<_> = regions.iter().map(|r| r.name.as_str()).collect();
+    assert_eq!(names, vec!["left", "middle"]);
+    assert_eq!(problems, vec![
+        "6: left: region is already defined".to_owned(),
+        "7: right: expected rect or poly, got 'circle 1120,360 160'".to_owned(),
+    ]);
+}
+
+#[test]
+fn test_regions() {
+    let rect = parse_region("a", "rect 10,20 30,40").unwrap();
+    assert!(matches!(rect.shape, Shape::Rect(pos, size) if pos == v2(10.0, 20.0) && size == v2(30.0, 40.0)));
+    assert_eq!(rect.rot, 0.0);
+
+    let poly = parse_region("b", "poly 0,0; 1,0; 0,1 rot 90").unwrap();
+    assert!(matches!(&poly.shape, Shape::Poly(ps) if ps.len() == 3 && ps[1] == v2(1.0, 0.0)));
+    assert_eq!(poly.rot, 90.0);
+
+    assert!(parse_region("c", "rect 10,20").is_err());
+    assert!(parse_region("d", "poly 0,0; 1,0").is_err());
+    assert!(parse_region("e", "rect 0,0 1,1 rot left").is_err());
+}
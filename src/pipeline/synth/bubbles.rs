@@ -6,6 +6,7 @@ use lib::gfx::pass::SynthPass;
 use lib::gfx::uniform::UniformStorage;
 
 use crate::Model;
+use crate::pipeline::shader;
 
 #[repr(C)]
 #[derive(Clone, Copy, Debug)]
@@ -26,7 +27,7 @@ pub struct BubblesPass {
 impl BubblesPass {
     pub fn new(device: &wgpu::Device, bubbles: Bubbles) -> Self {
         let uniform = UniformStorage::new(device, "bubbles", bubbles);
-        let synth = SynthPass::new(device, "bubbles", "bubbles.frag.spv", Some(&uniform.uniform));
+        let synth = SynthPass::new(device, "bubbles", shader("bubbles.frag.spv"), Some(&uniform.uniform));
         Self {
             synth,
             uniform,
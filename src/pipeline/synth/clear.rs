@@ -2,6 +2,8 @@ use lib::gfx::pass::SynthPass;
 use lib::gfx::frame::Frame;
 use lib::gfx::wgpu;
 
+use crate::pipeline::shader;
+
 pub struct ClearPass {
     color: wgpu::Color,
     synth: SynthPass,
@@ -11,7 +13,7 @@ impl ClearPass {
     pub fn new(device: &wgpu::Device, color: wgpu::Color) -> Self {
         Self {
             color,
-            synth: SynthPass::new::<()>(device, "clear", "clear.frag.spv", None)
+            synth: SynthPass::new::<()>(device, "clear", shader("clear.frag.spv"), None)
         }
     }
 
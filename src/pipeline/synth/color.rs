@@ -3,6 +3,8 @@ use lib::gfx::wgpu;
 use lib::gfx::pass::SynthPass;
 use lib::gfx::uniform::UniformStorage;
 
+use crate::pipeline::shader;
+
 pub struct ColorPass {
     synth: SynthPass,
     uniform: UniformStorage<[f32; 3]>,
@@ -11,7 +13,7 @@ pub struct ColorPass {
 impl ColorPass {
     pub fn new(device: &wgpu::Device, color: [f32; 3]) -> Self {
         let uniform = UniformStorage::new(device, "scroll_fr", color);
-        let synth = SynthPass::new(device, "fill", "color.frag.spv", Some(&uniform.uniform));
+        let synth = SynthPass::new(device, "fill", shader("color.frag.spv"), Some(&uniform.uniform));
         Self {
             synth,
             uniform,
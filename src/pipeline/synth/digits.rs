@@ -8,6 +8,7 @@ use lib::gfx::uniform::UniformStorage;
 use rand::Rng;
 
 use crate::Model;
+use crate::pipeline::shader;
 
 #[repr(C)]
 #[derive(Clone, Copy, Debug)]
@@ -29,7 +30,7 @@ impl DigitsPass {
             t: 0.0,
             i: (rand::thread_rng().gen::<f32>() * 1000.0).floor() as u32,
         });
-        let synth = SynthPass::new(device, "digits", "digits.frag.spv", Some(&uniform.uniform));
+        let synth = SynthPass::new(device, "digits", shader("digits.frag.spv"), Some(&uniform.uniform));
         Self {
             synth,
             uniform,
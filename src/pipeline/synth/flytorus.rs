@@ -6,6 +6,7 @@ use lib::gfx::pass::SynthPass;
 use lib::gfx::uniform::UniformStorage;
 
 use crate::Model;
+use crate::pipeline::shader;
 
 #[repr(C)]
 #[derive(Clone, Copy, Debug)]
@@ -26,7 +27,7 @@ pub struct FlyTorusPass {
 impl FlyTorusPass {
     pub fn new(device: &wgpu::Device, wormhole: FlyTorus) -> Self {
         let uniform = UniformStorage::new(device, "wormhole", wormhole);
-        let synth = SynthPass::new(device, "wormhole", "wormhole.frag.spv", Some(&uniform.uniform));
+        let synth = SynthPass::new(device, "wormhole", shader("wormhole.frag.spv"), Some(&uniform.uniform));
         Self {
             synth,
             uniform,
@@ -5,10 +5,13 @@ use lib::gfx::wgpu;
 use lib::gfx::pass::SynthPass;
 use lib::gfx::uniform::UniformStorage;
 
+use crate::pipeline::shader;
+
 #[repr(C)]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Default)]
 pub struct IsoTri {
     pub color: [f32; 3],
+    /// Height over width of the target, set by the pass from its size
     pub aspect: f32,
     pub t: f32,
     pub r: f32,
@@ -22,9 +25,10 @@ pub struct IsoTriPass {
 }
 
 impl IsoTriPass {
-    pub fn new(device: &wgpu::Device, isotri: IsoTri) -> Self {
-        let uniform = UniformStorage::new(device, "isotri", isotri);
-        let synth = SynthPass::new(device, "isotri", "isotri.frag.spv", Some(&uniform.uniform));
+    pub fn new(device: &wgpu::Device, (w, h): (usize, usize), isotri: IsoTri) -> Self {
+        let aspect = h as f32 / w as f32;
+        let uniform = UniformStorage::new(device, "isotri", IsoTri { aspect, ..isotri });
+        let synth = SynthPass::new(device, "isotri", shader("isotri.frag.spv"), Some(&uniform.uniform));
         Self {
             synth,
             uniform,
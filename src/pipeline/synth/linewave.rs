@@ -5,6 +5,8 @@ use lib::gfx::wgpu;
 use lib::gfx::pass::SynthPass;
 use lib::gfx::uniform::UniformStorage;
 
+use crate::pipeline::shader;
+
 #[repr(C)]
 #[derive(Clone, Copy, Debug)]
 pub struct LineWave {
@@ -28,7 +30,7 @@ pub struct LineWavePass {
 impl LineWavePass {
     pub fn new(device: &wgpu::Device, linewave: LineWave) -> Self {
         let uniform = UniformStorage::new(device, "linewave", linewave);
-        let synth = SynthPass::new(device, "linewave", "linewave.frag.spv", Some(&uniform.uniform));
+        let synth = SynthPass::new(device, "linewave", shader("linewave.frag.spv"), Some(&uniform.uniform));
         Self {
             synth,
             uniform,
@@ -3,6 +3,8 @@ use lib::gfx::wgpu;
 use lib::gfx::pass::SynthPass;
 use lib::gfx::uniform::UniformStorage;
 
+use crate::pipeline::shader;
+
 pub struct LoadingPass {
     synth: SynthPass,
     uniform: UniformStorage<f32>,
@@ -11,7 +13,7 @@ pub struct LoadingPass {
 impl LoadingPass {
     pub fn new(device: &wgpu::Device) -> Self {
         let uniform = UniformStorage::new(device, "loading", 0.0);
-        let synth = SynthPass::new(device, "loading", "loading.frag.spv", Some(&uniform.uniform));
+        let synth = SynthPass::new(device, "loading", shader("loading.frag.spv"), Some(&uniform.uniform));
         Self {
             synth,
             uniform,
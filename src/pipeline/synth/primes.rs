@@ -6,6 +6,8 @@ use lib::gfx::wgpu;
 use lib::gfx::pass::SynthPass;
 use lib::gfx::uniform::UniformStorage;
 
+use crate::pipeline::shader;
+
 #[repr(C)]
 #[derive(Clone, Copy, Debug)]
 pub struct Primes {
@@ -27,7 +29,7 @@ pub struct PrimesPass {
 impl PrimesPass {
     pub fn new(device: &wgpu::Device, primes: Primes) -> Self {
         let uniform = UniformStorage::new(device, "primes", primes);
-        let synth = SynthPass::new(device, "primes", "primes.frag.spv", Some(&uniform.uniform));
+        let synth = SynthPass::new(device, "primes", shader("primes.frag.spv"), Some(&uniform.uniform));
         Self {
             synth,
             uniform,
@@ -6,6 +6,7 @@ use lib::gfx::pass::SynthPass;
 use lib::gfx::uniform::UniformStorage;
 
 use crate::Model;
+use crate::pipeline::shader;
 
 pub struct ScanlinesPass {
     synth: SynthPass,
@@ -15,7 +16,7 @@ pub struct ScanlinesPass {
 impl ScanlinesPass {
     pub fn new(device: &wgpu::Device) -> Self {
         let uniform = UniformStorage::new(device, "scanlines", 0.0);
-        let synth = SynthPass::new(device, "scanlines", "scanlines.frag.spv", Some(&uniform.uniform));
+        let synth = SynthPass::new(device, "scanlines", shader("scanlines.frag.spv"), Some(&uniform.uniform));
         Self {
             synth,
             uniform,
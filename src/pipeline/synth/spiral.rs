@@ -5,10 +5,13 @@ use lib::gfx::wgpu;
 use lib::gfx::pass::SynthPass;
 use lib::gfx::uniform::UniformStorage;
 
+use crate::pipeline::shader;
+
 #[repr(C)]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Default)]
 pub struct Spiral {
     pub color: [f32; 3],
+    /// Height over width of the target, set by the pass from its size
     pub aspect: f32,
     pub t: f32,
     pub swirl: f32,
@@ -18,15 +21,32 @@ pub struct Spiral {
     pub spokes: u32,
 }
 
+impl Spiral {
+    /// A field by name, for setting from config.
+    pub fn field(&mut self, name: &str) -> Option<&mut f32> {
+        Some(match name {
+            "color.r" => &mut self.color[0],
+            "color.g" => &mut self.color[1],
+            "color.b" => &mut self.color[2],
+            "swirl" => &mut self.swirl,
+            "speed" => &mut self.speed,
+            "cutoff" => &mut self.cutoff,
+            "amount" => &mut self.amount,
+            _ => return None,
+        })
+    }
+}
+
 pub struct SpiralPass {
     synth: SynthPass,
     uniform: UniformStorage<Spiral>,
 }
 
 impl SpiralPass {
-    pub fn new(device: &wgpu::Device, spiral: Spiral) -> Self {
-        let uniform = UniformStorage::new(device, "spiral", spiral);
-        let synth = SynthPass::new(device, "spiral", "spiral.frag.spv", Some(&uniform.uniform));
+    pub fn new(device: &wgpu::Device, (w, h): (usize, usize), spiral: Spiral) -> Self {
+        let aspect = h as f32 / w as f32;
+        let uniform = UniformStorage::new(device, "spiral", Spiral { aspect, ..spiral });
+        let synth = SynthPass::new(device, "spiral", shader("spiral.frag.spv"), Some(&uniform.uniform));
         Self {
             synth,
             uniform,
@@ -5,6 +5,8 @@ use lib::gfx::wgpu;
 use lib::gfx::pass::SynthPass;
 use lib::gfx::uniform::UniformStorage;
 
+use crate::pipeline::shader;
+
 #[repr(C)]
 #[derive(Clone, Copy, Debug)]
 pub struct Starfield {
@@ -19,6 +21,25 @@ pub struct Starfield {
     pub acid: f32,
 }
 
+impl Starfield {
+    /// A field by name, for setting from config.
+    pub fn field(&mut self, name: &str) -> Option<&mut f32> {
+        Some(match name {
+            "color.r" => &mut self.color[0],
+            "color.g" => &mut self.color[1],
+            "color.b" => &mut self.color[2],
+            "x" => &mut self.x,
+            "y" => &mut self.y,
+            "w" => &mut self.w,
+            "h" => &mut self.h,
+            "speed" => &mut self.speed,
+            "warp" => &mut self.warp,
+            "acid" => &mut self.acid,
+            _ => return None,
+        })
+    }
+}
+
 pub struct StarfieldPass {
     synth: SynthPass,
     uniform: UniformStorage<Starfield>,
@@ -27,7 +48,7 @@ pub struct StarfieldPass {
 impl StarfieldPass {
     pub fn new(device: &wgpu::Device, starfield: Starfield) -> Self {
         let uniform = UniformStorage::new(device, "starfield", starfield);
-        let synth = SynthPass::new(device, "starfield", "starfield.frag.spv", Some(&uniform.uniform));
+        let synth = SynthPass::new(device, "starfield", shader("starfield.frag.spv"), Some(&uniform.uniform));
         Self {
             synth,
             uniform,
@@ -3,6 +3,8 @@ use lib::gfx::pass::SynthPass;
 use lib::gfx::frame::Frame;
 use lib::gfx::wgpu;
 
+use crate::pipeline::shader;
+
 #[derive(Default, Clone, Copy)]
 #[repr(C)]
 pub struct Stripes {
@@ -22,7 +24,7 @@ pub struct StripesPass {
 impl StripesPass {
     pub fn new(device: &wgpu::Device, size: (usize, usize), stripes: Stripes) -> Self {
         let uniform = UniformStorage::new(device, "stripes", stripes);
-        let synth = SynthPass::new(device, "stripes", "line_stripes.frag.spv", Some(&uniform.uniform));
+        let synth = SynthPass::new(device, "stripes", shader("line_stripes.frag.spv"), Some(&uniform.uniform));
 
         Self {
             uniform,
@@ -6,6 +6,7 @@ use lib::gfx::pass::SynthPass;
 use lib::gfx::uniform::UniformStorage;
 
 use crate::Model;
+use crate::pipeline::shader;
 
 #[repr(C)]
 #[derive(Clone, Copy, Debug)]
@@ -25,7 +26,7 @@ pub struct SubstratePass {
 impl SubstratePass {
     pub fn new(device: &wgpu::Device, substrate: Substrate) -> Self {
         let uniform = UniformStorage::new(device, "substrate", substrate);
-        let synth = SynthPass::new(device, "substrate", "substrate.frag.spv", Some(&uniform.uniform));
+        let synth = SynthPass::new(device, "substrate", shader("substrate.frag.spv"), Some(&uniform.uniform));
         Self {
             synth,
             uniform,
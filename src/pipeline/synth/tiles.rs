@@ -3,6 +3,8 @@ use lib::gfx::pass::SynthPass;
 use lib::gfx::frame::Frame;
 use lib::gfx::wgpu;
 
+use crate::pipeline::shader;
+
 #[derive(Default, Clone, Copy)]
 #[repr(C)]
 pub struct Tiles {
@@ -20,7 +22,7 @@ pub struct TilesPass {
 impl TilesPass {
     pub fn new(device: &wgpu::Device, size: (usize, usize), tiles: Tiles) -> Self {
         let uniform = UniformStorage::new(device, "tiles", tiles);
-        let synth = SynthPass::new(device, "tiles", "tiles.frag.spv", Some(&uniform.uniform));
+        let synth = SynthPass::new(device, "tiles", shader("tiles.frag.spv"), Some(&uniform.uniform));
 
         Self {
             uniform,
@@ -6,6 +6,7 @@ use lib::gfx::pass::SynthPass;
 use lib::gfx::uniform::UniformStorage;
 
 use crate::Model;
+use crate::pipeline::shader;
 
 #[repr(C)]
 #[derive(Clone, Copy, Debug)]
@@ -28,7 +29,7 @@ pub struct TorusPass {
 impl TorusPass {
     pub fn new(device: &wgpu::Device, torus: Torus) -> Self {
         let uniform = UniformStorage::new(device, "torus", torus);
-        let synth = SynthPass::new(device, "torus", "torus.frag.spv", Some(&uniform.uniform));
+        let synth = SynthPass::new(device, "torus", shader("torus.frag.spv"), Some(&uniform.uniform));
         Self {
             synth,
             uniform,
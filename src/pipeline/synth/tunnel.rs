@@ -5,6 +5,8 @@ use lib::gfx::wgpu;
 use lib::gfx::pass::SynthPass;
 use lib::gfx::uniform::UniformStorage;
 
+use crate::pipeline::shader;
+
 #[repr(C)]
 #[derive(Clone, Copy, Debug)]
 struct Tunnel {
@@ -27,7 +29,7 @@ impl TunnelPass {
             w: size.0 as f32,
             h: size.1 as f32,
         });
-        let synth = SynthPass::new(device, "tunnel", "tunnel.frag.spv", Some(&uniform.uniform));
+        let synth = SynthPass::new(device, "tunnel", shader("tunnel.frag.spv"), Some(&uniform.uniform));
 
         Self {
             synth,
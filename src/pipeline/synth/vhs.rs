@@ -3,6 +3,8 @@ use lib::gfx::wgpu;
 use lib::gfx::pass::SynthPass;
 use lib::gfx::uniform::UniformStorage;
 
+use crate::pipeline::shader;
+
 pub struct VhsBlocksPass {
     synth: SynthPass,
     uniform: UniformStorage<f32>,
@@ -11,7 +13,7 @@ pub struct VhsBlocksPass {
 impl VhsBlocksPass {
     pub fn new(device: &wgpu::Device) -> Self {
         let uniform = UniformStorage::new(device, "test", 0.0);
-        let synth = SynthPass::new(device, "test", "vhsblock.frag.spv", Some(&uniform.uniform));
+        let synth = SynthPass::new(device, "test", shader("vhsblock.frag.spv"), Some(&uniform.uniform));
         Self {
             synth,
             uniform,
@@ -5,6 +5,8 @@ use lib::gfx::wgpu;
 use lib::gfx::pass::SynthPass;
 use lib::gfx::uniform::UniformStorage;
 
+use crate::pipeline::shader;
+
 #[repr(C)]
 #[derive(Clone, Copy, Debug)]
 pub struct Test {
@@ -20,7 +22,7 @@ pub struct ToggleSynthPass<T: Copy = ()> {
 impl <T: Copy> ToggleSynthPass<T> {
     pub fn new(device: &wgpu::Device, uniform: Option<T>, active: bool) -> Self {
         let uniform = uniform.map(|u| UniformStorage::new(device, "toggle", u));
-        let synth = SynthPass::new(device, "fill", "loading.frag.spv", match uniform {
+        let synth = SynthPass::new(device, "fill", shader("loading.frag.spv"), match uniform {
             Some(ref u) => Some(&u.uniform),
             _ => None
         });
@@ -0,0 +1,361 @@
+use std::collections::HashSet;
+
+use lib::gfx::frame::Frame;
+use lib::gfx::uniform::UniformStorage;
+use lib::gfx::wgpu;
+use lib::math::{Matrix4, Vector2, Vector4};
+use lib::prelude::*;
+
+use crate::pipeline::{record_shaders, shader};
+use crate::util::{stage_config, StageCfg};
+
+const WARP_CFG: &str = "warp.cfg";
+/// Subdivisions of the drawn mesh along each side, as in `warp.vert`
+const GRID: u32 = 32;
+/// Most control points the bezier grid can have, as in `warp.vert`
+const MAX_POINTS: usize = 64;
+/// How far the arrow keys move a point, as a fraction of the output
+const STEP: f32 = 0.002;
+
+stage_config!(WarpConfig {
+    enabled: bool = false,
+    /// Where the output's corners land, from 0,0 at the top left to 1,1:
+    /// top left, top right, bottom right, bottom left
+    corners: Vec<Vector2> = vec![v2(0.0, 0.0), v2(1.0, 0.0), v2(1.0, 1.0), v2(0.0, 1.0)],
+    /// Bezier control points across and down
+    cols: usize [2, 8] = 4,
+    rows: usize [2, 8] = 4,
+    /// Each control point's offset from its place on an even lattice, row by row
+    mesh: Vec<Vector2> = vec![],
+    /// Soft edge widths as a fraction of the image: left, right, top, bottom
+    blend: Vector4 = v4(0.0, 0.0, 0.0, 0.0),
+    /// Of the projector, for ramping the soft edges in light
+    gamma: f32 [1.0, 3.0] = 2.2,
+});
+
+impl WarpConfig {
+    /// Fill in whatever the file left out, so every point has somewhere to be.
+    fn fix(&mut self) {
+        if self.corners.len() != 4 {
+            log::warn!("{}: expected 4 corners, got {}", WARP_CFG, self.corners.len());
+            self.corners = WarpConfig::default().corners;
+        }
+        if self.cols * self.rows > MAX_POINTS {
+            log::warn!("{}: at most {} control points", WARP_CFG, MAX_POINTS);
+            self.rows = MAX_POINTS / self.cols;
+        }
+        self.mesh.resize(self.cols * self.rows, v2(0.0, 0.0));
+    }
+
+    fn save(&self) -> String {
+        let points = |ps: &[Vector2]| ps.iter().map(|p| format!("{},{}", p.x, p.y)).collect::<Vec<_>>().join("; ");
+        let b = self.blend;
+        format!(
+            "# Written by the warp editor: F9 to edit, F10 to save\n\
+             enabled={}\n\
+             corners={}\n\
+             cols={}\n\
+             rows={}\n\
+             mesh={}\n\
+             blend={},{},{},{}\n\
+             gamma={}\n",
+            self.enabled,
+            points(&self.corners),
+            self.cols,
+            self.rows,
+            points(&self.mesh),
+            b.x,
+            b.y,
+            b.z,
+            b.w,
+            self.gamma
+        )
+    }
+}
+
+/// The projective transform taking the unit square onto `corners`, from
+/// Heckbert's square to quad mapping. In the upper 3x3, acting on `(u, v, 1)`.
+fn keystone(corners: &[Vector2]) -> Matrix4 {
+    let [p0, p1, p2, p3] = [corners[0], corners[1], corners[2], corners[3]];
+    let s = p0 - p1 + p2 - p3;
+    let d1 = p1 - p2;
+    let d2 = p3 - p2;
+
+    let den = d1.x * d2.y - d2.x * d1.y;
+    let (g, h) = match den.abs() < 1e-6 {
+        // A parallelogram, or the corners are on a line
+        true => (0.0, 0.0),
+        false => ((s.x * d2.y - d2.x * s.y) / den, (d1.x * s.y - s.x * d1.y) / den),
+    };
+
+    let a = p1 - p0 + p1 * g;
+    let b = p3 - p0 + p3 * h;
+    Matrix4::from_cols(
+        v4(a.x, a.y, g, 0.0),
+        v4(b.x, b.y, h, 0.0),
+        v4(0.0, 0.0, 0.0, 0.0),
+        v4(p0.x, p0.y, 1.0, 0.0),
+    )
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Edit {
+    Off,
+    Corners,
+    Mesh,
+}
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct Warp {
+    keystone: Matrix4,
+    points: [[f32; 4]; MAX_POINTS],
+    blend: [f32; 4],
+    /// cols, rows, gamma, edit mode
+    grid: [f32; 4],
+    selected: [f32; 4],
+}
+
+/// Projection mapping, drawn after the stage onto the real output.
+///
+/// The stage's image is pinned by its four corners for keystone correction,
+/// then bent by a bezier grid of control points for curved or uneven
+/// surfaces. Soft edges fade it out over the overlap with a second
+/// projector.
+///
+/// Read from `resources/config/warp.cfg` and edited live: F9 cycles between
+/// pinning corners, moving control points, and not editing. Tab and
+/// backslash pick the next and previous point, the arrows move it, Back
+/// puts it back, and F10 writes the file. Only keys reach the app from
+/// `lib::app::run`, so points can't be dragged with the mouse.
+///
+/// The stage is drawn into a target the size of the surface, resized by
+/// `resize` when the surface changes.
+pub struct WarpPass {
+    cfg: StageCfg<WarpConfig>,
+    edit: Edit,
+    selected: usize,
+
+    size: (usize, usize),
+    target: wgpu::TextureView,
+    sampler: wgpu::Sampler,
+    layout: wgpu::BindGroupLayout,
+    uniform: UniformStorage<Warp>,
+    pipeline: wgpu::RenderPipeline,
+    /// What `pipeline` was built with
+    shaders: HashSet<String>,
+    group: wgpu::BindGroup,
+    dirty: bool,
+}
+
+fn target(device: &wgpu::Device, (w, h): (usize, usize)) -> wgpu::TextureView {
+    wgpu::util::TextureBuilder::new("warp")
+        .size([w as u32, h as u32, 1u32])
+        .usage(wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING)
+        .build(device)
+        .view()
+        .build()
+}
+
+fn pipeline(device: &wgpu::Device, layout: &wgpu::BindGroupLayout) -> wgpu::RenderPipeline {
+    let vs = lib::resource::read_shader(device, shader("warp.vert.spv"));
+    let fs = lib::resource::read_shader(device, shader("warp.frag.spv"));
+    wgpu::util::PipelineBuilder::new("warp")
+        .with_layout(layout)
+        .render(&vs)
+        .fragment(&fs)
+        .build(device)
+}
+
+impl WarpPass {
+    pub fn new(device: &wgpu::Device, size: (usize, usize)) -> Self {
+        let mut cfg = StageCfg::<WarpConfig>::load(WARP_CFG);
+        cfg.fix();
+
+        let target = target(device, size);
+        let sampler = wgpu::util::SamplerBuilder::new("warp").build(device);
+
+        let uniform = UniformStorage::new(
+            device,
+            "warp",
+            Warp {
+                keystone: keystone(&cfg.corners),
+                points: [[0.0; 4]; MAX_POINTS],
+                blend: [0.0; 4],
+                grid: [0.0; 4],
+                selected: [0.0; 4],
+            },
+        );
+
+        let layout = wgpu::util::BindGroupLayoutBuilder::new("warp")
+            .tex(wgpu::ShaderStages::FRAGMENT)
+            .sampler(wgpu::ShaderStages::FRAGMENT)
+            .uniform(wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT)
+            .build(device);
+        let group = wgpu::util::BindGroupBuilder::new("warp")
+            .texture(&target)
+            .sampler(&sampler)
+            .uniform(&uniform.uniform)
+            .build(device, &layout);
+        let (pipeline, shaders) = record_shaders(|| pipeline(device, &layout));
+
+        Self {
+            cfg,
+            edit: Edit::Off,
+            selected: 0,
+
+            size,
+            target,
+            sampler,
+            layout,
+            uniform,
+            pipeline,
+            shaders,
+            group,
+            dirty: true,
+        }
+    }
+
+    /// Whether to draw the stage into `view` and warp it, or draw straight
+    /// to the output.
+    pub fn active(&self) -> bool {
+        self.cfg.enabled || self.edit != Edit::Off
+    }
+
+    /// Where the stage should draw when active.
+    pub fn view(&self) -> &wgpu::RawTextureView {
+        &self.target
+    }
+
+    /// Match the target to the surface's size, before the stage draws into it.
+    pub fn resize(&mut self, device: &wgpu::Device, size: (usize, usize)) {
+        if size == self.size {
+            return;
+        }
+        log::debug!("Resizing the warp target to {}x{}", size.0, size.1);
+
+        self.size = size;
+        self.target = target(device, size);
+        self.group = wgpu::util::BindGroupBuilder::new("warp")
+            .texture(&self.target)
+            .sampler(&self.sampler)
+            .uniform(&self.uniform.uniform)
+            .build(device, &self.layout);
+    }
+
+    /// Rebuild the pipeline if any of the `changed` shaders are its own.
+    pub fn reload(&mut self, device: &wgpu::Device, changed: &[String]) {
+        if changed.iter().any(|c| self.shaders.contains(c)) {
+            self.pipeline = pipeline(device, &self.layout);
+        }
+    }
+
+    pub fn update(&mut self) {
+        if self.cfg.reload() {
+            self.cfg.fix();
+            self.selected = 0;
+            self.dirty = true;
+        }
+    }
+
+    fn points(&mut self) -> &mut Vec<Vector2> {
+        match self.edit {
+            Edit::Mesh => &mut self.cfg.mesh,
+            _ => &mut self.cfg.corners,
+        }
+    }
+
+    /// Where the selected point sits on the image, to mark it while editing.
+    fn marker(&self) -> Vector2 {
+        match self.edit {
+            Edit::Mesh => v2(
+                (self.selected % self.cfg.cols) as f32 / (self.cfg.cols - 1) as f32,
+                (self.selected / self.cfg.cols) as f32 / (self.cfg.rows - 1) as f32,
+            ),
+            _ => WarpConfig::default().corners[self.selected],
+        }
+    }
+
+    /// Handle an editing key, returning whether it was one.
+    pub fn key(&mut self, key: Key) -> bool {
+        if key == Key::F9 {
+            self.edit = match self.edit {
+                Edit::Off => Edit::Corners,
+                Edit::Corners => Edit::Mesh,
+                Edit::Mesh => Edit::Off,
+            };
+            self.selected = 0;
+            self.dirty = true;
+            return true;
+        }
+        if self.edit == Edit::Off {
+            return false;
+        }
+
+        let n = self.points().len();
+        let step = match key {
+            Key::Left => v2(-STEP, 0.0),
+            Key::Right => v2(STEP, 0.0),
+            Key::Up => v2(0.0, -STEP),
+            Key::Down => v2(0.0, STEP),
+            _ => v2(0.0, 0.0),
+        };
+
+        match key {
+            Key::Tab => self.selected = (self.selected + 1) % n,
+            Key::Backslash => self.selected = (self.selected + n - 1) % n,
+            Key::Left | Key::Right | Key::Up | Key::Down => {
+                let i = self.selected;
+                self.points()[i] += step;
+            }
+            Key::Back => {
+                let i = self.selected;
+                self.points()[i] = match self.edit {
+                    Edit::Mesh => v2(0.0, 0.0),
+                    _ => WarpConfig::default().corners[i],
+                };
+            }
+            Key::F10 => {
+                let path = std::path::Path::new("resources/config").join(WARP_CFG);
+                match std::fs::write(&path, self.cfg.save()) {
+                    Ok(()) => log::info!("Saved warp to {:?}", path),
+                    Err(e) => log::error!("Failed to save warp to {:?}: {}", path, e),
+                }
+            }
+            _ => return false,
+        }
+        self.dirty = true;
+        true
+    }
+
+    pub fn encode(&mut self, frame: &mut Frame, target: &wgpu::RawTextureView) {
+        if self.dirty {
+            let cfg = &self.cfg;
+            let marker = self.marker();
+            let edit = match self.edit {
+                Edit::Off => 0.0,
+                Edit::Corners => 1.0,
+                Edit::Mesh => 2.0,
+            };
+
+            self.uniform.keystone = keystone(&cfg.corners);
+            for (point, offset) in self.uniform.points.iter_mut().zip(&cfg.mesh) {
+                *point = [offset.x, offset.y, 0.0, 0.0];
+            }
+            self.uniform.blend = cfg.blend.into();
+            self.uniform.grid = [cfg.cols as f32, cfg.rows as f32, cfg.gamma, edit];
+            self.uniform.selected = [marker.x, marker.y, 0.0, 0.0];
+
+            self.uniform.upload(frame);
+            self.dirty = false;
+        }
+
+        let mut pass = wgpu::util::RenderPassBuilder::new()
+            .color_attachment(target, |a| a.color(|op| op.clear(wgpu::Color::BLACK)))
+            .begin(frame);
+
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.group, &[]);
+        pass.draw(0..GRID * GRID * 6, 0..1);
+    }
+}
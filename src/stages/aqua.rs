@@ -4,27 +4,25 @@ use lib::gfx::pass::*;
 use lib::gfx::scene::Node;
 use lib::prelude::*;
 
-use crate::demo::{Event, Player, Stage};
+use crate::demo::{Event, Id, Player, Stage, StageInfo};
 use crate::pipeline::*;
 
 pub struct Aqua {
     segment: Segment,
 
-    t: f32,
-    t_mul: f32,
+    clock: Clock,
 
     decay: DecayEnv,
     count: CounterEnv,
-    cfg: Config,
+    cfg: StageCfg<AquaConfig>,
 
     scene: Phong,
     animator: Animator,
 
-    clear: ClearPass,
     text: TextPass,
     tfx: FxPass,
 
-    composite: FilterPass,
+    graph: Graph,
     fx: FxPass,
     blit: BlitPass,
 }
@@ -33,9 +31,32 @@ enum Segment {
     Init,
 }
 
+stage_config!(AquaConfig {
+    tmul: f32 = 200.0,
+    tvhs: f32 [0.0, 1.0] = 0.9,
+    tbloom: f32 [0.0, 1.0] = 0.2,
+    shake: f32 = 1.0,
+    flash: f32 = 0.25,
+    aqua_s: f32 = 400.0,
+    aqua: Vector2 = v2(100.0, 300.0),
+    /// Only read at startup
+    tfx_chain: Vec<Effect> = vec![Effect::Bloom, Effect::Vhs],
+});
+
 impl Aqua {
-    pub fn new(app: &App) -> Self {
+    pub fn info() -> StageInfo {
+        StageInfo::default()
+            .cfg::<AquaConfig>("aqua.cfg")
+            .scene("aquanox.glb")
+            .nodes(&["Camera", "Camera0", "Camera1", "Camera2", "Camera3", "Camera4"])
+            .notes(&[13, 14, 22, 23, 24, 25, 26, 27, 60, 61, 62, 64])
+            .mods(&[0])
+            .go(Id::Note(10), "reality")
+    }
+
+    pub fn new(app: &App, targets: &Targets, presets: &FxPresets) -> Self {
         let device = &app.device;
+        let res = Resolution::of("aqua");
 
         let decay = DecayEnv::default()
             .with("crash", 0.0)
@@ -48,35 +69,31 @@ impl Aqua {
         let count = CounterEnv::default()
             .with("aqua", 1 + 1);
 
-        let cfg = lib::resource::read_cfg("aqua.cfg");
+        let cfg = StageCfg::<AquaConfig>::load("aqua.cfg");
 
         let scene = Phong::new(app, "aquanox.glb", |_node| true, |_mat| true);
         let animator = Animator::new(&scene.scene);
 
-        let clear = ClearPass::new(device, wgpu::Color::BLACK);
         let text = TextPassBuilder::default()
             .with("default", "da_mad_rave_italic.otf")
             .build(device);
-        let mut tfx = FxPass::new(device, (640, 360));
-        tfx.vhs = cfg.f32("tvhs");
-        tfx.bloom = cfg.f32("tbloom");
-
-        let composite = FilterPass::new_composite_sized::<()>(
-            device,
-            "aqua_composite",
-            2,
-            Some("composite_add.frag.spv"),
-            None,
-            (640, 360),
-        );
-        let fx = FxPass::new(device, (640, 360));
-        let blit = BlitPass::new("aqua").build(device);
+        let mut tfx = FxPass::with_chain(device, res.size(), targets, presets, &cfg.tfx_chain);
+        tfx.vhs = cfg.tvhs;
+        tfx.bloom = cfg.tbloom;
+
+        let graph = Graph::new("aqua", res.size())
+            .source("scene")
+            .source("text")
+            .filter("composite", "composite_add.frag.spv", &["scene", "text"], None)
+            .output("composite")
+            .build(device, targets);
+        let fx = FxPass::new(device, res.size(), targets, presets);
+        let blit = BlitPass::new("aqua").fit(res).pooled(targets, res.size()).build(device);
 
         Self {
             segment: Segment::Init,
 
-            t: 0.0,
-            t_mul: 1.0,
+            clock: Clock::new("rms").speed(cfg.tmul),
 
             decay,
             count,
@@ -85,11 +102,10 @@ impl Aqua {
             scene,
             animator,
 
-            clear,
             text,
             tfx,
 
-            composite,
+            graph,
             fx,
             blit
         }
@@ -99,28 +115,33 @@ impl Aqua {
 #[async_trait]
 impl Stage for Aqua {
     async fn init(&mut self, p: &mut Player) {
-        self.animator.play(self.t, true, "Landscape.001Action");
-        self.animator.play(self.t, true, "Landscape.001Action.001");
-        self.animator.play(self.t, true, "Landscape.002Action.001");
+        self.animator.play(self.clock.t(), true, "Landscape.001Action");
+        self.animator.play(self.clock.t(), true, "Landscape.001Action.001");
+        self.animator.play(self.clock.t(), true, "Landscape.002Action.001");
     }
 
     async fn update(&mut self, p: &mut Player, dt: f32) {
-        self.t += self.cfg.f32("tmul") * p.rms() * self.t_mul * dt;
+        if self.cfg.reload() {
+            self.tfx.vhs = self.cfg.tvhs;
+            self.tfx.bloom = self.cfg.tbloom;
+        }
+        self.clock.set_speed(self.cfg.tmul);
+        self.clock.update(p, dt);
 
-        self.animator.update(self.t, &mut self.scene.scene);
+        self.animator.update(self.clock.t(), &mut self.scene.scene);
         self.decay.update(dt);
-        self.fx.update(p.t(), self.t);
-        self.tfx.update(p.t(), self.t);
+        self.fx.update(p.t(), self.clock.t());
+        self.tfx.update(p.t(), self.clock.t());
 
         self.fx.flash = self.decay.v("crash") * 0.5;
 
         if let Some(fr) = self.decay.vv("crash") {
             self.fx.flash = fr * 0.5;
         } else {
-            self.fx.flash = self.decay.v("bigsnare") * self.cfg.f32("flash")
+            self.fx.flash = self.decay.v("bigsnare") * self.cfg.flash
         }
 
-        self.fx.shake = self.decay.v("bigkick") * self.cfg.f32("shake");
+        self.fx.shake = self.decay.v("bigkick") * self.cfg.shake;
 
         self.fx.vhs = self.decay.v("vhs");
 
@@ -172,27 +193,35 @@ impl Stage for Aqua {
         }
     }
 
+    fn reload(&mut self, device: &wgpu::Device, changed: &[String]) {
+        self.tfx.reload(device, changed);
+        self.graph.reload(device, changed);
+        self.fx.reload(device, changed);
+        self.blit.reload(device, changed);
+    }
+
     fn view(&mut self, frame: &mut Frame, view: &wgpu::RawTextureView) {
         let decay = &self.decay;
         let count = &self.count;
         let cfg = &self.cfg;
 
-        self.scene.encode(frame, self.composite.view(0));
-
-        if let Some(n) = self.count.vv("aqua") {
-            let pos = cfg.v2("aqua");
-            let scale = cfg.f32("aqua_s");
-            let col = v4(1.0, 1.0, 1.0, 1.0);
-            self.text.draw(|d| d.at(pos).text("aqua", |t| t.scale(scale).color(col)));
-
-            self.text.encode(frame, self.tfx.view());
-            self.tfx.upload(frame);
-            self.tfx.encode(frame, self.composite.view(1));
-        } else {
-            self.clear.encode(frame, self.composite.view(1));
-        }
+        self.graph.encode(frame, self.fx.view(), |frame, node, target| match node {
+            "scene" => self.scene.encode(frame, target),
+            "text" => {
+                if let Some(n) = count.vv("aqua") {
+                    let pos = cfg.aqua;
+                    let scale = cfg.aqua_s;
+                    let col = v4(1.0, 1.0, 1.0, 1.0);
+                    self.text.draw(|d| d.at(pos).text("aqua", |t| t.scale(scale).color(col)));
+
+                    self.text.encode(frame, self.tfx.view());
+                    self.tfx.upload(frame);
+                    self.tfx.encode(frame, target);
+                }
+            }
+            _ => {}
+        });
 
-        self.composite.encode(frame, self.fx.view());
         self.fx.upload(frame);
         self.fx.encode(frame, self.blit.view());
         self.blit.encode(frame, view);
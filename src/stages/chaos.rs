@@ -4,18 +4,17 @@ use lib::gfx::pass::*;
 use lib::gfx::scene::Node;
 use lib::prelude::*;
 
-use crate::demo::{Event, Player, Stage};
+use crate::demo::{Event, Id, Player, Stage, StageInfo};
 use crate::pipeline::*;
 
 pub struct Chaos {
     segment: Segment,
 
-    t: f32,
-    t_mul: f32,
+    clock: Clock,
 
     decay: DecayEnv,
     count: CounterEnv,
-    cfg: Config,
+    cfg: StageCfg<ChaosConfig>,
 
     digits: DigitsPass,
 
@@ -28,9 +27,25 @@ enum Segment {
 
 }
 
+stage_config!(ChaosConfig {
+    tmul: f32 = 100.0,
+    shake: f32 = 1.0,
+    flash: f32 = 0.25,
+    edge: f32 = 0.6,
+});
+
 impl Chaos {
-    pub fn new(app: &App) -> Self {
+    pub fn info() -> StageInfo {
+        StageInfo::default()
+            .cfg::<ChaosConfig>("chaos.cfg")
+            .notes(&[60, 61])
+            .mods(&[0])
+            .go(Id::Note(10), "dragonage")
+    }
+
+    pub fn new(app: &App, targets: &Targets, presets: &FxPresets) -> Self {
         let device = &app.device;
+        let res = Resolution::of("chaostheory");
 
         let decay = DecayEnv::default()
             .with("kick", 0.0)
@@ -38,18 +53,17 @@ impl Chaos {
             ;
         let count = CounterEnv::default();
 
-        let cfg = lib::resource::read_cfg("chaos.cfg");
+        let cfg = StageCfg::<ChaosConfig>::load("chaos.cfg");
 
         let digits = DigitsPass::new(device, [1.0, 0.1, 0.3]);
 
-        let fx = FxPass::new(device, (640, 360));
-        let blit = BlitPass::new("chaos").build(device);
+        let fx = FxPass::new(device, res.size(), targets, presets);
+        let blit = BlitPass::new("chaos").fit(res).pooled(targets, res.size()).build(device);
 
         Self {
             segment: Segment::Init,
 
-            t: 0.0,
-            t_mul: 1.0,
+            clock: Clock::new("rms").speed(cfg.tmul),
 
             decay,
             count,
@@ -67,20 +81,22 @@ impl Chaos {
 impl Stage for Chaos {
     async fn init(&mut self, p: &mut Player) {
         *self.fx.alpha = 0.0;
-        self.fx.edge = self.cfg.f32("edge");
+        self.fx.edge = self.cfg.edge;
     }
 
     async fn update(&mut self, p: &mut Player, dt: f32) {
-        self.t += self.cfg.f32("tmul") * p.rms() * self.t_mul * dt;
+        self.cfg.reload();
+        self.clock.set_speed(self.cfg.tmul);
+        self.clock.update(p, dt);
 
         self.decay.update(dt);
-        self.fx.update(p.t(), self.t);
-        self.digits.update(self.t);
+        self.fx.update(p.t(), self.clock.t());
+        self.digits.update(self.clock.t());
 
-        let min = self.cfg.f32("edge");
+        let min = self.cfg.edge;
         self.fx.edge = min + (1.0 - min) * self.decay.v("kick");
-        self.fx.shake = self.decay.v("kick") * self.cfg.f32("shake");
-        self.fx.flash = self.decay.v("snare") * self.cfg.f32("flash");
+        self.fx.shake = self.decay.v("kick") * self.cfg.shake;
+        self.fx.flash = self.decay.v("snare") * self.cfg.flash;
     }
 
     async fn event(&mut self, p: &mut Player, ev: Event) {
@@ -111,6 +127,11 @@ impl Stage for Chaos {
         }
     }
 
+    fn reload(&mut self, device: &wgpu::Device, changed: &[String]) {
+        self.fx.reload(device, changed);
+        self.blit.reload(device, changed);
+    }
+
     fn view(&mut self, frame: &mut Frame, view: &wgpu::RawTextureView) {
         let decay = &self.decay;
         let count = &self.count;
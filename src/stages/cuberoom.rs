@@ -51,6 +51,10 @@ impl Stage for CubeRoom {
     async fn event(&mut self, p: &mut Player, ev: Event) {}
     async fn key(&mut self, p: &mut Player, state: KeyState, key: Key) {}
 
+    fn reload(&mut self, device: &wgpu::Device, changed: &[String]) {
+        self.blit.reload(device, changed);
+    }
+
     fn view(&mut self, frame: &mut Frame, target: &wgpu::RawTextureView) {
         self.scene.encode(frame, self.blit.view());
         self.blit.encode(frame, target);
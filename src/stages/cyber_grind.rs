@@ -4,14 +4,13 @@ use lib::gfx::pass::*;
 use lib::gfx::scene::Node;
 use lib::prelude::*;
 
-use crate::demo::{Event, Player, Stage};
+use crate::demo::{Event, Id, Player, Stage, StageInfo};
 use crate::pipeline::*;
 
 pub struct CyberGrind {
     segment: Segment,
 
-    t: f32,
-    t_mul: f32,
+    clock: Clock,
 
     rot: f32,
     vel: f32,
@@ -22,7 +21,7 @@ pub struct CyberGrind {
 
     decay: DecayEnv,
     count: CounterEnv,
-    cfg: Config,
+    cfg: StageCfg<CyberGrindConfig>,
 
     pyramid: Phong,
     core: Phong,
@@ -31,7 +30,7 @@ pub struct CyberGrind {
 
     starfield: StarfieldPass,
 
-    composite: FilterPass,
+    graph: Graph,
     fx: FxPass,
     blit: BlitPass,
 }
@@ -45,9 +44,30 @@ enum Segment {
     GreenFly,
 }
 
+stage_config!(CyberGrindConfig {
+    accel: f32 = 20.0,
+    boost: f32 = 8.0,
+    vmin: f32 = 3.0,
+    warp: f32 = 0.92,
+    mega: f32 = 0.4,
+});
+
 impl CyberGrind {
-    pub fn new(app: &App) -> Self {
+    pub fn info() -> StageInfo {
+        StageInfo::default()
+            .cfg::<CyberGrindConfig>("pyraship.cfg")
+            .scene("pyraship.glb")
+            .scene("coreship.glb")
+            .scene("technoship.glb")
+            .nodes(&["Pyramid", "Sphere", "Icosphere", "IcosphereCore"])
+            .notes(&[29, 60, 61, 62, 63, 64])
+            .mods(&[0, 1, 2, 3, 4, 5, 7, 8])
+            .go(Id::Note(10), "halo")
+    }
+
+    pub fn new(app: &App, targets: &Targets, presets: &FxPresets) -> Self {
         let device = &app.device;
+        let res = Resolution::of("cyber_grind");
 
         let decay = DecayEnv::default()
             .with("drop", 10.0)
@@ -59,7 +79,7 @@ impl CyberGrind {
             ;
         let count = CounterEnv::default();
 
-        let cfg = lib::resource::read_cfg("pyraship.cfg");
+        let cfg = StageCfg::<CyberGrindConfig>::load("pyraship.cfg");
 
         let pyramid = Phong::new(app, "pyraship.glb", |_node| true, |_mat| true);
         let core = Phong::new(app, "coreship.glb", |_node| true, |_mat| true);
@@ -70,34 +90,31 @@ impl CyberGrind {
             color: [1.0, 0.0, 0.0],
             x: 0.5,
             y: 0.85,
-            w: 640.0,
-            h: 360.0,
+            w: res.width as f32,
+            h: res.height as f32,
             t: 0.0,
             speed: 1.0,
-            warp: cfg.f32("warp"),
+            warp: cfg.warp,
             acid: 0.0,
         });
 
-        let composite = FilterPass::new_composite_sized::<()>(
-            device,
-            "pyraship_composite",
-            2,
-            Some("composite.frag.spv"),
-            None,
-            (640, 360),
-        );
-        let fx = FxPass::new(device, (640, 360));
-        let blit = BlitPass::new("funky").build(device);
+        let graph = Graph::new("pyraship", res.size())
+            .source("stars")
+            .source("ship")
+            .filter("composite", "composite.frag.spv", &["stars", "ship"], None)
+            .output("composite")
+            .build(device, targets);
+        let fx = FxPass::new(device, res.size(), targets, presets);
+        let blit = BlitPass::new("funky").fit(res).pooled(targets, res.size()).build(device);
 
         Self {
             segment: Segment::Init,
 
-            t: 0.0,
-            t_mul: 1.0,
+            clock: Clock::new("rms").speed(50.0),
 
             rot: 0.0,
             vel: 0.0,
-            accel: cfg.f32("accel"),
+            accel: cfg.accel,
             min: 0.0,
             acid: 0.0,
             edge: 0.0,
@@ -113,7 +130,7 @@ impl CyberGrind {
 
             starfield,
 
-            composite,
+            graph,
             fx,
             blit
         }
@@ -123,7 +140,7 @@ impl CyberGrind {
 #[async_trait]
 impl Stage for CyberGrind {
     async fn init(&mut self, p: &mut Player) {
-        // self.animator.play(self.t, true, "");
+        // self.animator.play(self.clock.t(), true, "");
         // for i in 0..13 {
         //     for j in 0..13 {
         //         let n = i + j*12;
@@ -137,13 +154,17 @@ impl Stage for CyberGrind {
     }
 
     async fn update(&mut self, p: &mut Player, dt: f32) {
+        if self.cfg.reload() {
+            self.starfield.warp = self.cfg.warp;
+            self.accel = self.cfg.accel;
+        }
         let cfg = &self.cfg;
 
-        self.t += 50.0 * p.rms() * self.t_mul * dt;
+        self.clock.update(p, dt);
 
         self.animator.update(p.t(), &mut self.pyramid.scene);
         self.decay.update(dt);
-        self.fx.update(p.t(), self.t);
+        self.fx.update(p.t(), self.clock.t());
         self.starfield.update(p.t());
 
         let pyramid = self.pyramid.node("Pyramid");
@@ -167,7 +188,7 @@ impl Stage for CyberGrind {
         self.ico.node("Icosphere").transform.scale = v3(2.9, 2.9, 2.9) + 0.5 * v3(kick, kick, kick);
         self.ico.node("IcosphereCore").transform.scale = v3(2.5, 2.5, 2.5) + 0.5 * v3(kick, kick, kick);
 
-        self.fx.mega = cfg.f32("mega") * self.decay.v("womp");
+        self.fx.mega = cfg.mega * self.decay.v("womp");
         self.fx.edge = self.decay.v("womp") + self.edge;
         self.fx.glitch = self.decay.v("womp");
         self.fx.shake = self.decay.v("clap");
@@ -190,8 +211,8 @@ impl Stage for CyberGrind {
 
                         let tap = self.decay.v("tap") * 0.15;
 
-                        let sx = (i as f32 * 0.4 + self.t).sin() * 0.8;
-                        let sy = (j as f32 * 0.5 + 5.0 + self.t).sin() * 0.8;
+                        let sx = (i as f32 * 0.4 + self.clock.t()).sin() * 0.8;
+                        let sy = (j as f32 * 0.5 + 5.0 + self.clock.t()).sin() * 0.8;
 
                         let node = self.pyramid.node(&format!("Cube{:03}", n));
                         node.transform.scale.y = 5.0 + (sx * sy);
@@ -213,7 +234,7 @@ impl Stage for CyberGrind {
             Event::Beat { id: 63, t } => decay.set_t("tap", t),
             Event::Beat { id: 62, t } => decay.set_t("clap", t),
             Event::Beat { id: 61, t } => decay.set_t("kick", t),
-            Event::Beat { id: 60, t } => { decay.set_t("womp", t); self.vel += cfg.f32("boost") },
+            Event::Beat { id: 60, t } => { decay.set_t("womp", t); self.vel += cfg.boost },
 
             Event::Trigger { id: 29 } => self.segment = match self.segment {
                 Segment::Init => {
@@ -232,7 +253,7 @@ impl Stage for CyberGrind {
                     Segment::Rotate
                 },
                 Segment::Rotate => {
-                    self.min = self.cfg.f32("vmin");
+                    self.min = self.cfg.vmin;
                     Segment::RedFly
                 },
                 Segment::RedFly => {
@@ -249,7 +270,7 @@ impl Stage for CyberGrind {
             },
             Event::Trigger { id: 10 } => p.go("halo").await,
 
-            Event::Mod { id: 0, fr } => self.t_mul = 4.0 * fr,
+            Event::Mod { id: 0, fr } => self.clock.set_mul(4.0 * fr),
             Event::Mod { id: 1, fr } => self.fx.red = fr,
             Event::Mod { id: 2, fr } => self.acid = fr,
             Event::Mod { id: 3, fr } => self.fx.invert = fr,
@@ -282,24 +303,26 @@ impl Stage for CyberGrind {
         }
     }
 
+    fn reload(&mut self, device: &wgpu::Device, changed: &[String]) {
+        self.graph.reload(device, changed);
+        self.fx.reload(device, changed);
+        self.blit.reload(device, changed);
+    }
+
     fn view(&mut self, frame: &mut Frame, view: &wgpu::RawTextureView) {
         let decay = &self.decay;
         let count = &self.count;
         let cfg = &self.cfg;
 
-        match self.segment {
-            Segment::RedFly | Segment::BlueFly | Segment::GreenFly =>
-                self.starfield.encode(frame, self.composite.view(0)),
+        let segment = &self.segment;
+        self.graph.encode(frame, self.fx.view(), |frame, node, target| match (node, segment) {
+            ("stars", Segment::RedFly | Segment::BlueFly | Segment::GreenFly) =>
+                self.starfield.encode(frame, target),
+            ("ship", Segment::BlueFly) => self.core.encode(frame, target),
+            ("ship", Segment::GreenFly) => self.ico.encode(frame, target),
+            ("ship", _) => self.pyramid.encode(frame, target),
             _ => {}
-        }
-
-        match self.segment {
-            Segment::BlueFly => self.core.encode(frame, self.composite.view(1)),
-            Segment::GreenFly => self.ico.encode(frame, self.composite.view(1)),
-            _ => self.pyramid.encode(frame, self.composite.view(1)),
-        }
-
-        self.composite.encode(frame, self.fx.view());
+        });
         self.fx.upload(frame);
         self.fx.encode(frame, self.blit.view());
         self.blit.encode(frame, view);
@@ -4,24 +4,23 @@ use lib::gfx::pass::*;
 use lib::gfx::scene::Node;
 use lib::prelude::*;
 
-use crate::demo::{Event, Player, Stage};
+use crate::demo::{Event, Id, Player, Stage, StageInfo};
 use crate::pipeline::*;
 
 pub struct Dragon {
     segment: Segment,
 
-    t: f32,
-    t_mul: f32,
+    clock: Clock,
 
     decay: DecayEnv,
     count: CounterEnv,
-    cfg: Config,
+    cfg: StageCfg<DragonConfig>,
 
     scene: Phong,
     animator: Animator,
     tri: IsoTriPass,
 
-    composite: FilterPass,
+    graph: Graph,
     fx: FxPass,
     blit: BlitPass,
 }
@@ -30,9 +29,34 @@ enum Segment {
     Init,
 }
 
+stage_config!(DragonConfig {
+    tmul: f32 = 800.0,
+    weight0: f32 = 0.5,
+    weight1: f32 = 0.9,
+    weightmin: f32 = 0.0,
+    weightmax: f32 = 0.8,
+    shake: f32 = 1.0,
+    flash: f32 = 0.25,
+    edge: f32 = 1.0,
+    rot0: f32 = 1.0,
+    rot1: f32 = 0.5,
+    rot2: f32 = 1.0,
+});
+
 impl Dragon {
-    pub fn new(app: &App) -> Self {
+    pub fn info() -> StageInfo {
+        StageInfo::default()
+            .cfg::<DragonConfig>("dragonage.cfg")
+            .scene("dragonage.glb")
+            .nodes(&["Sword0", "Sword1", "Sword2", "Camera0", "Camera1", "Camera2", "Camera3", "Camera4", "Camera5"])
+            .notes(&[16, 17, 18, 19, 20, 21, 22, 23, 24, 61, 62, 63, 64, 65])
+            .mods(&[0, 1])
+            .go(Id::Note(10), "yume")
+    }
+
+    pub fn new(app: &App, targets: &Targets, presets: &FxPresets) -> Self {
         let device = &app.device;
+        let res = Resolution::of("dragonage");
 
         let decay = DecayEnv::default()
             .with("kick", 0.0)
@@ -42,36 +66,33 @@ impl Dragon {
 
         let count = CounterEnv::default();
 
-        let cfg = lib::resource::read_cfg("dragonage.cfg");
+        let cfg = StageCfg::<DragonConfig>::load("dragonage.cfg");
 
         let scene = Phong::new(app, "dragonage.glb", |_node| true, |_mat| true);
         let animator = Animator::new(&scene.scene);
 
-        let tri = IsoTriPass::new(device, IsoTri {
+        let tri = IsoTriPass::new(device, res.size(), IsoTri {
             color: [1.0, 0.0, 0.0],
-            aspect: 16.0 / 9.0,
             t: 0.0,
             r: -3.0,
             weight: 0.2,
             thickness: 0.0,
+            ..Default::default()
         });
 
-        let composite = FilterPass::new_composite_sized::<()>(
-            device,
-            "dragon_composite",
-            2,
-            Some("composite.frag.spv"),
-            None,
-            (640, 360),
-        );
-        let fx = FxPass::new(device, (640, 360));
-        let blit = BlitPass::new("dragon").build(device);
+        let graph = Graph::new("dragon", res.size())
+            .source("tri")
+            .source("scene")
+            .filter("composite", "composite.frag.spv", &["tri", "scene"], None)
+            .output("composite")
+            .build(device, targets);
+        let fx = FxPass::new(device, res.size(), targets, presets);
+        let blit = BlitPass::new("dragon").fit(res).pooled(targets, res.size()).build(device);
 
         Self {
             segment: Segment::Init,
 
-            t: 0.0,
-            t_mul: 1.0,
+            clock: Clock::new("rms").speed(cfg.tmul),
 
             decay,
             count,
@@ -81,7 +102,7 @@ impl Dragon {
             animator,
             tri,
 
-            composite,
+            graph,
             fx,
             blit
         }
@@ -95,43 +116,45 @@ impl Stage for Dragon {
     }
 
     async fn update(&mut self, p: &mut Player, dt: f32) {
-        self.t += self.cfg.f32("tmul") * p.rms() * self.t_mul * dt;
+        self.cfg.reload();
+        self.clock.set_speed(self.cfg.tmul);
+        self.clock.update(p, dt);
 
         self.animator.update(p.t(), &mut self.scene.scene);
         self.decay.update(dt);
-        self.fx.update(p.t(), self.t);
+        self.fx.update(p.t(), self.clock.t());
 
-        self.tri.update(self.t);
+        self.tri.update(self.clock.t());
         // self.tri.thickness = match self.segment {
-        //     Segment::Init => self.decay.v("beat") * self.cfg.f32("weight0"),
+        //     Segment::Init => self.decay.v("beat") * self.cfg.weight0,
         //     Segment::Bass => {
-        //         let min = self.cfg.f32("weightmin");
-        //         let max = self.cfg.f32("weightmax");
+        //         let min = self.cfg.weightmin;
+        //         let max = self.cfg.weightmax;
         //         min + self.decay.v("weight") * (max - min)
         //     },
         // }
 
         self.scene.node("Sword0").transform.rotate = Euler {
             x: Rad(PI),
-            y: Rad(self.t * self.cfg.f32("rot0")),
+            y: Rad(self.clock.t() * self.cfg.rot0),
             z: Rad(0.0)
         }.into();
 
         self.scene.node("Sword1").transform.rotate = Euler {
             x: Rad(PI),
-            y: Rad(self.t * self.cfg.f32("rot1")),
+            y: Rad(self.clock.t() * self.cfg.rot1),
             z: Rad(0.0)
         }.into();
 
         self.scene.node("Sword2").transform.rotate = Euler {
             x: Rad(PI),
-            y: Rad(self.t * self.cfg.f32("rot2")),
+            y: Rad(self.clock.t() * self.cfg.rot2),
             z: Rad(0.0)
         }.into();
 
-        self.fx.edge = self.decay.v("kick") * self.cfg.f32("edge");
-        self.fx.shake = self.decay.v("kick") * self.cfg.f32("shake");
-        self.fx.flash = self.decay.v("hat") * self.cfg.f32("flash");
+        self.fx.edge = self.decay.v("kick") * self.cfg.edge;
+        self.fx.shake = self.decay.v("kick") * self.cfg.shake;
+        self.fx.flash = self.decay.v("hat") * self.cfg.flash;
     }
 
     async fn event(&mut self, p: &mut Player, ev: Event) {
@@ -164,7 +187,7 @@ impl Stage for Dragon {
                 self.animator.play(p.t(), false, "Sword Down for Camera.001");
             },
             Event::Trigger { id: 16 } => {
-                self.tri.thickness = self.cfg.f32("weight1");
+                self.tri.thickness = self.cfg.weight1;
             }
 
             Event::Trigger { id: 10 } => p.go("yume").await,
@@ -189,15 +212,22 @@ impl Stage for Dragon {
         }
     }
 
+    fn reload(&mut self, device: &wgpu::Device, changed: &[String]) {
+        self.graph.reload(device, changed);
+        self.fx.reload(device, changed);
+        self.blit.reload(device, changed);
+    }
+
     fn view(&mut self, frame: &mut Frame, view: &wgpu::RawTextureView) {
         let decay = &self.decay;
         let count = &self.count;
         let cfg = &self.cfg;
 
-        self.tri.encode(frame, self.composite.view(0));
-        self.scene.encode(frame, self.composite.view(1));
-
-        self.composite.encode(frame, self.fx.view());
+        self.graph.encode(frame, self.fx.view(), |frame, node, target| match node {
+            "tri" => self.tri.encode(frame, target),
+            "scene" => self.scene.encode(frame, target),
+            _ => {}
+        });
         self.fx.upload(frame);
         self.fx.encode(frame, self.blit.view());
         self.blit.encode(frame, view);
@@ -4,19 +4,20 @@ use lib::gfx::pass::*;
 use lib::gfx::scene::Node;
 use lib::prelude::*;
 
-use crate::demo::{Event, Player, Stage};
+use crate::demo::{Event, Player, Stage, StageInfo};
 use crate::pipeline::*;
 
 pub struct FunkyBeat {
-    segment: Segment,
+    seq: Sequencer<Segment, Phong>,
 
-    t: f32,
-    t_mul: f32,
+    clock: Clock,
+    /// Set by the `pause` and `resume` hooks, to hold the pause look
+    paused: bool,
 
     animator: Animator,
     decay: DecayEnv,
     count: CounterEnv,
-    cfg: Config,
+    cfg: StageCfg<FunkyBeatConfig>,
 
     scene: Phong,
 
@@ -25,11 +26,12 @@ pub struct FunkyBeat {
     tfx: FxPass,
     clear: ClearPass,
 
-    composite: FilterPass,
+    graph: Graph,
     fx: FxPass,
     blit: BlitPass,
 }
 
+#[derive(Clone, Copy, PartialEq)]
 enum Segment {
     Intro,
     Synth,
@@ -37,9 +39,143 @@ enum Segment {
     AhAh,
 }
 
+stage_config!(FunkyBeatConfig {
+    noise: Vector2 = v2(120.0, 120.0),
+    ride: Vector2 = v2(1490.0, 320.0),
+    bang: Vector2 = v2(80.0, 950.0),
+    vhs: f32 = 0.6,
+    bloom: f32 = 0.2,
+    funkys: f32 = 300.0,
+    funky: Vector2 = v2(230.0, 200.0),
+    fun_kys0: f32 = 500.0,
+    fun_kys1: f32 = 500.0,
+    fun_ky0: Vector2 = v2(50.0, 50.0),
+    fun_ky1: Vector2 = v2(800.0, 600.0),
+    beats: f32 = 300.0,
+    beat0: Vector2 = v2(330.0, 560.0),
+    beats0: Vector2 = v2(220.0, 560.0),
+    fade: f32 = 0.7,
+    gimmes0: f32 = 144.0,
+    gimmes1: f32 = 144.0,
+    gimmes2: f32 = 144.0,
+    gimmes3: f32 = 144.0,
+    gimme0: Vector2 = v2(140.0, 260.0),
+    gimme1: Vector2 = v2(140.0, 260.0),
+    gimme2: Vector2 = v2(611.0, 581.0),
+    gimme3: Vector2 = v2(1263.0, 855.0),
+    aints0: f32 = 150.0,
+    aints1: f32 = 150.0,
+    aints2: f32 = 150.0,
+    aints3: f32 = 340.0,
+    aints4: f32 = 340.0,
+    aint0: Vector2 = v2(270.0, 270.0),
+    aint1: Vector2 = v2(840.0, 270.0),
+    aint2: Vector2 = v2(840.0, 270.0),
+    aint3: Vector2 = v2(50.0, 640.0),
+    aint4: Vector2 = v2(1250.0, 640.0),
+    soundss0: f32 = 200.0,
+    soundss1: f32 = 200.0,
+    soundss2: f32 = 360.0,
+    sounds0: Vector2 = v2(340.0, 260.0),
+    sounds1: Vector2 = v2(740.0, 560.0),
+    sounds2: Vector2 = v2(160.0, 320.0),
+    freshs0: f32 = 200.0,
+    freshs1: f32 = 200.0,
+    fresh0: Vector2 = v2(20.0, 600.0),
+    fresh1: Vector2 = v2(930.0, 600.0),
+    reals0: f32 = 150.0,
+    reals1: f32 = 150.0,
+    reals2: f32 = 150.0,
+    reals3: f32 = 200.0,
+    reals4: f32 = 200.0,
+    reals5: f32 = 240.0,
+    reals6: f32 = 240.0,
+    real0: Vector2 = v2(270.0, 270.0),
+    real1: Vector2 = v2(840.0, 270.0),
+    real2: Vector2 = v2(840.0, 270.0),
+    real3: Vector2 = v2(300.0, 510.0),
+    real4: Vector2 = v2(950.0, 510.0),
+    real5: Vector2 = v2(30.0, 740.0),
+    real6: Vector2 = v2(850.0, 740.0),
+    bocks0: f32 = 250.0,
+    bocks1: f32 = 144.0,
+    bocks2: f32 = 250.0,
+    bocks3: f32 = 300.0,
+    bocks4: f32 = 300.0,
+    bock0: Vector2 = v2(30.0, 30.0),
+    bock1: Vector2 = v2(650.0, 320.0),
+    bock2: Vector2 = v2(900.0, 500.0),
+    bock3: Vector2 = v2(300.0, 750.0),
+    bock4: Vector2 = v2(930.0, 750.0),
+    cuts0: f32 = 200.0,
+    cuts1: f32 = 200.0,
+    cuts2: f32 = 200.0,
+    cuts3: f32 = 400.0,
+    cut0: Vector2 = v2(120.0, 60.0),
+    cut1: Vector2 = v2(530.0, 280.0),
+    cut2: Vector2 = v2(950.0, 480.0),
+    cut3: Vector2 = v2(350.0, 700.0),
+    ah_s: f32 = 150.0,
+    do_s: f32 = 150.0,
+    getup: Vector2 = v2(80.0, 50.0),
+    getup_s: f32 = 250.0,
+    getdown: Vector2 = v2(1100.0, 900.0),
+    getdown_s: f32 = 100.0,
+    give_s: f32 = 400.0,
+    give: Vector2 = v2(375.0, 200.0),
+    me_s: f32 = 400.0,
+    me: Vector2 = v2(525.0, 600.0),
+    boi_s: f32 = 400.0,
+    uh_s: f32 = 400.0,
+    uh: Vector2 = v2(430.0, 300.0),
+    ow_s: f32 = 450.0,
+    ow: Vector2 = v2(400.0, 300.0),
+    ah_at: Vec<Vector2> = vec![
+        v2(1430.0, 840.0),
+        v2(530.0, 300.0),
+        v2(860.0, 670.0),
+        v2(210.0, 130.0),
+        v2(20.0, 400.0),
+        v2(780.0, 150.0),
+        v2(460.0, 330.0),
+        v2(1500.0, 460.0),
+    ],
+    do_at: Vec<Vector2> = vec![
+        v2(1430.0, 840.0),
+        v2(530.0, 300.0),
+        v2(860.0, 670.0),
+        v2(210.0, 130.0),
+        v2(20.0, 400.0),
+        v2(780.0, 150.0),
+        v2(460.0, 330.0),
+        v2(1500.0, 460.0),
+    ],
+    boi_at: Vec<Vector2> = vec![
+        v2(50.0, 600.0),
+        v2(400.0, 300.0),
+        v2(750.0, 50.0),
+    ],
+});
+
 impl FunkyBeat {
-    pub fn new(app: &App) -> Self {
+    pub fn info() -> StageInfo {
+        StageInfo::default()
+            .cfg::<FunkyBeatConfig>("funky_beat.cfg")
+            .scene("cuberoom.glb")
+            .nodes(&["Camera.000", "Cube"])
+            .materials(&["Cube", "Room"])
+            .mods(&[0, 1])
+            .names(&[
+                "do", "ow", "boi", "ah", "uh", "me", "give", "crash", "noise", "rride", "ride",
+                "bang", "hat", "synth", "kick", "getup", "getdown", "gimme", "aint", "sounds",
+                "fresh", "fun-ky", "real", "bock", "cut", "next_segment",
+            ])
+            .go_named("next_stage", "thanks")
+    }
+
+    pub fn new(app: &App, targets: &Targets, presets: &FxPresets) -> Self {
         let device = &app.device;
+        let res = Resolution::of("funky_beat");
 
         let decay = DecayEnv::default()
             .with("crash", 0.0)
@@ -86,7 +222,7 @@ impl FunkyBeat {
             .with("cut", 6 + 1)
             ;
 
-        let cfg = lib::resource::read_cfg("funky_beat.cfg");
+        let cfg = StageCfg::<FunkyBeatConfig>::load("funky_beat.cfg");
 
         let text0 = TextPassBuilder::default()
             .with("default", "da_mad_rave_italic.otf")
@@ -94,30 +230,37 @@ impl FunkyBeat {
         let text1 = TextPassBuilder::default()
             .with("default", "da_mad_rave_italic.otf")
             .build(device);
-        let mut tfx = FxPass::new(device, (640, 360));
-        tfx.vhs = cfg.f32("vhs");
-        tfx.bloom = cfg.f32("bloom");
+        let mut tfx = FxPass::new(device, res.size(), targets, presets);
+        tfx.vhs = cfg.vhs;
+        tfx.bloom = cfg.bloom;
         // tfx.edge = 1.0;
         let clear = ClearPass::new(device, wgpu::Color::BLACK);
 
         let scene = Phong::new(app, "cuberoom.glb", |_node| true, |_mat| true);
+        let seq = Sequencer::new()
+            .segment(Segment::Intro).on_name("next_segment")
+            .segment(Segment::Synth).on_name("next_segment")
+                .enter(|scene: &mut Phong| scene.material("Cube").color = v4(1.0, 1.0, 0.0, 1.0))
+            .segment(Segment::Twang).on_name("next_segment")
+                .enter(|scene: &mut Phong| scene.material("Cube").color = v4(0.0, 1.0, 1.0, 1.0))
+            .segment(Segment::AhAh).on_name("next_segment")
+                .enter(|scene: &mut Phong| scene.material("Cube").color = v4(1.0, 0.0, 1.0, 1.0));
         let animator = Animator::new(&scene.scene);
-        let composite = FilterPass::new_composite_sized::<()>(
-            device,
-            "funky",
-            3,
-            Some("composite_add.frag.spv"),
-            None,
-            (640, 360),
-        );
-        let fx = FxPass::new(device, (640, 360));
-        let blit = BlitPass::new("funky").build(device);
+        let graph = Graph::new("funky", res.size())
+            .source("scene")
+            .source("text0")
+            .source("text1")
+            .filter("composite", "composite_add.frag.spv", &["scene", "text0", "text1"], None)
+            .output("composite")
+            .build(device, targets);
+        let fx = FxPass::new(device, res.size(), targets, presets);
+        let blit = BlitPass::new("funky").fit(res).pooled(targets, res.size()).build(device);
 
         Self {
-            segment: Segment::Intro,
+            seq,
 
-            t: 0.0,
-            t_mul: 1.0,
+            clock: Clock::new("rms").speed(50.0),
+            paused: false,
 
             animator,
             decay,
@@ -130,7 +273,7 @@ impl FunkyBeat {
             clear,
 
             scene,
-            composite,
+            graph,
             fx,
             blit,
         }
@@ -140,11 +283,16 @@ impl FunkyBeat {
 #[async_trait]
 impl Stage for FunkyBeat {
     async fn init(&mut self, p: &mut Player) {
-        self.animator.play(self.t, true, "CubeAction");
+        self.animator.play(self.clock.t(), true, "CubeAction");
     }
 
     async fn update(&mut self, p: &mut Player, dt: f32) {
-        self.t += 50.0 * p.rms() * self.t_mul * dt;
+        if self.cfg.reload() {
+            self.tfx.vhs = self.cfg.vhs;
+            self.tfx.bloom = self.cfg.bloom;
+        }
+        self.clock.update(p, dt);
+        self.seq.update(p, dt, &mut self.scene);
 
         // Room white
         if let Some(fr) = self.decay.vv("hat") {
@@ -174,7 +322,7 @@ impl Stage for FunkyBeat {
         // self.fx.state.edge = self.decay.v("edge");
         self.fx.state.edge = self.decay.v("ah");
         self.fx.state.flash = self.decay.v("rride");
-        self.fx.state.vhs = match self.segment {
+        self.fx.state.vhs = match self.seq.current() {
             Segment::Twang => self.decay.v("hat"),
             _ => 0.0,
         };
@@ -182,74 +330,65 @@ impl Stage for FunkyBeat {
         self.fx.state.mega = self.decay.v("shake");
         self.fx.state.glitch = self.decay.v("glitch");
         self.fx.state.vhs = self.decay.v("vhs");
-        self.fx.state.pause = self.decay.v("pause");
+        self.fx.state.pause = if self.paused { 1.0 } else { self.decay.v("pause") };
         self.fx.state.red = self.decay.v("red");
         self.fx.state.mega = self.decay.v("mega");
         self.fx.state.invert = self.decay.v("invert");
 
-        self.animator.update(self.t, &mut self.scene.scene);
+        self.animator.update(self.clock.t(), &mut self.scene.scene);
         self.decay.update(dt);
-        self.fx.update(p.t(), self.t);
-        self.tfx.update(p.t(), self.t);
+        self.fx.update(p.t(), self.clock.t());
+        self.tfx.update(p.t(), self.clock.t());
     }
 
     async fn event(&mut self, p: &mut Player, ev: Event) {
+        self.seq.event(p, ev, &mut self.scene);
+
+        match ev {
+            Event::Mod { id: 0, fr } => self.fx.state.invert = fr,
+            Event::Mod { id: 1, fr } => self.clock.set_mul(2.0 * fr),
+
+            _ => {}
+        }
+    }
+
+    async fn named(&mut self, p: &mut Player, name: &str, ev: Event) {
         let decay = &mut self.decay;
         let count = &mut self.count;
+        let t = match ev {
+            Event::Beat { t, .. } => t,
+            _ => 0.0,
+        };
 
-        match ev {
-            Event::Beat { id: 75, t } => { count.inc("do"); decay.set_t("do", t * 1.25); },
-            Event::Beat { id: 74, t } => decay.set_t("ow", t * 2.0),
-            Event::Beat { id: 73, t } => { count.inc("boi"); decay.set_t("boi", t * 2.0); },
-            Event::Beat { id: 72, t } => {
+        match name {
+            "do" => { count.inc("do"); decay.set_t("do", t * 1.25); },
+            "ow" => decay.set_t("ow", t * 2.0),
+            "boi" => { count.inc("boi"); decay.set_t("boi", t * 2.0); },
+            "ah" => {
                 decay.set_t("ah", t * 1.25);
                 decay.set_t("kick", t * 1.25);
                 count.inc("ah");
                 count.inc("camjump");
             },
-            Event::Beat { id: 71, t } => decay.set_t("uh", t * 2.0),
-            Event::Beat { id: 70, t } => decay.set_t("me", t * 2.0),
-            Event::Beat { id: 69, t } => decay.set_t("give", t * 2.0),
-
-            Event::Beat { id: 68, t } => decay.set_t("crash", t),
-            Event::Beat { id: 67, t } => decay.set_t("noise", t * 4.0),
-            Event::Beat { id: 66, t } => decay.set_t("rride", t),
-            Event::Beat { id: 65, t } => decay.set_t("ride", t * 4.0),
-            Event::Beat { id: 64, t } => decay.set_t("bang", t * 4.0),
-            Event::Beat { id: 62, t } => decay.set_t("hat", t),
-            Event::Beat { id: 61, t } => decay.set_t("synth", t),
-            Event::Beat { id: 60, t } => { decay.set_t("kick", t * 1.25); count.inc("camjump"); },
-
-            Event::Trigger { id: 25 } => self.segment = match self.segment {
-                Segment::Intro => {
-                    self.scene.material("Cube").color = v4(1.0, 1.0, 0.0, 1.0);
-                    Segment::Synth
-                },
-                Segment::Synth => {
-                    self.scene.material("Cube").color = v4(0.0, 1.0, 1.0, 1.0);
-                    Segment::Twang
-                },
-                Segment::Twang => {
-                    self.scene.material("Cube").color = v4(1.0, 0.0, 1.0, 1.0);
-                    Segment::AhAh
-                },
-                Segment::AhAh => Segment::Intro,
+            "uh" => decay.set_t("uh", t * 2.0),
+            "me" => decay.set_t("me", t * 2.0),
+            "give" => decay.set_t("give", t * 2.0),
+
+            "crash" => decay.set_t("crash", t),
+            "noise" => decay.set_t("noise", t * 4.0),
+            "rride" => decay.set_t("rride", t),
+            "ride" => decay.set_t("ride", t * 4.0),
+            "bang" => decay.set_t("bang", t * 4.0),
+            "hat" => decay.set_t("hat", t),
+            "synth" => decay.set_t("synth", t),
+            "kick" => { decay.set_t("kick", t * 1.25); count.inc("camjump"); },
+
+            // Lyric triggers step through their words
+            "getup" | "getdown" | "gimme" | "aint" | "sounds" | "fresh" | "fun-ky" | "real" | "bock" | "cut" => {
+                count.inc(name);
             },
-            Event::Trigger { id: 29 } => { count.inc("getup"); }
-            Event::Trigger { id: 28 } => { count.inc("getdown"); }
-            Event::Trigger { id: 27 } => { count.inc("gimme"); }
-            Event::Trigger { id: 26 } => { count.inc("aint"); }
-            Event::Trigger { id: 24 } => { count.inc("sounds"); },
-            Event::Trigger { id: 23 } => { count.inc("fresh"); },
-            Event::Trigger { id: 22 } => { count.inc("fun-ky"); },
-            Event::Trigger { id: 21 } => { count.inc("real"); },
-            Event::Trigger { id: 20 } => { count.inc("bock"); },
-            Event::Trigger { id: 19 } => { count.inc("cut"); },
-
-            Event::Trigger { id: 10 } => p.go("thanks").await,
 
-            Event::Mod { id: 0, fr } => self.fx.state.invert = fr,
-            Event::Mod { id: 1, fr } => self.t_mul = 2.0 * fr,
+            "next_stage" => p.go("thanks").await,
 
             _ => {}
         }
@@ -265,7 +404,34 @@ impl Stage for FunkyBeat {
         }
     }
 
+    async fn leave(&mut self, p: &mut Player) {
+        self.animator.stop("CubeAction");
+    }
+
+    async fn pause(&mut self, p: &mut Player) {
+        self.paused = true;
+        self.fx.state.pause = 1.0;
+    }
+
+    async fn resume(&mut self, p: &mut Player) {
+        self.paused = false;
+        self.fx.state.pause = self.decay.v("pause");
+    }
+
+    async fn reset(&mut self, p: &mut Player) {
+        self.seq.reset();
+        self.clock.reset();
+        self.count.reset();
+    }
+
     #[rustfmt::skip]
+    fn reload(&mut self, device: &wgpu::Device, changed: &[String]) {
+        self.tfx.reload(device, changed);
+        self.graph.reload(device, changed);
+        self.fx.reload(device, changed);
+        self.blit.reload(device, changed);
+    }
+
     fn view(&mut self, frame: &mut Frame, view: &wgpu::RawTextureView) {
         let text0 = &mut self.text0;
         let text1 = &mut self.text1;
@@ -274,143 +440,143 @@ impl Stage for FunkyBeat {
         let cfg = &self.cfg;
 
         if let Some(fr) = decay.vv("noise") {
-            text(text0, "*kssh*", 80.0, cfg.v2("noise"), v4(1.0, 1.0, 1.0, fr)); }
+            text(text0, "*kssh*", 80.0, cfg.noise, v4(1.0, 1.0, 1.0, fr)); }
         if let Some(fr) = decay.vv("ride") {
-            text(text0, "*ting*", 80.0, cfg.v2("ride"), v4(1.0, 1.0, 1.0, fr)); }
+            text(text0, "*ting*", 80.0, cfg.ride, v4(1.0, 1.0, 1.0, fr)); }
         if let Some(fr) = decay.vv("bang") {
-            text(text0, "*bang*", 80.0, cfg.v2("bang"), v4(1.0, 1.0, 1.0, fr)); }
+            text(text0, "*bang*", 80.0, cfg.bang, v4(1.0, 1.0, 1.0, fr)); }
 
         if let Some(fr) = decay.vv("give") {
-            text(text0, "give", cfg.f32("give_s"), cfg.v2("give"), v4(1.0, 1.0, 1.0, fr)); }
+            text(text0, "give", cfg.give_s, cfg.give, v4(1.0, 1.0, 1.0, fr)); }
         if let Some(fr) = decay.vv("me") {
-            text(text0, "me", cfg.f32("me_s"), cfg.v2("me"), v4(1.0, 1.0, 1.0, fr)); }
+            text(text0, "me", cfg.me_s, cfg.me, v4(1.0, 1.0, 1.0, fr)); }
 
         if let Some(fr) = decay.vv("boi") {
-            text(text0, "boi!", cfg.f32("boi_s"), cfg.v2(&format!("boi{}", count.v("boi"))), v4(1.0, 1.0, 1.0, fr)); }
+            text(text0, "boi!", cfg.boi_s, nth(&cfg.boi_at, count.v("boi")), v4(1.0, 1.0, 1.0, fr)); }
         if let Some(fr) = decay.vv("uh") {
-            text(text0, "uh!", cfg.f32("uh_s"), cfg.v2("uh"), v4(1.0, 1.0, 1.0, fr)); }
+            text(text0, "uh!", cfg.uh_s, cfg.uh, v4(1.0, 1.0, 1.0, fr)); }
         if let Some(fr) = decay.vv("ow") {
-            text(text0, "ow!", cfg.f32("ow_s"), cfg.v2("ow"), v4(1.0, 1.0, 1.0, fr)); }
+            text(text0, "ow!", cfg.ow_s, cfg.ow, v4(1.0, 1.0, 1.0, fr)); }
 
         if let Some(fr) = decay.vv("ah") {
-            text(text0, "ah!", cfg.f32("ah_s"), cfg.v2(&format!("ah{}", count.v("ah"))), v4(1.0, 0.0, 1.0, 1.0)) }
+            text(text0, "ah!", cfg.ah_s, nth(&cfg.ah_at, count.v("ah")), v4(1.0, 0.0, 1.0, 1.0)) }
 
         if let Some(fr) = decay.vv("do") {
-            text(text0, "do!", cfg.f32("do_s"), cfg.v2(&format!("do{}", count.v("do"))), v4(1.0, 1.0, 0.0, 1.0)) }
+            text(text0, "do!", cfg.do_s, nth(&cfg.do_at, count.v("do")), v4(1.0, 1.0, 0.0, 1.0)) }
 
 
         let w = v4(1.0, 1.0, 1.0, 1.0);
-        let fw = v4(1.0, 1.0, 1.0, cfg.f32("fade"));
+        let fw = v4(1.0, 1.0, 1.0, cfg.fade);
 
-        type_text(text0, count.v("getup"), "get^ up^", cfg.f32("getup_s"), cfg.v2("getup"), w);
-        type_text(text0, count.v("getdown"), "get^ down^", cfg.f32("getdown_s"), cfg.v2("getdown"), fw);
+        type_text(text0, count.v("getup"), "get^ up^", cfg.getup_s, cfg.getup, w);
+        type_text(text0, count.v("getdown"), "get^ down^", cfg.getdown_s, cfg.getdown, fw);
 
         place_texts(count.v("gimme"), &mut [text0, text1], &[
             (0, vec![
-                ("gim",   cfg.f32("gimmes0"), cfg.v2("gimme0"), fw),
-                ("gimme", cfg.f32("gimmes1"), cfg.v2("gimme1"), fw),
-                ("one",   cfg.f32("gimmes2"), cfg.v2("gimme2"), fw),
-                ("dem",   cfg.f32("gimmes3"), cfg.v2("gimme3"), fw),
+                ("gim",   cfg.gimmes0, cfg.gimme0, fw),
+                ("gimme", cfg.gimmes1, cfg.gimme1, fw),
+                ("one",   cfg.gimmes2, cfg.gimme2, fw),
+                ("dem",   cfg.gimmes3, cfg.gimme3, fw),
             ]),
             (1, vec![
-                ("fun",   cfg.f32("funkys"), cfg.v2("funky"), w),
-                ("funky", cfg.f32("funkys"), cfg.v2("funky"), w),
-                ("beats", cfg.f32("beats"), cfg.v2("beats0"), w),
+                ("fun",   cfg.funkys, cfg.funky, w),
+                ("funky", cfg.funkys, cfg.funky, w),
+                ("beats", cfg.beats, cfg.beats0, w),
             ]),
         ]);
 
         place_texts(count.v("aint"), &mut [text0, text1], &[
             (0, vec![
-                ("ain't",   cfg.f32("aints0"), cfg.v2("aint0"), fw),
-                ("no",      cfg.f32("aints1"), cfg.v2("aint1"), fw),
-                ("nothing", cfg.f32("aints2"), cfg.v2("aint2"), fw),
-                ("like",    cfg.f32("aints3"), cfg.v2("aint3"), fw),
-                ("a",       cfg.f32("aints4"), cfg.v2("aint4"), fw),
+                ("ain't",   cfg.aints0, cfg.aint0, fw),
+                ("no",      cfg.aints1, cfg.aint1, fw),
+                ("nothing", cfg.aints2, cfg.aint2, fw),
+                ("like",    cfg.aints3, cfg.aint3, fw),
+                ("a",       cfg.aints4, cfg.aint4, fw),
             ]),
             (1, vec![
-                ("fun",   cfg.f32("funkys"), cfg.v2("funky"), w),
-                ("funky", cfg.f32("funkys"), cfg.v2("funky"), w),
-                ("beat",  cfg.f32("beats"), cfg.v2("beat0"), w),
+                ("fun",   cfg.funkys, cfg.funky, w),
+                ("funky", cfg.funkys, cfg.funky, w),
+                ("beat",  cfg.beats, cfg.beat0, w),
             ]),
         ]);
 
         place_texts(count.v("sounds"), &mut [text0, text1], &[
             (0, vec![
-                ("sounds", cfg.f32("soundss0"), cfg.v2("sounds0"), fw),
-                ("so",     cfg.f32("soundss1"), cfg.v2("sounds1"), fw),
+                ("sounds", cfg.soundss0, cfg.sounds0, fw),
+                ("so",     cfg.soundss1, cfg.sounds1, fw),
             ]),
             (1, vec![
-                ("fun",   cfg.f32("funkys"), cfg.v2("funky"), w),
-                ("funky", cfg.f32("funkys"), cfg.v2("funky"), w),
+                ("fun",   cfg.funkys, cfg.funky, w),
+                ("funky", cfg.funkys, cfg.funky, w),
             ]),
             (0, vec![
-                ("to", cfg.f32("soundss2"), cfg.v2("sounds2"), w),
-                ("to me", cfg.f32("soundss2"), cfg.v2("sounds2"), w),
+                ("to", cfg.soundss2, cfg.sounds2, w),
+                ("to me", cfg.soundss2, cfg.sounds2, w),
             ]),
         ]);
 
         place_texts(count.v("fresh"), &mut [text0, text1], &[
             (1, vec![
-                ("fun",   cfg.f32("funkys"), cfg.v2("funky"), w),
-                ("funky", cfg.f32("funkys"), cfg.v2("funky"), w),
-                ("fresh", cfg.f32("freshs0"), cfg.v2("fresh0"), fw),
-                ("beats", cfg.f32("freshs1"), cfg.v2("fresh1"), fw),
+                ("fun",   cfg.funkys, cfg.funky, w),
+                ("funky", cfg.funkys, cfg.funky, w),
+                ("fresh", cfg.freshs0, cfg.fresh0, fw),
+                ("beats", cfg.freshs1, cfg.fresh1, fw),
             ]),
         ]);
 
         place_text(text1, count.v("fun-ky"), &[
-            ("fun", cfg.f32("fun-kys0"), cfg.v2("fun-ky0"), w),
-            ("ky",  cfg.f32("fun-kys1"), cfg.v2("fun-ky1"), w),
+            ("fun", cfg.fun_kys0, cfg.fun_ky0, w),
+            ("ky",  cfg.fun_kys1, cfg.fun_ky1, w),
         ]);
 
         place_text(text0, count.v("real"), &[
-            ("ain't",   cfg.f32("reals0"), cfg.v2("real0"), fw),
-            ("no",      cfg.f32("reals1"), cfg.v2("real1"), fw),
-            ("nothing", cfg.f32("reals2"), cfg.v2("real2"), fw),
-            ("like",    cfg.f32("reals3"), cfg.v2("real3"), fw),
-            ("the",     cfg.f32("reals4"), cfg.v2("real4"), fw),
-            ("real",    cfg.f32("reals5"), cfg.v2("real5"), fw),
-            ("thing",   cfg.f32("reals6"), cfg.v2("real6"), fw),
+            ("ain't",   cfg.reals0, cfg.real0, fw),
+            ("no",      cfg.reals1, cfg.real1, fw),
+            ("nothing", cfg.reals2, cfg.real2, fw),
+            ("like",    cfg.reals3, cfg.real3, fw),
+            ("the",     cfg.reals4, cfg.real4, fw),
+            ("real",    cfg.reals5, cfg.real5, fw),
+            ("thing",   cfg.reals6, cfg.real6, fw),
         ]);
 
         place_texts(count.v("bock"), &mut [text0, text1], &[
             (0, vec![
-                ("bock", cfg.f32("bocks0"), cfg.v2("bock0"), fw),
-                ("the",  cfg.f32("bocks1"), cfg.v2("bock1"), fw),
-                ("bock", cfg.f32("bocks2"), cfg.v2("bock2"), fw),
-                ("i'm",  cfg.f32("bocks3"), cfg.v2("bock3"), fw),
-                ("on",   cfg.f32("bocks4"), cfg.v2("bock4"), fw),
+                ("bock", cfg.bocks0, cfg.bock0, fw),
+                ("the",  cfg.bocks1, cfg.bock1, fw),
+                ("bock", cfg.bocks2, cfg.bock2, fw),
+                ("i'm",  cfg.bocks3, cfg.bock3, fw),
+                ("on",   cfg.bocks4, cfg.bock4, fw),
             ]),
             (1, vec![
-                ("fun", cfg.f32("fun-kys0"), cfg.v2("fun-ky0"), w),
-                ("ky",  cfg.f32("fun-kys1"), cfg.v2("fun-ky1"), w),
+                ("fun", cfg.fun_kys0, cfg.fun_ky0, w),
+                ("ky",  cfg.fun_kys1, cfg.fun_ky1, w),
             ]),
         ]);
 
         place_texts(count.v("cut"), &mut [text0, text1], &[
             (0, vec![
-                ("cut",  cfg.f32("cuts0"), cfg.v2("cut0"), fw),
-                ("the",  cfg.f32("cuts1"), cfg.v2("cut1"), fw),
-                ("beat", cfg.f32("cuts2"), cfg.v2("cut2"), fw),
-                ("it's", cfg.f32("cuts3"), cfg.v2("cut3"), fw),
+                ("cut",  cfg.cuts0, cfg.cut0, fw),
+                ("the",  cfg.cuts1, cfg.cut1, fw),
+                ("beat", cfg.cuts2, cfg.cut2, fw),
+                ("it's", cfg.cuts3, cfg.cut3, fw),
             ]),
             (1, vec![
-                ("fun", cfg.f32("fun-kys0"), cfg.v2("fun-ky0"), w),
-                ("ky",  cfg.f32("fun-kys1"), cfg.v2("fun-ky1"), w),
+                ("fun", cfg.fun_kys0, cfg.fun_ky0, w),
+                ("ky",  cfg.fun_kys1, cfg.fun_ky1, w),
             ]),
         ]);
 
-        self.scene.encode(frame, self.composite.view(0));
-
-        self.clear.encode(frame, self.composite.view(1));
-        self.text0.encode(frame, self.composite.view(1));
-
-        self.clear.encode(frame, self.tfx.view());
-        self.text1.encode(frame, self.tfx.view());
-        self.tfx.upload(frame);
-        self.tfx.encode(frame, self.composite.view(2));
-
-        self.composite.encode(frame, self.fx.view());
+        self.graph.encode(frame, self.fx.view(), |frame, node, target| match node {
+            "scene" => self.scene.encode(frame, target),
+            "text0" => self.text0.encode(frame, target),
+            "text1" => {
+                self.clear.encode(frame, self.tfx.view());
+                self.text1.encode(frame, self.tfx.view());
+                self.tfx.upload(frame);
+                self.tfx.encode(frame, target);
+            }
+            _ => {}
+        });
         self.fx.upload(frame);
         self.fx.encode(frame, self.blit.view());
         self.blit.encode(frame, view);
@@ -421,6 +587,11 @@ fn text(pass: &mut TextPass, s: &str, scale: f32, pos: Vector2, color: Vector4)
     pass.draw(|d| d.at(pos).text(s, |t| t.scale(scale).color(color)));
 }
 
+/// The `n`th of a list of positions, or the origin if the list is too short.
+fn nth(at: &[Vector2], n: usize) -> Vector2 {
+    at.get(n).copied().unwrap_or_else(|| v2(0.0, 0.0))
+}
+
 fn type_text(pass: &mut TextPass, n: usize, s: &str, scale: f32, pos: Vector2, color: Vector4) {
     match n {
         n if n > 0 => {
@@ -4,25 +4,26 @@ use lib::gfx::pass::*;
 use lib::gfx::scene::Node;
 use lib::prelude::*;
 
-use crate::demo::{Event, Player, Stage};
+use crate::demo::{Event, Id, Player, Stage, StageInfo};
 use crate::pipeline::*;
 
 pub struct Halo {
     segment: Segment,
 
-    t: f32,
-    t_mul: f32,
+    clock: Clock,
+    spin: Clock,
     angle: Euler<Rad<f32>>,
 
     decay: DecayEnv,
     count: CounterEnv,
-    cfg: Config,
+    cfg: StageCfg<HaloConfig>,
+    matrix: ModMatrix,
 
     scene: Phong,
     animator1: Animator,
     animator2: Animator,
 
-    composite: FilterPass,
+    graph: Graph,
     fx: FxPass,
     blit: BlitPass,
 }
@@ -32,9 +33,28 @@ enum Segment {
 
 }
 
+stage_config!(HaloConfig {
+    tmul: f32 = 800.0,
+    mul: f32 = 800.0,
+    x: f32 = 1.0,
+    y: f32 = 2.0,
+    z: f32 = 0.3,
+});
+
 impl Halo {
-    pub fn new(app: &App) -> Self {
+    pub fn info() -> StageInfo {
+        StageInfo::default()
+            .cfg::<HaloConfig>("halo.cfg")
+            .matrix("halo.cfg")
+            .scene("halo.glb")
+            .nodes(&["Halo"])
+            .notes(&[28, 60, 61])
+            .go(Id::Note(10), "aqua")
+    }
+
+    pub fn new(app: &App, targets: &Targets, presets: &FxPresets) -> Self {
         let device = &app.device;
+        let res = Resolution::of("halo");
 
         let decay = DecayEnv::default()
             .with("bigkick", 0.0)
@@ -42,28 +62,26 @@ impl Halo {
             ;
         let count = CounterEnv::default();
 
-        let cfg = lib::resource::read_cfg("halo.cfg");
+        let cfg = StageCfg::<HaloConfig>::load("halo.cfg");
+        let matrix = ModMatrix::load("halo.cfg", &decay, &count, None);
 
         let scene = Phong::new(app, "halo.glb", |_node| true, |_mat| true);
         let animator1 = Animator::new(&scene.scene);
         let animator2 = Animator::new(&scene.scene);
 
-        let composite = FilterPass::new_composite_sized::<()>(
-            device,
-            "halo_composite",
-            2,
-            Some("composite_add.frag.spv"),
-            None,
-            (640, 360),
-        );
-        let fx = FxPass::new(device, (640, 360));
-        let blit = BlitPass::new("halo").build(device);
+        let graph = Graph::new("halo", res.size())
+            .source("scene")
+            .filter("composite", "composite_add.frag.spv", &["scene"], None)
+            .output("composite")
+            .build(device, targets);
+        let fx = FxPass::new(device, res.size(), targets, presets);
+        let blit = BlitPass::new("halo").fit(res).pooled(targets, res.size()).build(device);
 
         Self {
             segment: Segment::Init,
 
-            t: 0.0,
-            t_mul: 0.0,
+            clock: Clock::new("rms").speed(cfg.tmul).mul(0.0),
+            spin: Clock::new("rms").speed(cfg.mul).mul(0.0),
             angle: Euler {
                 x: Rad(0.0),
                 y: Rad(0.0),
@@ -73,12 +91,13 @@ impl Halo {
             decay,
             count,
             cfg,
+            matrix,
 
             scene,
             animator1,
             animator2,
 
-            composite,
+            graph,
             fx,
             blit
         }
@@ -92,21 +111,24 @@ impl Stage for Halo {
     }
 
     async fn update(&mut self, p: &mut Player, dt: f32) {
-        self.t += self.cfg.f32("tmul") * p.rms() * self.t_mul * dt;
+        self.cfg.reload();
+        self.clock.set_speed(self.cfg.tmul);
+        self.clock.update(p, dt);
 
         self.animator1.update(p.t(), &mut self.scene.scene);
-        self.animator2.update(self.t, &mut self.scene.scene);
+        self.animator2.update(self.clock.t(), &mut self.scene.scene);
         self.decay.update(dt);
-        self.fx.update(p.t(), self.t);
+        self.fx.update(p.t(), self.clock.t());
 
-        let mul = self.cfg.f32("mul");
-        self.angle.x += Rad(mul * p.rms() * self.t_mul * dt * self.cfg.f32("x"));
-        self.angle.y += Rad(mul * p.rms() * self.t_mul * dt * self.cfg.f32("y"));
-        self.angle.z += Rad(mul * p.rms() * self.t_mul * dt * self.cfg.f32("z"));
+        self.spin.set_speed(self.cfg.mul);
+        self.spin.update(p, dt);
+        self.angle.x += Rad(self.spin.dt() * self.cfg.x);
+        self.angle.y += Rad(self.spin.dt() * self.cfg.y);
+        self.angle.z += Rad(self.spin.dt() * self.cfg.z);
         self.scene.node("Halo").transform.rotate = self.angle.into();
 
-        self.fx.shake = self.decay.v("bigkick") * self.cfg.f32("shake");
-        self.fx.flash = self.decay.v("bigsnare") * self.cfg.f32("flash");
+        self.matrix.update(p, &self.decay, &self.count, None);
+        self.matrix.apply_fx(&mut self.fx);
     }
 
     async fn event(&mut self, p: &mut Player, ev: Event) {
@@ -119,17 +141,12 @@ impl Stage for Halo {
 
             // Event::Trigger { id: 29 } => self.animator1.play(p.t(), false, "Camera Intro Pan"),
             Event::Trigger { id: 28 } => {
-                self.t_mul = 1.0;
+                self.clock.set_mul(1.0);
+                self.spin.set_mul(1.0);
                 self.animator1.play(p.t(), false, "Camera Intro Impact")
             },
             Event::Trigger { id: 10 } => p.go("aqua").await,
 
-            Event::Mod { id: 0, fr } => {
-                self.fx.vhs = fr;
-                self.fx.edge = fr;
-                self.fx.glitch = fr;
-            }
-
             _ => {}
         }
     }
@@ -149,14 +166,21 @@ impl Stage for Halo {
         }
     }
 
+    fn reload(&mut self, device: &wgpu::Device, changed: &[String]) {
+        self.graph.reload(device, changed);
+        self.fx.reload(device, changed);
+        self.blit.reload(device, changed);
+    }
+
     fn view(&mut self, frame: &mut Frame, view: &wgpu::RawTextureView) {
         let decay = &self.decay;
         let count = &self.count;
         let cfg = &self.cfg;
 
-        self.scene.encode(frame, self.composite.view(0));
-
-        self.composite.encode(frame, self.fx.view());
+        self.graph.encode(frame, self.fx.view(), |frame, node, target| match node {
+            "scene" => self.scene.encode(frame, target),
+            _ => {}
+        });
         self.fx.upload(frame);
         self.fx.encode(frame, self.blit.view());
         self.blit.encode(frame, view);
@@ -5,24 +5,25 @@ use lib::gfx::scene::Node;
 use lib::prelude::*;
 use palette::{Hsl, Srgb, FromColor};
 
-use crate::demo::{Event, Player, Stage};
+use crate::demo::{Event, Id, Player, Stage, StageInfo};
 use crate::pipeline::*;
 
 pub struct Lobby {
     segment: Segment,
 
-    t: f32,
-    t_mul: f32,
+    clock: Clock,
 
     decay: DecayEnv,
     count: CounterEnv,
-    cfg: Config,
+    cfg: StageCfg<LobbyConfig>,
+    routes: Routes,
+    matrix: ModMatrix,
 
     scene: Phong,
     animator1: Animator,
     animator2: Animator,
 
-    composite: FilterPass,
+    graph: Graph,
     fx: FxPass,
     blit: BlitPass,
 }
@@ -32,9 +33,28 @@ enum Segment {
 
 }
 
+stage_config!(LobbyConfig {
+    tmul: f32 = 500.0,
+    lrange: f32 = 2.0,
+});
+
 impl Lobby {
-    pub fn new(app: &App) -> Self {
+    pub fn info() -> StageInfo {
+        StageInfo::default()
+            .cfg::<LobbyConfig>("lobby.cfg")
+            .routes("lobby.cfg")
+            .matrix("lobby.cfg")
+            .scene("demo_console.glb")
+            .materials(&["ButtonMain"])
+            .lights(&["ButtonMainPoint"])
+            .notes(&[28, 29])
+            .mods(&[0, 1])
+            .go(Id::Note(10), "metalheart")
+    }
+
+    pub fn new(app: &App, targets: &Targets, presets: &FxPresets) -> Self {
         let device = &app.device;
+        let res = Resolution::of("lobby");
 
         let decay = DecayEnv::default()
             .with("bling", 0.0)
@@ -46,38 +66,38 @@ impl Lobby {
 
         let count = CounterEnv::default();
 
-        let cfg = lib::resource::read_cfg("lobby.cfg");
+        let cfg = StageCfg::<LobbyConfig>::load("lobby.cfg");
+        let routes = Routes::load("lobby.cfg", &decay, &count);
+        let matrix = ModMatrix::load("lobby.cfg", &decay, &count, None);
 
         let scene = Phong::new(app, "demo_console.glb", |_node| true, |_mat| true);
         let animator1 = Animator::new(&scene.scene);
         let animator2 = Animator::new(&scene.scene);
 
-        let composite = FilterPass::new_composite_sized::<()>(
-            device,
-            "lobby_composite",
-            2,
-            Some("composite.frag.spv"),
-            None,
-            (640, 360),
-        );
-        let fx = FxPass::new(device, (640, 360));
-        let blit = BlitPass::new("funky").build(device);
+        let graph = Graph::new("lobby", res.size())
+            .source("scene")
+            .filter("composite", "composite.frag.spv", &["scene"], None)
+            .output("composite")
+            .build(device, targets);
+        let fx = FxPass::new(device, res.size(), targets, presets);
+        let blit = BlitPass::new("funky").fit(res).pooled(targets, res.size()).build(device);
 
         Self {
             segment: Segment::Init,
 
-            t: 0.0,
-            t_mul: 1.0,
+            clock: Clock::new("rms").speed(cfg.tmul),
 
             decay,
             count,
             cfg,
+            routes,
+            matrix,
 
             scene,
             animator1,
             animator2,
 
-            composite,
+            graph,
             fx,
             blit
         }
@@ -92,19 +112,22 @@ impl Stage for Lobby {
     }
 
     async fn update(&mut self, p: &mut Player, dt: f32) {
-        self.t += self.cfg.f32("tmul") * p.rms() * self.t_mul * dt;
+        self.cfg.reload();
+        self.clock.set_speed(self.cfg.tmul);
+        self.clock.update(p, dt);
 
         self.animator1.update(p.t(), &mut self.scene.scene);
-        self.animator2.update(self.t, &mut self.scene.scene);
+        self.animator2.update(self.clock.t(), &mut self.scene.scene);
         self.decay.update(dt);
-        self.fx.update(p.t(), self.t);
+        self.fx.update(p.t(), self.clock.t());
 
         let hsl = Hsl::new(0.0, self.decay.v("hat"), 0.5);
         let rgb = Srgb::from_color(hsl);
         self.scene.material("ButtonMain").color = v4(rgb.red, rgb.green, rgb.blue, 1.0);
-        self.scene.light("ButtonMainPoint").range = self.cfg.f32("lrange") * self.decay.v("hat2");
+        self.scene.light("ButtonMainPoint").range = self.cfg.lrange * self.decay.v("hat2");
 
-        self.fx.shake = self.decay.v("kick");
+        self.matrix.update(p, &self.decay, &self.count, None);
+        self.matrix.apply_fx(&mut self.fx);
     }
 
     async fn event(&mut self, p: &mut Player, ev: Event) {
@@ -112,51 +135,68 @@ impl Stage for Lobby {
         let count = &mut self.count;
 
         match ev {
-            // Event::Beat { id: 60, t } => self.decay.set_t("kick", t * 2.0),
-            Event::Beat { id: 61, t } => {
-                self.decay.set_t("hat", t * 2.0);
-                self.decay.set_t("hat2", t);
-            },
-            Event::Beat { id: 62, t } => self.decay.set_t("kick", t),
-            Event::Beat { id: 63, t } => self.decay.set_t("snare", t),
-
-            Event::Trigger { id: 29 } => self.animator2.play(self.t, true, "Idle Disc Rotate Loop"),
+            Event::Trigger { id: 29 } => self.animator2.play(self.clock.t(), true, "Idle Disc Rotate Loop"),
             Event::Trigger { id: 28 } => {
                 self.animator1.stop("Idle Disc Bob Loop");
                 self.animator1.play(p.t(), false, "Disc Insert");
             },
             Event::Trigger { id: 10 } => p.go("metalheart").await,
 
-            Event::Mod { id: 0, fr } => self.t_mul = fr * 2.0,
+            Event::Mod { id: 0, fr } => self.clock.set_mul(fr * 2.0),
             Event::Mod { id: 1, fr } => *self.fx.alpha = 1.0 - fr,
-            Event::Mod { id: 2, fr } => self.fx.flash = fr,
-            Event::Mod { id: 3, fr } => self.fx.edge = fr,
-            Event::Mod { id: 4, fr } => self.fx.vhs = fr,
 
             _ => {}
         }
     }
 
+    fn routes(&mut self) -> Option<(&mut Routes, &mut DecayEnv, &mut CounterEnv)> {
+        Some((&mut self.routes, &mut self.decay, &mut self.count))
+    }
+
     async fn key(&mut self, p: &mut Player, state: KeyState, key: Key) {
         if state != KeyState::Pressed {
             return;
         }
 
         match key {
-            Key::Key1 => { log::info!("Disc rotate"); self.animator2.play(self.t, false, "Idle Disc Rotate Loop"); },
+            Key::Key1 => { log::info!("Disc rotate"); self.animator2.play(self.clock.t(), false, "Idle Disc Rotate Loop"); },
             Key::Key2 => { log::info!("Disc Insert"); self.animator1.play(p.t(), false, "Disc Insert"); },
             _ => {}
         }
     }
 
+    async fn leave(&mut self, p: &mut Player) {
+        self.animator1.stop("Idle Disc Bob Loop");
+        self.animator2.stop("Idle Disc Rotate Loop");
+    }
+
+    async fn pause(&mut self, p: &mut Player) {
+        self.fx.pause = 1.0;
+    }
+
+    async fn resume(&mut self, p: &mut Player) {
+        self.fx.pause = 0.0;
+    }
+
+    async fn reset(&mut self, p: &mut Player) {
+        self.clock.reset();
+    }
+
+    fn reload(&mut self, device: &wgpu::Device, changed: &[String]) {
+        self.graph.reload(device, changed);
+        self.fx.reload(device, changed);
+        self.blit.reload(device, changed);
+    }
+
     fn view(&mut self, frame: &mut Frame, view: &wgpu::RawTextureView) {
         let decay = &self.decay;
         let count = &self.count;
         let cfg = &self.cfg;
 
-        self.scene.encode(frame, self.composite.view(1));
-
-        self.composite.encode(frame, self.fx.view());
+        self.graph.encode(frame, self.fx.view(), |frame, node, target| match node {
+            "scene" => self.scene.encode(frame, target),
+            _ => {}
+        });
         self.fx.upload(frame);
         self.fx.encode(frame, self.blit.view());
         self.blit.encode(frame, view);
@@ -4,23 +4,22 @@ use lib::gfx::pass::*;
 use lib::gfx::scene::Node;
 use lib::prelude::*;
 
-use crate::demo::{Event, Player, Stage};
+use crate::demo::{Event, Id, Player, Stage, StageInfo};
 use crate::pipeline::*;
 
 pub struct Metalheart {
     segment: Segment,
 
-    t: f32,
-    t_mul: f32,
+    clock: Clock,
 
     decay: DecayEnv,
     count: CounterEnv,
-    cfg: Config,
+    cfg: StageCfg<MetalheartConfig>,
 
     scene: Phong,
     tri: IsoTriPass,
 
-    composite: FilterPass,
+    graph: Graph,
     fx: FxPass,
     blit: BlitPass,
 }
@@ -30,9 +29,33 @@ enum Segment {
     Main
 }
 
+stage_config!(MetalheartConfig {
+    tmul: f32 = 800.0,
+    speed: f32 = 1.5,
+    plonkfr: f32 = 0.1,
+    plonkweight: f32 = 0.2,
+    lrange: f32 = 40.0,
+    weightmin: f32 = 0.0,
+    weightmax: f32 = 0.8,
+    vhs1: f32 = 1.0,
+    vhs2: f32 = 0.4,
+});
+
 impl Metalheart {
-    pub fn new(app: &App) -> Self {
+    pub fn info() -> StageInfo {
+        StageInfo::default()
+            .cfg::<MetalheartConfig>("metalheart.cfg")
+            .scene("metalheart.glb")
+            .nodes(&["Heart"])
+            .lights(&["Point"])
+            .notes(&[29, 60, 61])
+            .mods(&[1, 2, 3, 4])
+            .go(Id::Note(10), "cyber_grind")
+    }
+
+    pub fn new(app: &App, targets: &Targets, presets: &FxPresets) -> Self {
         let device = &app.device;
+        let res = Resolution::of("metalheart");
 
         let decay = DecayEnv::default()
             .with("plonk", 0.0)
@@ -40,34 +63,31 @@ impl Metalheart {
             ;
         let count = CounterEnv::default();
 
-        let cfg = lib::resource::read_cfg("metalheart.cfg");
+        let cfg = StageCfg::<MetalheartConfig>::load("metalheart.cfg");
 
         let scene = Phong::new(app, "metalheart.glb", |_node| true, |_mat| true);
-        let tri = IsoTriPass::new(device, IsoTri {
+        let tri = IsoTriPass::new(device, res.size(), IsoTri {
             color: [0.369, 0.756, 0.871],
-            aspect: 16.0 / 9.0,
             t: 0.0,
             r: -3.0,
-            weight: cfg.f32("plonkweight"),
+            weight: cfg.plonkweight,
             thickness: 0.5,
+            ..Default::default()
         });
 
-        let composite = FilterPass::new_composite_sized::<()>(
-            device,
-            "metalheart_composite",
-            2,
-            Some("composite.frag.spv"),
-            None,
-            (640, 360),
-        );
-        let fx = FxPass::new(device, (640, 360));
-        let blit = BlitPass::new("metalheart").build(device);
+        let graph = Graph::new("metalheart", res.size())
+            .source("tri")
+            .source("scene")
+            .filter("composite", "composite.frag.spv", &["tri", "scene"], None)
+            .output("composite")
+            .build(device, targets);
+        let fx = FxPass::new(device, res.size(), targets, presets);
+        let blit = BlitPass::new("metalheart").fit(res).pooled(targets, res.size()).build(device);
 
         Self {
             segment: Segment::Init,
 
-            t: 0.0,
-            t_mul: 0.3,
+            clock: Clock::new("rms").speed(cfg.tmul).mul(0.3),
 
             decay,
             count,
@@ -76,7 +96,7 @@ impl Metalheart {
             scene,
             tri,
 
-            composite,
+            graph,
             fx,
             blit
         }
@@ -90,25 +110,29 @@ impl Stage for Metalheart {
     }
 
     async fn update(&mut self, p: &mut Player, dt: f32) {
-        self.t += self.cfg.f32("tmul") * p.rms() * self.t_mul * dt;
+        if self.cfg.reload() {
+            self.tri.weight = self.cfg.plonkweight;
+        }
+        self.clock.set_speed(self.cfg.tmul);
+        self.clock.update(p, dt);
 
         self.scene.node("Heart").transform.rotate = Euler {
             x: Rad(0.0),
-            y: Rad(self.t * self.cfg.f32("speed")),
+            y: Rad(self.clock.t() * self.cfg.speed),
             z: Rad(0.0),
         }.into();
 
         self.decay.update(dt);
-        self.fx.update(p.t(), self.t);
-        self.tri.update(self.t);
+        self.fx.update(p.t(), self.clock.t());
+        self.tri.update(self.clock.t());
 
         if let Segment::Init = self.segment {
-            *self.fx.alpha = self.decay.v("plonk") * self.cfg.f32("plonkfr");
+            *self.fx.alpha = self.decay.v("plonk") * self.cfg.plonkfr;
         }
 
         if let Segment::Main = self.segment {
-            let min = self.cfg.f32("weightmin");
-            let max = self.cfg.f32("weightmax");
+            let min = self.cfg.weightmin;
+            let max = self.cfg.weightmax;
             self.tri.thickness = min + self.decay.v("weight") * (max - min);
         }
     }
@@ -122,15 +146,15 @@ impl Stage for Metalheart {
             Event::Beat { id: 61, t } => self.decay.set_t("weight", t * 2.0),
             Event::Beat { id: 60, t } => self.decay.set_t("plonk", t * 2.0),
 
-            Event::Mod { id: 1, fr } => self.fx.vhs = cfg.f32("vhs1") * fr,
+            Event::Mod { id: 1, fr } => self.fx.vhs = cfg.vhs1 * fr,
             Event::Mod { id: 2, fr } => { self.fx.glitch = fr; self.fx.edge = fr; },
             Event::Mod { id: 3, fr } => self.fx.flash = fr,
-            Event::Mod { id: 4, fr } => self.fx.vhs = cfg.f32("vhs2") * fr,
+            Event::Mod { id: 4, fr } => self.fx.vhs = cfg.vhs2 * fr,
 
             Event::Trigger { id: 29 } => {
                 self.segment = Segment::Main;
                 *self.fx.alpha = 1.0;
-                self.scene.light("Point").range = self.cfg.f32("lrange");
+                self.scene.light("Point").range = self.cfg.lrange;
             },
 
             Event::Trigger { id: 10 } => p.go("cyber_grind").await,
@@ -149,15 +173,22 @@ impl Stage for Metalheart {
         }
     }
 
+    fn reload(&mut self, device: &wgpu::Device, changed: &[String]) {
+        self.graph.reload(device, changed);
+        self.fx.reload(device, changed);
+        self.blit.reload(device, changed);
+    }
+
     fn view(&mut self, frame: &mut Frame, view: &wgpu::RawTextureView) {
         let decay = &self.decay;
         let count = &self.count;
         let cfg = &self.cfg;
 
-        self.tri.encode(frame, self.composite.view(0));
-        self.scene.encode(frame, self.composite.view(1));
-
-        self.composite.encode(frame, self.fx.view());
+        self.graph.encode(frame, self.fx.view(), |frame, node, target| match node {
+            "tri" => self.tri.encode(frame, target),
+            "scene" => self.scene.encode(frame, target),
+            _ => {}
+        });
         self.fx.upload(frame);
         self.fx.encode(frame, self.blit.view());
         self.blit.encode(frame, view);
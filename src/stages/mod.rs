@@ -1,3 +1,5 @@
+use crate::demo::StageInfo;
+
 // mod test; pub use test::Test;
 // mod test1; pub use test1::Test1;
 // mod test2; pub use test2::Test2;
@@ -17,6 +19,26 @@ mod resolve; pub use resolve::Resolve;
 mod funky_beat; pub use funky_beat::FunkyBeat;
 mod thanks; pub use thanks::Thanks;
 
+/// What each stage above declares about itself, by stage name. Checked by
+/// `validate` and replayed against a demo by `coverage`.
+pub fn infos() -> Vec<(&'static str, StageInfo)> {
+    vec![
+        ("lobby", Lobby::info()),
+        ("metalheart", Metalheart::info()),
+        ("cyber_grind", CyberGrind::info()),
+        ("halo", Halo::info()),
+        ("aqua", Aqua::info()),
+        ("reality", Reality::info()),
+        ("pod", Pod::info()),
+        ("chaostheory", Chaos::info()),
+        ("dragonage", Dragon::info()),
+        ("yume", Yume::info()),
+        ("resolve", Resolve::info()),
+        ("funky_beat", FunkyBeat::info()),
+        ("thanks", Thanks::info()),
+    ]
+}
+
 /* TBD Ideas */
 
 /* Ideas */
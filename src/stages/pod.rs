@@ -4,18 +4,17 @@ use lib::gfx::pass::*;
 use lib::gfx::scene::Node;
 use lib::prelude::*;
 
-use crate::demo::{Event, Player, Stage};
+use crate::demo::{Event, Id, Player, Stage, StageInfo};
 use crate::pipeline::*;
 
 pub struct Pod {
     segment: Segment,
 
-    t: f32,
-    t_mul: f32,
+    clock: Clock,
 
     decay: DecayEnv,
     count: CounterEnv,
-    cfg: Config,
+    cfg: StageCfg<PodConfig>,
 
     scene: Phong,
     animator: Animator,
@@ -23,7 +22,7 @@ pub struct Pod {
     white: SynthPass,
     starfield: StarfieldPass,
 
-    composite: FilterPass,
+    graph: Graph,
     fx: FxPass,
     blit: BlitPass,
 }
@@ -33,9 +32,31 @@ enum Segment {
     Fast,
 }
 
+stage_config!(PodConfig {
+    tmul: f32 = 100.0,
+    tmul2: f32 = 3.0,
+    shake: f32 = 1.0,
+    flash: f32 = 0.25,
+    noise: f32 = 0.05,
+    star_y: f32 = 0.5,
+    warp: f32 = 0.92,
+    speed: f32 = 1.0,
+    edge: f32 = 0.7,
+});
+
 impl Pod {
-    pub fn new(app: &App) -> Self {
+    pub fn info() -> StageInfo {
+        StageInfo::default()
+            .cfg::<PodConfig>("pod.cfg")
+            .scene("oceanfly.glb")
+            .notes(&[13, 29, 60, 61, 62, 63, 64])
+            .mods(&[0, 1, 2])
+            .go(Id::Note(10), "chaostheory")
+    }
+
+    pub fn new(app: &App, targets: &Targets, presets: &FxPresets) -> Self {
         let device = &app.device;
+        let res = Resolution::of("pod");
 
         let decay = DecayEnv::default()
             .with("bigkick", 0.0)
@@ -44,40 +65,37 @@ impl Pod {
             ;
         let count = CounterEnv::default();
 
-        let cfg = lib::resource::read_cfg("pod.cfg");
+        let cfg = StageCfg::<PodConfig>::load("pod.cfg");
 
         let scene = Phong::new(app, "oceanfly.glb", |_node| true, |_mat| true);
         let animator = Animator::new(&scene.scene);
 
-        let white = SynthPass::new::<()>(device, "white", "white.frag.spv", None);
+        let white = SynthPass::new::<()>(device, "white", shader("white.frag.spv"), None);
         let starfield = StarfieldPass::new(device, Starfield {
             color: [0.8, 0.3, 0.0],
             x: 0.5,
-            y: cfg.f32("star_y"),
-            w: 640.0,
-            h: 360.0,
+            y: cfg.star_y,
+            w: res.width as f32,
+            h: res.height as f32,
             t: 0.0,
-            speed: cfg.f32("speed"),
-            warp: cfg.f32("warp"),
+            speed: cfg.speed,
+            warp: cfg.warp,
             acid: 0.0,
         });
 
-        let composite = FilterPass::new_composite_sized::<()>(
-            device,
-            "pod_composite",
-            2,
-            Some("composite.frag.spv"),
-            None,
-            (640, 360),
-        );
-        let fx = FxPass::new(device, (640, 360));
-        let blit = BlitPass::new("pod").build(device);
+        let graph = Graph::new("pod", res.size())
+            .source("back")
+            .source("scene")
+            .filter("composite", "composite.frag.spv", &["back", "scene"], None)
+            .output("composite")
+            .build(device, targets);
+        let fx = FxPass::new(device, res.size(), targets, presets);
+        let blit = BlitPass::new("pod").fit(res).pooled(targets, res.size()).build(device);
 
         Self {
             segment: Segment::Init,
 
-            t: 0.0,
-            t_mul: 1.0,
+            clock: Clock::new("rms").speed(cfg.tmul),
 
             decay,
             count,
@@ -89,7 +107,7 @@ impl Pod {
             white,
             starfield,
 
-            composite,
+            graph,
             fx,
             blit
         }
@@ -99,25 +117,31 @@ impl Pod {
 #[async_trait]
 impl Stage for Pod {
     async fn init(&mut self, p: &mut Player) {
-        self.animator.play(self.t, true, "PovFly");
-        self.animator.play(self.t, true, "SunFly");
-        // self.animator.play(self.t, true, "LightFly");
+        self.animator.play(self.clock.t(), true, "PovFly");
+        self.animator.play(self.clock.t(), true, "SunFly");
+        // self.animator.play(self.clock.t(), true, "LightFly");
     }
 
     async fn update(&mut self, p: &mut Player, dt: f32) {
-        self.t += self.cfg.f32("tmul") * p.rms() * self.t_mul * dt;
+        if self.cfg.reload() {
+            self.starfield.y = self.cfg.star_y;
+            self.starfield.speed = self.cfg.speed;
+            self.starfield.warp = self.cfg.warp;
+        }
+        self.clock.set_speed(self.cfg.tmul);
+        self.clock.update(p, dt);
 
-        self.animator.update(self.t, &mut self.scene.scene);
+        self.animator.update(self.clock.t(), &mut self.scene.scene);
         self.decay.update(dt);
-        self.fx.update(p.t(), self.t);
-        self.starfield.update(self.t);
+        self.fx.update(p.t(), self.clock.t());
+        self.starfield.update(self.clock.t());
 
-        self.fx.shake = self.decay.v("bigkick") * self.cfg.f32("shake");
+        self.fx.shake = self.decay.v("bigkick") * self.cfg.shake;
 
         if let Some(fr) = self.decay.vv("noise") {
-            self.fx.flash = fr * self.cfg.f32("noise");
+            self.fx.flash = fr * self.cfg.noise;
         } else {
-            self.fx.flash = self.decay.v("bigsnare") * self.cfg.f32("flash");
+            self.fx.flash = self.decay.v("bigsnare") * self.cfg.flash;
         }
     }
 
@@ -141,8 +165,8 @@ impl Stage for Pod {
             Event::Mod { id: 2, fr } => *self.fx.alpha = 1.0 - fr,
 
             Event::Trigger { id: 29 } => {
-                self.fx.edge = self.cfg.f32("edge");
-                self.t_mul = self.cfg.f32("tmul2");
+                self.fx.edge = self.cfg.edge;
+                self.clock.set_mul(self.cfg.tmul2);
                 self.segment = Segment::Fast;
             },
 
@@ -165,23 +189,24 @@ impl Stage for Pod {
         }
     }
 
+    fn reload(&mut self, device: &wgpu::Device, changed: &[String]) {
+        self.graph.reload(device, changed);
+        self.fx.reload(device, changed);
+        self.blit.reload(device, changed);
+    }
+
     fn view(&mut self, frame: &mut Frame, view: &wgpu::RawTextureView) {
         let decay = &self.decay;
         let count = &self.count;
         let cfg = &self.cfg;
 
-        match self.segment {
-            Segment::Init => {
-                self.white.encode(frame, self.composite.view(0));
-                self.scene.encode(frame, self.composite.view(1));
-            },
-            Segment::Fast => {
-                self.starfield.encode(frame, self.composite.view(0));
-                self.scene.encode(frame, self.composite.view(1));
-            },
-        }
-
-        self.composite.encode(frame, self.fx.view());
+        let segment = &self.segment;
+        self.graph.encode(frame, self.fx.view(), |frame, node, target| match (node, segment) {
+            ("back", Segment::Init) => self.white.encode(frame, target),
+            ("back", Segment::Fast) => self.starfield.encode(frame, target),
+            ("scene", _) => self.scene.encode(frame, target),
+            _ => {}
+        });
         self.fx.upload(frame);
         self.fx.encode(frame, self.blit.view());
         self.blit.encode(frame, view);
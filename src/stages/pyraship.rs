@@ -46,8 +46,9 @@ enum Segment {
 }
 
 impl CyberGrind {
-    pub fn new(app: &App) -> Self {
+    pub fn new(app: &App, targets: &Targets, presets: &FxPresets) -> Self {
         let device = &app.device;
+        let res = Resolution::of("pyraship");
 
         let decay = DecayEnv::default()
             .with("drop", 10.0)
@@ -70,8 +71,8 @@ impl CyberGrind {
             color: [1.0, 0.0, 0.0],
             x: 0.5,
             y: 0.85,
-            w: 640.0,
-            h: 360.0,
+            w: res.width as f32,
+            h: res.height as f32,
             t: 0.0,
             speed: 1.0,
             warp: cfg.f32("warp"),
@@ -82,12 +83,12 @@ impl CyberGrind {
             device,
             "pyraship_composite",
             2,
-            Some("composite.frag.spv"),
+            Some(shader("composite.frag.spv")),
             None,
-            (640, 360),
+            res.size(),
         );
-        let fx = FxPass::new(device, (640, 360));
-        let blit = BlitPass::new("funky").build(device);
+        let fx = FxPass::new(device, res.size(), targets, presets);
+        let blit = BlitPass::new("funky").fit(res).pooled(targets, res.size()).build(device);
 
         Self {
             segment: Segment::Init,
@@ -281,6 +282,11 @@ impl Stage for CyberGrind {
         }
     }
 
+    fn reload(&mut self, device: &wgpu::Device, changed: &[String]) {
+        self.fx.reload(device, changed);
+        self.blit.reload(device, changed);
+    }
+
     fn view(&mut self, frame: &mut Frame, view: &wgpu::RawTextureView) {
         let decay = &self.decay;
         let count = &self.count;
@@ -109,8 +109,8 @@ impl Test {
 
         // let vs = lib::resource::read_shader(device, "billboard.vert.spv");
         // let fs = lib::resource::read_shader(device, "uv.frag.spv");
-        let vs = lib::resource::read_shader(device, "mesh.vert.spv");
-        let fs = lib::resource::read_shader(device, "mesh.frag.spv");
+        let vs = lib::resource::read_shader(device, shader("mesh.vert.spv"));
+        let fs = lib::resource::read_shader(device, shader("mesh.frag.spv"));
 
         let pipeline = wgpu::util::PipelineBuilder::new("test")
             .render(&vs)
@@ -5,20 +5,19 @@ use lib::gfx::scene::Node;
 use lib::prelude::*;
 use palette::{Hsl, Srgb, FromColor};
 
-use crate::demo::{Event, Player, Stage};
+use crate::demo::{Event, Id, Player, Stage, StageInfo};
 use crate::pipeline::*;
 
 pub struct Reality {
     segment: Segment,
 
-    t: f32,
-    t_mul: f32,
+    clock: Clock,
     rot: f32,
     rot_speed: f32,
 
     decay: DecayEnv,
     count: CounterEnv,
-    cfg: Config,
+    cfg: StageCfg<RealityConfig>,
 
     scene: Phong,
     animator1: Animator,
@@ -26,14 +25,13 @@ pub struct Reality {
     scale: f32,
     flash: f32,
 
-    clear: ClearPass,
     text: TextPass,
     tfx: FxPass,
 
     spiral: SpiralPass,
     spiralamt: f32,
 
-    composite: FilterPass,
+    graph: Graph,
     fx: FxPass,
     blit: BlitPass,
 }
@@ -44,9 +42,34 @@ enum Segment {
     Rainbow,
 }
 
+stage_config!(RealityConfig {
+    rot_speed: f32 = 10.0,
+    tvhs: f32 = 0.9,
+    tbloom: f32 = 0.2,
+    get_sz: f32 = 300.0,
+    get: Vector2 = v2(330.0, 200.0),
+    down_sz: f32 = 300.0,
+    down: Vector2 = v2(430.0, 560.0),
+    edge1: f32 = 0.4,
+    edge2: f32 = 1.0,
+});
+
 impl Reality {
-    pub fn new(app: &App) -> Self {
+    pub fn info() -> StageInfo {
+        StageInfo::default()
+            .cfg::<RealityConfig>("reality.cfg")
+            .scene("uvbounce.glb")
+            .nodes(&["Sphere", "Cube"])
+            .materials(&["SphereChecker", "SphereWhite"])
+            .lights(&["PointTop", "PointL", "PointR"])
+            .notes(&[20, 21, 23, 24, 25, 26, 27, 28, 61, 62, 63, 64, 65])
+            .mods(&[0, 1, 2])
+            .go(Id::Note(10), "pod")
+    }
+
+    pub fn new(app: &App, targets: &Targets, presets: &FxPresets) -> Self {
         let device = &app.device;
+        let res = Resolution::of("reality");
 
         let decay = DecayEnv::default()
             .with("hat", 0.0)
@@ -60,47 +83,44 @@ impl Reality {
             .with("getdown", 3)
             ;
 
-        let cfg = lib::resource::read_cfg("reality.cfg");
+        let cfg = StageCfg::<RealityConfig>::load("reality.cfg");
 
         let scene = Phong::new(app, "uvbounce.glb", |_node| true, |_mat| true);
         let animator1 = Animator::new(&scene.scene);
         let animator2 = Animator::new(&scene.scene);
 
-        let clear = ClearPass::new(device, wgpu::Color::BLACK);
         let text = TextPassBuilder::default()
             .with("default", "da_mad_rave_italic.otf")
             .build(device);
-        let mut tfx = FxPass::new(device, (640, 360));
-        tfx.vhs = cfg.f32("tvhs");
-        tfx.bloom = cfg.f32("tbloom");
+        let mut tfx = FxPass::new(device, res.size(), targets, presets);
+        tfx.vhs = cfg.tvhs;
+        tfx.bloom = cfg.tbloom;
 
-        let spiral = SpiralPass::new(device, Spiral {
+        let spiral = SpiralPass::new(device, res.size(), Spiral {
             color: [1.0, 1.0, 1.0],
-            aspect: 360.0 / 640.0,
             t: 0.0,
             swirl: 0.0,
             speed: 0.5,
             cutoff: 0.7,
             amount: 0.0,
             spokes: 3,
+            ..Default::default()
         });
 
-        let composite = FilterPass::new_composite_sized::<()>(
-            device,
-            "reality",
-            3,
-            Some("composite_add.frag.spv"),
-            None,
-            (640, 360),
-        );
-        let fx = FxPass::new(device, (640, 360));
-        let blit = BlitPass::new("reality").build(device);
+        let graph = Graph::new("reality", res.size())
+            .source("spiral")
+            .source("scene")
+            .source("text")
+            .filter("composite", "composite_add.frag.spv", &["spiral", "scene", "text"], None)
+            .output("composite")
+            .build(device, targets);
+        let fx = FxPass::new(device, res.size(), targets, presets);
+        let blit = BlitPass::new("reality").fit(res).pooled(targets, res.size()).build(device);
 
         Self {
             segment: Segment::Init,
 
-            t: 0.0,
-            t_mul: 1.0,
+            clock: Clock::new("rms").speed(50.0),
             rot: 0.0,
             rot_speed: 0.0,
 
@@ -114,14 +134,13 @@ impl Reality {
             scale: 0.0,
             flash: 0.0,
 
-            clear,
             text,
             tfx,
 
             spiral,
             spiralamt: 0.0,
 
-            composite,
+            graph,
             fx,
             blit
         }
@@ -134,34 +153,38 @@ impl Stage for Reality {
     }
 
     async fn update(&mut self, p: &mut Player, dt: f32) {
-        self.t += 50.0 * p.rms() * self.t_mul * dt;
+        if self.cfg.reload() {
+            self.tfx.vhs = self.cfg.tvhs;
+            self.tfx.bloom = self.cfg.tbloom;
+        }
+        self.clock.update(p, dt);
 
         self.animator1.update(p.t(), &mut self.scene.scene);
-        self.animator2.update(self.t, &mut self.scene.scene);
+        self.animator2.update(self.clock.t(), &mut self.scene.scene);
         self.decay.update(dt);
-        self.tfx.update(p.t(), self.t);
-        self.fx.update(p.t(), self.t);
+        self.tfx.update(p.t(), self.clock.t());
+        self.fx.update(p.t(), self.clock.t());
 
         self.scene.light("PointTop").range = 2.0 + self.decay.v("hat");
 
         let s = 0.3 + self.decay.v("synth") * 0.1 * self.scale;
         self.scene.node("Sphere").transform.scale = v3(s, s, s);
 
-        self.rot += self.rot_speed * 50.0 * p.rms() * self.t_mul * dt;
+        self.rot += self.rot_speed * self.clock.dt();
         self.scene.node("Sphere").transform.rotate = Euler {
             x: Rad(0.0),
             y: Rad(self.rot),
             z: Rad(0.0)
         }.into();
 
-        self.spiral.t = self.t;
+        self.spiral.t = self.clock.t();
         self.spiral.amount = self.decay.v("spiralbeat") + self.spiralamt;
 
         self.fx.flash = self.decay.v("bam") + self.flash;
         self.fx.shake = self.decay.v("bamshake");
 
         if let Segment::Rainbow = self.segment {
-            let hsl = Hsl::new((self.t / 4.0) * 360.0 % 360.0, 1.0, 0.5);
+            let hsl = Hsl::new((self.clock.t() / 4.0) * 360.0 % 360.0, 1.0, 0.5);
             let rgb = Srgb::from_color(hsl);
             self.scene.material("SphereChecker").color = v4(rgb.red, rgb.green, rgb.blue, 1.0);
             self.scene.material("SphereWhite").color = v4(rgb.red, rgb.green, rgb.blue, 1.0);
@@ -193,12 +216,12 @@ impl Stage for Reality {
             // Event::Mod { id: 3, fr } => self.spiral.spokes = (8.0 * fr).floor() as u32,
 
             Event::Trigger { id: 28 } => self.animator1.play(p.t(), false, "SphereFall"),
-            Event::Trigger { id: 27 } => self.rot_speed = cfg.f32("rot_speed"),
+            Event::Trigger { id: 27 } => self.rot_speed = cfg.rot_speed,
             Event::Trigger { id: 26 } => {
                 self.scene.light("PointL").range = 2.0;
                 self.scene.light("PointR").range = 2.0;
                 log::info!("Sphere tr {:?}", self.scene.node("Sphere").transform.translate);
-                self.animator2.play(self.t, true, "Figure8");
+                self.animator2.play(self.clock.t(), true, "Figure8");
             },
             Event::Trigger { id: 25 } => { self.count.inc("getdown"); },
             Event::Trigger { id: 24 } => {
@@ -206,7 +229,7 @@ impl Stage for Reality {
                 let j = self.scene.mat_names["SphereWhite"];
                 self.scene.mats.swap(i, j);
                 self.segment = Segment::Rainbow;
-                self.fx.edge = cfg.f32("edge1");
+                self.fx.edge = cfg.edge1;
                 self.spiral.color = [1.0, 1.0, 1.0];
                 self.spiral.spokes = 6;
             },
@@ -218,7 +241,7 @@ impl Stage for Reality {
             },
 
             Event::Trigger { id: 21 } => {
-                self.fx.edge = cfg.f32("edge1");
+                self.fx.edge = cfg.edge1;
                 self.spiral.color = [0.8, 0.0, 0.0];
                 self.spiralamt = 1.0;
                 self.spiral.swirl = 1.0;
@@ -226,7 +249,7 @@ impl Stage for Reality {
                 self.spiral.speed = 3.0;
             },
             Event::Trigger { id: 20 } => {
-                self.fx.edge = cfg.f32("edge2");
+                self.fx.edge = cfg.edge2;
                 self.spiral.color = [0.8, 0.0, 0.8];
                 self.spiral.spokes = 8;
                 self.spiral.speed = 10.0;
@@ -248,36 +271,37 @@ impl Stage for Reality {
         }
     }
 
+    fn reload(&mut self, device: &wgpu::Device, changed: &[String]) {
+        self.tfx.reload(device, changed);
+        self.graph.reload(device, changed);
+        self.fx.reload(device, changed);
+        self.blit.reload(device, changed);
+    }
+
     fn view(&mut self, frame: &mut Frame, view: &wgpu::RawTextureView) {
         let decay = &self.decay;
         let count = &self.count;
         let cfg = &self.cfg;
 
-        match self.segment {
-            Segment::Init => {
-                self.scene.encode(frame, self.composite.view(0));
-            },
-            Segment::Spiral | Segment::Rainbow => {
-                if let Some(n) = self.count.vv("getdown") {
+        let segment = &self.segment;
+        self.graph.encode(frame, self.fx.view(), |frame, node, target| match (node, segment) {
+            ("scene", _) => self.scene.encode(frame, target),
+            ("spiral", Segment::Spiral | Segment::Rainbow) => self.spiral.encode(frame, target),
+            ("text", Segment::Spiral | Segment::Rainbow) => {
+                if let Some(n) = count.vv("getdown") {
                     let col = v4(1.0, 1.0, 1.0, 1.0);
                     place_text(&mut self.text, n, &[
-                        ("get", cfg.f32("get_sz"), cfg.v2("get"), col),
-                        ("down",  cfg.f32("down_sz"), cfg.v2("down"), col),
+                        ("get", cfg.get_sz, cfg.get, col),
+                        ("down",  cfg.down_sz, cfg.down, col),
                     ]);
 
                     self.text.encode(frame, self.tfx.view());
                     self.tfx.upload(frame);
-                    self.tfx.encode(frame, self.composite.view(2));
-                } else {
-                    self.clear.encode(frame, self.composite.view(2));
+                    self.tfx.encode(frame, target);
                 }
-
-                self.spiral.encode(frame, self.composite.view(0));
-                self.scene.encode(frame, self.composite.view(1));
             },
-        }
-
-        self.composite.encode(frame, self.fx.view());
+            _ => {}
+        });
         self.fx.upload(frame);
         self.fx.encode(frame, self.blit.view());
         self.blit.encode(frame, view);
@@ -31,8 +31,9 @@ enum Segment {
 }
 
 impl Redzone {
-    pub fn new(app: &App) -> Self {
+    pub fn new(app: &App, targets: &Targets, presets: &FxPresets) -> Self {
         let device = &app.device;
+        let res = Resolution::of("redzone");
 
         let decay = DecayEnv::default();
         let count = CounterEnv::default();
@@ -46,12 +47,12 @@ impl Redzone {
             device,
             "template_composite",
             2,
-            Some("composite_add.frag.spv"),
+            Some(shader("composite_add.frag.spv")),
             None,
-            (640, 360),
+            res.size(),
         );
-        let fx = FxPass::new(device, (640, 360));
-        let blit = BlitPass::new("template").build(device);
+        let fx = FxPass::new(device, res.size(), targets, presets);
+        let blit = BlitPass::new("template").fit(res).pooled(targets, res.size()).build(device);
 
         Self {
             segment: Segment::Init,
@@ -107,6 +108,11 @@ impl Stage for Redzone {
         }
     }
 
+    fn reload(&mut self, device: &wgpu::Device, changed: &[String]) {
+        self.fx.reload(device, changed);
+        self.blit.reload(device, changed);
+    }
+
     fn view(&mut self, frame: &mut Frame, view: &wgpu::RawTextureView) {
         let decay = &self.decay;
         let count = &self.count;
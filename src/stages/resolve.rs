@@ -4,18 +4,18 @@ use lib::gfx::pass::*;
 use lib::gfx::scene::Node;
 use lib::prelude::*;
 
-use crate::demo::{Event, Player, Stage};
+use crate::demo::{Event, Id, Player, Stage, StageInfo};
 use crate::pipeline::*;
 
 pub struct Resolve {
     segment: Segment,
 
-    t: f32,
-    t_mul: f32,
+    clock: Clock,
 
     decay: DecayEnv,
     count: CounterEnv,
-    cfg: Config,
+    cfg: StageCfg<ResolveConfig>,
+    routes: Routes,
 
     wormhole: FlyTorusPass,
 
@@ -28,9 +28,27 @@ enum Segment {
 
 }
 
+stage_config!(ResolveConfig {
+    tmul: f32 = 100.0,
+    shake: f32 = 1.0,
+    flash: f32 = 0.25,
+    edge: f32 = 0.6,
+    morph: f32 = 0.2,
+});
+
 impl Resolve {
-    pub fn new(app: &App) -> Self {
+    pub fn info() -> StageInfo {
+        StageInfo::default()
+            .cfg::<ResolveConfig>("resolve.cfg")
+            .routes("resolve.cfg")
+            .notes(&[21, 22, 23, 24, 25, 26])
+            .mods(&[0])
+            .go(Id::Note(10), "funky_beat")
+    }
+
+    pub fn new(app: &App, targets: &Targets, presets: &FxPresets) -> Self {
         let device = &app.device;
+        let res = Resolution::of("resolve");
 
         let decay = DecayEnv::default()
             .with("kick", 0.0)
@@ -40,29 +58,30 @@ impl Resolve {
             ;
         let count = CounterEnv::default();
 
-        let cfg = lib::resource::read_cfg("resolve.cfg");
+        let cfg = StageCfg::<ResolveConfig>::load("resolve.cfg");
+        let routes = Routes::load("resolve.cfg", &decay, &count);
 
         let wormhole = FlyTorusPass::new(device, FlyTorus {
                 color: [1.0, 0.1, 0.3],
                 t: 0.0,
                 speed: 0.3,
-                mx: 16.0 / 9.0,
+                mx: res.aspect(),
                 my: 1.0,
                 warp: 0.65,
         });
 
-        let fx = FxPass::new(device, (640, 360));
-        let blit = BlitPass::new("resolve").build(device);
+        let fx = FxPass::new(device, res.size(), targets, presets);
+        let blit = BlitPass::new("resolve").fit(res).pooled(targets, res.size()).build(device);
 
         Self {
             segment: Segment::Init,
 
-            t: 0.0,
-            t_mul: 0.5,
+            clock: Clock::new("rms").speed(cfg.tmul).mul(0.5),
 
             decay,
             count,
             cfg,
+            routes,
 
             wormhole,
 
@@ -75,21 +94,23 @@ impl Resolve {
 #[async_trait]
 impl Stage for Resolve {
     async fn init(&mut self, p: &mut Player) {
-        // self.animator.play(self.t, true, "");
+        // self.animator.play(self.clock.t(), true, "");
     }
 
     async fn update(&mut self, p: &mut Player, dt: f32) {
-        self.t += self.cfg.f32("tmul") * p.rms() * self.t_mul * dt;
+        self.cfg.reload();
+        self.clock.set_speed(self.cfg.tmul);
+        self.clock.update(p, dt);
 
         self.decay.update(dt);
-        self.fx.update(p.t(), self.t);
-        self.wormhole.update(self.t);
+        self.fx.update(p.t(), self.clock.t());
+        self.wormhole.update(self.clock.t());
 
         self.wormhole.warp = 0.65 + 0.05 * self.decay.v("kick");
 
-        self.fx.edge = self.decay.v("snare") * self.cfg.f32("edge");
-        self.fx.shake = self.decay.v("snare") * self.cfg.f32("shake");
-        self.fx.flash = self.decay.v("hat") * self.cfg.f32("flash");
+        self.fx.edge = self.decay.v("snare") * self.cfg.edge;
+        self.fx.shake = self.decay.v("snare") * self.cfg.shake;
+        self.fx.flash = self.decay.v("hat") * self.cfg.flash;
     }
 
     async fn event(&mut self, p: &mut Player, ev: Event) {
@@ -97,50 +118,42 @@ impl Stage for Resolve {
         let count = &mut self.count;
 
         match ev {
-            Event::Beat { id: 61, t } => self.decay.set_t("kick", t * 2.0),
-            Event::Beat { id: 62, t } => self.decay.set_t("hat", t * 2.0),
-            Event::Beat { id: 63, t } => self.decay.set_t("snare", t * 2.0),
-            Event::Beat { id: 64, t } => self.decay.set_t("crash", t * 2.0),
-
             Event::Mod { id: 0, fr } => *self.fx.alpha = 1.0 - fr,
 
-            Event::Trigger { id: 22 } => {
-                self.fx.glitch = 0.0;
-                self.fx.vhs = 0.0;
-            },
-            Event::Trigger { id: 23 } => {
-                self.fx.glitch = 0.2;
-                self.fx.vhs = 0.4;
-            },
-            Event::Trigger { id: 24 } => {
-                self.fx.glitch = 0.1;
-                self.fx.vhs = 0.3;
-            },
-            Event::Trigger { id: 25 } => {
-                self.fx.glitch = 0.6;
-                self.fx.vhs = 0.6;
-            },
-            Event::Trigger { id: 26 } => {
-                self.fx.glitch = 0.9;
-                self.fx.vhs = 0.9;
-            },
-
-            Event::Trigger { id: 21 } => self.t_mul = 2.0,
+            Event::Trigger { id: 22 } => self.fx.recall("clean", self.cfg.morph),
+            Event::Trigger { id: 23 } => self.fx.recall("glitch_mid", self.cfg.morph),
+            Event::Trigger { id: 24 } => self.fx.recall("glitch_low", self.cfg.morph),
+            Event::Trigger { id: 25 } => self.fx.recall("glitch_high", self.cfg.morph),
+            Event::Trigger { id: 26 } => self.fx.recall("glitch_max", self.cfg.morph),
+
+            Event::Trigger { id: 21 } => self.clock.set_mul(2.0),
             Event::Trigger { id: 10 } => p.go("funky_beat").await,
             _ => {}
         }
     }
 
+    fn routes(&mut self) -> Option<(&mut Routes, &mut DecayEnv, &mut CounterEnv)> {
+        Some((&mut self.routes, &mut self.decay, &mut self.count))
+    }
+
     async fn key(&mut self, p: &mut Player, state: KeyState, key: Key) {
         if state != KeyState::Pressed {
             return;
         }
 
         match key {
+            Key::Key1 => self.fx.recall("clean", self.cfg.morph),
+            Key::Key2 => self.fx.recall("glitch_low", self.cfg.morph),
+            Key::Key3 => self.fx.recall("glitch_high", self.cfg.morph),
             _ => {}
         }
     }
 
+    fn reload(&mut self, device: &wgpu::Device, changed: &[String]) {
+        self.fx.reload(device, changed);
+        self.blit.reload(device, changed);
+    }
+
     fn view(&mut self, frame: &mut Frame, view: &wgpu::RawTextureView) {
         let decay = &self.decay;
         let count = &self.count;
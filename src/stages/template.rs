@@ -10,12 +10,11 @@ use crate::pipeline::*;
 pub struct Lobby {
     segment: Segment,
 
-    t: f32,
-    t_mul: f32,
+    clock: Clock,
 
     decay: DecayEnv,
     count: CounterEnv,
-    cfg: Config,
+    cfg: StageCfg<TemplateConfig>,
 
     scene: Phong,
     animator: Animator,
@@ -30,14 +29,19 @@ enum Segment {
 
 }
 
+stage_config!(TemplateConfig {
+    tmul: f32 = 800.0,
+});
+
 impl Lobby {
-    pub fn new(app: &App) -> Self {
+    pub fn new(app: &App, targets: &Targets, presets: &FxPresets) -> Self {
         let device = &app.device;
+        let res = Resolution::of("template");
 
         let decay = DecayEnv::default();
         let count = CounterEnv::default();
 
-        let cfg = lib::resource::read_cfg("template.cfg");
+        let cfg = StageCfg::<TemplateConfig>::load("template.cfg");
 
         let scene = Phong::new(app, "test.glb", |_node| true, |_mat| true);
         let animator = Animator::new(&scene.scene);
@@ -46,18 +50,17 @@ impl Lobby {
             device,
             "template_composite",
             2,
-            Some("composite_add.frag.spv"),
+            Some(shader("composite_add.frag.spv")),
             None,
-            (640, 360),
+            res.size(),
         );
-        let fx = FxPass::new(device, (640, 360));
-        let blit = BlitPass::new("template").build(device);
+        let fx = FxPass::new(device, res.size(), targets, presets);
+        let blit = BlitPass::new("template").fit(res).pooled(targets, res.size()).build(device);
 
         Self {
             segment: Segment::Init,
 
-            t: 0.0,
-            t_mul: 1.0,
+            clock: Clock::new("rms").speed(cfg.tmul),
 
             decay,
             count,
@@ -76,15 +79,17 @@ impl Lobby {
 #[async_trait]
 impl Stage for Lobby {
     async fn init(&mut self, p: &mut Player) {
-        // self.animator.play(self.t, true, "");
+        // self.animator.play(self.clock.t(), true, "");
     }
 
     async fn update(&mut self, p: &mut Player, dt: f32) {
-        self.t += 50.0 * p.rms() * self.t_mul * dt;
+        self.cfg.reload();
+        self.clock.set_speed(self.cfg.tmul);
+        self.clock.update(p, dt);
 
-        self.animator.update(self.t, &mut self.scene.scene);
+        self.animator.update(self.clock.t(), &mut self.scene.scene);
         self.decay.update(dt);
-        self.fx.update(p.t(), self.t);
+        self.fx.update(p.t(), self.clock.t());
     }
 
     async fn event(&mut self, p: &mut Player, ev: Event) {
@@ -106,6 +111,11 @@ impl Stage for Lobby {
         }
     }
 
+    fn reload(&mut self, device: &wgpu::Device, changed: &[String]) {
+        self.fx.reload(device, changed);
+        self.blit.reload(device, changed);
+    }
+
     fn view(&mut self, frame: &mut Frame, view: &wgpu::RawTextureView) {
         let decay = &self.decay;
         let count = &self.count;
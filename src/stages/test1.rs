@@ -2,7 +2,7 @@ use async_trait::async_trait;
 use lib::prelude::*;
 
 use crate::demo::{Event, Player, Stage};
-use crate::pipeline::{IsoTri, IsoTriPass};
+use crate::pipeline::{IsoTri, IsoTriPass, Resolution};
 
 pub struct Test1 {
     strobe: Decay,
@@ -15,6 +15,7 @@ pub struct Test1 {
 
 impl Test1 {
     pub fn new(device: &wgpu::Device) -> Self {
+        let res = Resolution::of("test1");
         Self {
             strobe: Decay::new(25.0),
             beat: Decay::new(200.0),
@@ -23,13 +24,14 @@ impl Test1 {
 
             tri: IsoTriPass::new(
                 device,
+                res.size(),
                 IsoTri {
                     color: [1.0, 0.5, 0.0],
-                    aspect: 9.0 / 16.0,
                     t: 0.0,
                     r: 0.0,
                     weight: 0.5,
                     thickness: 0.5,
+                    ..Default::default()
                 },
             ),
         }
@@ -1,7 +1,7 @@
 use lib::prelude::*;
 use async_trait::async_trait;
 
-use crate::pipeline::{IsoTriPass, IsoTri};
+use crate::pipeline::{IsoTriPass, IsoTri, Resolution};
 use crate::demo::{Event, Player, Stage};
 
 pub struct Test2 {
@@ -15,19 +15,20 @@ pub struct Test2 {
 
 impl Test2 {
     pub fn new(device: &wgpu::Device) -> Self {
+        let res = Resolution::of("test2");
         Self {
             strobe: Decay::new(25.0),
             beat: Decay::new(200.0),
             t: 0.0,
             t_mul: 1.0,
 
-            tri: IsoTriPass::new(device, IsoTri {
+            tri: IsoTriPass::new(device, res.size(), IsoTri {
                 color: [0.6, 0.0, 0.8],
-                aspect: 9.0 / 16.0,
                 t: 0.0,
                 r: 0.0,
                 weight: 0.5,
                 thickness: 0.5,
+                ..Default::default()
             }),
         }
     }
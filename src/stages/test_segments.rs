@@ -1,7 +1,7 @@
 use lib::prelude::*;
 use async_trait::async_trait;
 
-use crate::pipeline::{IsoTriPass, IsoTri};
+use crate::pipeline::{IsoTriPass, IsoTri, Resolution};
 use crate::demo::{Event, Player, Stage};
 
 pub struct TestSegments {
@@ -23,6 +23,7 @@ enum Segment {
 
 impl TestSegments {
     pub fn new(device: &wgpu::Device) -> Self {
+        let res = Resolution::of("test_segments");
         Self {
             hat: Decay::new(25.0),
             kick: Decay::new(200.0),
@@ -31,21 +32,21 @@ impl TestSegments {
 
             segment: Segment::Tri1,
 
-            tri1: IsoTriPass::new(device, IsoTri {
+            tri1: IsoTriPass::new(device, res.size(), IsoTri {
                 color: [1.0, 0.5, 0.0],
-                aspect: 9.0 / 16.0,
                 t: 0.0,
                 r: 0.0,
                 weight: 0.5,
                 thickness: 0.5,
+                ..Default::default()
             }),
-            tri2: IsoTriPass::new(device, IsoTri {
+            tri2: IsoTriPass::new(device, res.size(), IsoTri {
                 color: [0.6, 0.0, 0.8],
-                aspect: 9.0 / 16.0,
                 t: 0.0,
                 r: 0.0,
                 weight: 0.5,
                 thickness: 0.5,
+                ..Default::default()
             }),
         }
     }
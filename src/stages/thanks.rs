@@ -4,7 +4,7 @@ use lib::gfx::pass::*;
 use lib::gfx::scene::Node;
 use lib::prelude::*;
 
-use crate::demo::{Event, Player, Stage};
+use crate::demo::{Event, Player, Stage, StageInfo};
 use crate::pipeline::*;
 
 pub struct Thanks {
@@ -12,6 +12,10 @@ pub struct Thanks {
 }
 
 impl Thanks {
+    pub fn info() -> StageInfo {
+        StageInfo::default()
+    }
+
     pub fn new(app: &App) -> Self {
         let device = &app.device;
 
@@ -5,18 +5,17 @@ use lib::gfx::scene::Node;
 use lib::prelude::*;
 use lib::time::Spring;
 
-use crate::demo::{Event, Player, Stage};
+use crate::demo::{Event, Id, Player, Stage, StageInfo};
 use crate::pipeline::*;
 
 pub struct Yume {
     segment: Segment,
 
-    t: f32,
-    t_mul: f32,
+    clock: Clock,
 
     decay: DecayEnv,
     count: CounterEnv,
-    cfg: Config,
+    cfg: StageCfg<YumeConfig>,
 
     waves: LineWavePass,
     spring: Spring,
@@ -30,9 +29,28 @@ enum Segment {
 
 }
 
+stage_config!(YumeConfig {
+    tmul: f32 = 100.0,
+    shake: f32 = 1.0,
+    flash: f32 = 0.25,
+    edge: f32 = 0.6,
+    n: usize = 40,
+    dz: f32 = 0.25,
+    ddz: f32 = 0.25,
+    scale: f32 = 1.0,
+});
+
 impl Yume {
-    pub fn new(app: &App) -> Self {
+    pub fn info() -> StageInfo {
+        StageInfo::default()
+            .cfg::<YumeConfig>("yume.cfg")
+            .notes(&[61, 66, 69])
+            .go(Id::Note(10), "resolve")
+    }
+
+    pub fn new(app: &App, targets: &Targets, presets: &FxPresets) -> Self {
         let device = &app.device;
+        let res = Resolution::of("yume");
 
         let decay = DecayEnv::default()
             .with("crash", 0.0)
@@ -41,30 +59,29 @@ impl Yume {
             ;
         let count = CounterEnv::default();
 
-        let cfg = lib::resource::read_cfg("yume.cfg");
+        let cfg = StageCfg::<YumeConfig>::load("yume.cfg");
 
         let waves = LineWavePass::new(device, LineWave {
             color: [0.25, 0.78, 1.0],
             t: 0.0,
-            w: 640.0,
-            h: 360.0,
+            w: res.width as f32,
+            h: res.height as f32,
             n1: 0.0,
             n2: 0.48,
             dz: 0.25,
             thickness: 0.25,
             falloff: 0.07,
-            n: cfg.usize("n") as u32,
+            n: cfg.n as u32,
         });
         let spring = Spring::new(1000.0);
 
-        let fx = FxPass::new(device, (640, 360));
-        let blit = BlitPass::new("yume").build(device);
+        let fx = FxPass::new(device, res.size(), targets, presets);
+        let blit = BlitPass::new("yume").fit(res).pooled(targets, res.size()).build(device);
 
         Self {
             segment: Segment::Init,
 
-            t: 0.0,
-            t_mul: 1.0,
+            clock: Clock::new("rms").speed(cfg.tmul),
 
             decay,
             count,
@@ -82,30 +99,34 @@ impl Yume {
 #[async_trait]
 impl Stage for Yume {
     async fn init(&mut self, p: &mut Player) {
-        // self.animator.play(self.t, true, "");
+        // self.animator.play(self.clock.t(), true, "");
     }
 
     async fn update(&mut self, p: &mut Player, dt: f32) {
-        self.t += self.cfg.f32("tmul") * p.rms() * self.t_mul * dt;
+        if self.cfg.reload() {
+            self.waves.n = self.cfg.n as u32;
+        }
+        self.clock.set_speed(self.cfg.tmul);
+        self.clock.update(p, dt);
 
         self.decay.update(dt);
-        self.fx.update(p.t(), self.t);
-        self.waves.update(self.t);
+        self.fx.update(p.t(), self.clock.t());
+        self.waves.update(self.clock.t());
 
-        // self.spring.set(self.cfg.f32("scale") * 100.0 * p.rms());
+        // self.spring.set(self.cfg.scale * 100.0 * p.rms());
         // self.spring.update(dt);
         // self.waves.n1 = self.spring.v();
         self.waves.n1 = 0.4 * self.decay.v("synth");
         self.waves.thickness = 0.25 + 0.75 * self.decay.v("kick");
-        self.waves.dz = self.cfg.f32("dz") + self.cfg.f32("ddz") * self.decay.v("kick");
+        self.waves.dz = self.cfg.dz + self.cfg.ddz * self.decay.v("kick");
 
-        self.fx.edge = self.decay.v("synth") * self.cfg.f32("edge");
-        self.fx.shake = self.decay.v("kick") * self.cfg.f32("shake");
+        self.fx.edge = self.decay.v("synth") * self.cfg.edge;
+        self.fx.shake = self.decay.v("kick") * self.cfg.shake;
 
         if let Some(fr) = self.decay.vv("crash") {
             self.fx.flash = 0.6 * fr;
         } else {
-            self.fx.flash = self.decay.v("kick") * self.cfg.f32("flash");
+            self.fx.flash = self.decay.v("kick") * self.cfg.flash;
         }
     }
 
@@ -134,6 +155,11 @@ impl Stage for Yume {
         }
     }
 
+    fn reload(&mut self, device: &wgpu::Device, changed: &[String]) {
+        self.fx.reload(device, changed);
+        self.blit.reload(device, changed);
+    }
+
     fn view(&mut self, frame: &mut Frame, view: &wgpu::RawTextureView) {
         let decay = &self.decay;
         let count = &self.count;
@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+
+use crate::demo::Player;
+
+/// The clocks `Player` provides for stages to follow, by name.
+///
+/// - `wall`: real time, which keeps running while paused
+/// - `song`: song time
+/// - `rms`: runs at the song's loudness
+/// - `beat`: counts beats at the song's tempo
+pub struct Clocks {
+    /// Time and rate of each clock
+    map: HashMap<&'static str, (f32, f32)>,
+}
+
+impl Default for Clocks {
+    fn default() -> Self {
+        let map = ["wall", "song", "rms", "beat"]
+            .into_iter()
+            .map(|name| (name, (0.0, 0.0)))
+            .collect();
+        Self { map }
+    }
+}
+
+impl Clocks {
+    pub fn update(&mut self, dt: f32, playing: bool, song_t: f32, rms: f32, bpm: f32) {
+        let run = if playing { 1.0 } else { 0.0 };
+
+        self.advance("wall", 1.0, dt);
+        self.map.insert("song", (song_t, run));
+        self.advance("rms", rms * run, dt);
+        self.advance("beat", bpm / 60.0 * run, dt);
+    }
+
+    fn advance(&mut self, name: &'static str, rate: f32, dt: f32) {
+        let (t, _) = self.map.get(name).copied().unwrap_or_default();
+        self.map.insert(name, (t + rate * dt, rate));
+    }
+
+    fn get(&self, name: &str) -> (f32, f32) {
+        *self
+            .map
+            .get(name)
+            .unwrap_or_else(|| panic!("no such clock {}", name))
+    }
+
+    pub fn t(&self, name: &str) -> f32 {
+        self.get(name).0
+    }
+
+    pub fn rate(&self, name: &str) -> f32 {
+        self.get(name).1
+    }
+}
+
+struct Ramp {
+    from: f32,
+    to: f32,
+    t: f32,
+    over: f32,
+}
+
+/// A stage's own time, following one of the player's clocks.
+///
+/// ```ignore
+/// let clock = Clock::new("rms").speed(50.0).smoothing(0.1);
+/// // In `update`
+/// self.clock.update(p, dt);
+/// self.animator.update(self.clock.t(), &mut self.scene.scene);
+/// ```
+pub struct Clock {
+    source: &'static str,
+    speed: f32,
+    /// Seconds for the rate to catch up with the source, 0 to follow it exactly
+    smoothing: f32,
+
+    mul: f32,
+    ramp: Option<Ramp>,
+
+    rate: f32,
+    t: f32,
+    dt: f32,
+}
+
+impl Clock {
+    pub fn new(source: &'static str) -> Self {
+        Self {
+            source,
+            speed: 1.0,
+            smoothing: 0.0,
+
+            mul: 1.0,
+            ramp: None,
+
+            rate: 0.0,
+            t: 0.0,
+            dt: 0.0,
+        }
+    }
+
+    pub fn speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// The initial speed multiplier.
+    pub fn mul(mut self, mul: f32) -> Self {
+        self.mul = mul;
+        self
+    }
+
+    pub fn smoothing(mut self, smoothing: f32) -> Self {
+        self.smoothing = smoothing;
+        self
+    }
+
+    pub fn update(&mut self, p: &Player, dt: f32) {
+        if let Some(ramp) = self.ramp.as_mut() {
+            ramp.t = (ramp.t + dt).min(ramp.over);
+            let fr = if ramp.over > 0.0 { ramp.t / ramp.over } else { 1.0 };
+            let fr = fr * fr * (3.0 - 2.0 * fr);
+            self.mul = ramp.from + (ramp.to - ramp.from) * fr;
+
+            if ramp.t >= ramp.over {
+                self.ramp = None;
+            }
+        }
+
+        let target = p.rate(self.source);
+        self.rate = if self.smoothing > 0.0 {
+            self.rate + (target - self.rate) * (1.0 - (-dt / self.smoothing).exp())
+        } else {
+            target
+        };
+
+        self.dt = self.speed * self.mul * self.rate * dt;
+        self.t += self.dt;
+    }
+
+    /// The clock's time.
+    pub fn t(&self) -> f32 {
+        self.t
+    }
+
+    /// How far the clock moved in the last update.
+    pub fn dt(&self) -> f32 {
+        self.dt
+    }
+
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed;
+    }
+
+    /// Set the speed multiplier, e.g. from a Mod slider.
+    pub fn set_mul(&mut self, mul: f32) {
+        self.mul = mul;
+        self.ramp = None;
+    }
+
+    /// Ease the speed multiplier to `mul` over `over` seconds.
+    pub fn ramp(&mut self, mul: f32, over: f32) {
+        self.ramp = Some(Ramp {
+            from: self.mul,
+            to: mul,
+            t: 0.0,
+            over,
+        });
+    }
+
+    /// Back to 0 at a multiplier of 1.
+    pub fn reset(&mut self) {
+        self.mul = 1.0;
+        self.ramp = None;
+        self.rate = 0.0;
+        self.t = 0.0;
+        self.dt = 0.0;
+    }
+}
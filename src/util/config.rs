@@ -0,0 +1,347 @@
+use std::ops::{Deref, DerefMut, Range};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use lib::math::{Vector2, Vector3, Vector4};
+use lib::prelude::*;
+
+use super::{Ease, Wave};
+
+/// A value that can be read from the right hand side of a `.cfg` line.
+pub trait CfgValue: Sized {
+    fn parse_cfg(s: &str) -> Result<Self, String>;
+}
+
+impl CfgValue for f32 {
+    fn parse_cfg(s: &str) -> Result<Self, String> {
+        s.parse().map_err(|_| format!("expected a number, got '{}'", s))
+    }
+}
+
+impl CfgValue for usize {
+    fn parse_cfg(s: &str) -> Result<Self, String> {
+        s.parse().map_err(|_| format!("expected a whole number, got '{}'", s))
+    }
+}
+
+impl CfgValue for bool {
+    fn parse_cfg(s: &str) -> Result<Self, String> {
+        match s {
+            "true" | "on" | "1" => Ok(true),
+            "false" | "off" | "0" => Ok(false),
+            _ => Err(format!("expected true or false, got '{}'", s)),
+        }
+    }
+}
+
+impl CfgValue for String {
+    fn parse_cfg(s: &str) -> Result<Self, String> {
+        Ok(s.to_owned())
+    }
+}
+
+fn floats(s: &str, lens: &[usize]) -> Result<Vec<f32>, String> {
+    let v = s.split(',').map(|x| f32::parse_cfg(x.trim())).collect::<Result<Vec<_>, _>>()?;
+    if !lens.contains(&v.len()) {
+        let lens = lens.iter().map(|n| n.to_string()).collect::<Vec<_>>().join(" or ");
+        return Err(format!("expected {} comma separated numbers, got '{}'", lens, s));
+    }
+    Ok(v)
+}
+
+/// `x,y`
+impl CfgValue for Vector2 {
+    fn parse_cfg(s: &str) -> Result<Self, String> {
+        let v = floats(s, &[2])?;
+        Ok(v2(v[0], v[1]))
+    }
+}
+
+/// `x,y,z`
+impl CfgValue for Vector3 {
+    fn parse_cfg(s: &str) -> Result<Self, String> {
+        let v = floats(s, &[3])?;
+        Ok(v3(v[0], v[1], v[2]))
+    }
+}
+
+/// A color, as `#rrggbb`, `#rrggbbaa`, or `r,g,b[,a]` from 0 to 1.
+impl CfgValue for Vector4 {
+    fn parse_cfg(s: &str) -> Result<Self, String> {
+        if let Some(hex) = s.strip_prefix('#') {
+            let bad = || format!("expected a #rrggbb color, got '{}'", s);
+            if !(hex.len() == 6 || hex.len() == 8) || !hex.is_ascii() {
+                return Err(bad());
+            }
+            let mut c = [1.0; 4];
+            for (i, c) in c.iter_mut().enumerate().take(hex.len() / 2) {
+                *c = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|_| bad())? as f32 / 255.0;
+            }
+            return Ok(v4(c[0], c[1], c[2], c[3]));
+        }
+
+        let v = floats(s, &[3, 4])?;
+        Ok(v4(v[0], v[1], v[2], v.get(3).copied().unwrap_or(1.0)))
+    }
+}
+
+/// `min..max`
+impl CfgValue for Range<f32> {
+    fn parse_cfg(s: &str) -> Result<Self, String> {
+        match s.split_once("..") {
+            Some((start, end)) => Ok(f32::parse_cfg(start.trim())?..f32::parse_cfg(end.trim())?),
+            None => Err(format!("expected a range like 0.0..1.0, got '{}'", s)),
+        }
+    }
+}
+
+/// `a; b; c`
+impl<T: CfgValue> CfgValue for Vec<T> {
+    fn parse_cfg(s: &str) -> Result<Self, String> {
+        s.split(';')
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .map(T::parse_cfg)
+            .collect()
+    }
+}
+
+impl CfgValue for Ease {
+    fn parse_cfg(s: &str) -> Result<Self, String> {
+        Ease::parse(s).ok_or_else(|| format!("unknown easing '{}'", s))
+    }
+}
+
+impl CfgValue for Wave {
+    fn parse_cfg(s: &str) -> Result<Self, String> {
+        Ok(match s {
+            "sine" => Wave::Sine,
+            "saw" => Wave::Saw,
+            "square" => Wave::Square,
+            "random" => Wave::Random,
+            _ => return Err(format!("expected one of sine, saw, square, random, got '{}'", s)),
+        })
+    }
+}
+
+/// Read a stage's own enum from its snake case names.
+///
+/// ```ignore
+/// cfg_enum!(Mode { Orbit = "orbit", Fly = "fly" });
+/// ```
+macro_rules! cfg_enum {
+    ($name:ident { $($variant:ident = $s:literal),* $(,)? }) => {
+        impl $crate::util::CfgValue for $name {
+            fn parse_cfg(s: &str) -> Result<Self, String> {
+                match s {
+                    $($s => Ok($name::$variant),)*
+                    _ => Err(format!(
+                        "expected one of {}, got '{}'",
+                        [$($s),*].join(", "),
+                        s
+                    )),
+                }
+            }
+        }
+    };
+}
+pub(crate) use cfg_enum;
+
+/// A stage's typed settings, usually declared with `stage_config!`.
+pub trait StageConfig: Default {
+    /// Set a field from its key. Returns `None` if there's no such key.
+    fn set(&mut self, key: &str, value: &str) -> Option<Result<(), String>>;
+}
+
+/// Declare a stage's config struct with a default for every key, and
+/// optional bounds checked when the file is read.
+///
+/// ```ignore
+/// stage_config!(AquaConfig {
+///     tmul: f32 = 200.0,
+///     tvhs: f32 [0.0, 1.0] = 0.9,
+///     aqua: Vector2 = v2(100.0, 300.0),
+///     tint: Vector4 = v4(1.0, 1.0, 1.0, 1.0),
+/// });
+/// ```
+macro_rules! stage_config {
+    ($(#[$smeta:meta])* $name:ident { $($(#[$meta:meta])* $field:ident: $ty:ty $([$min:expr, $max:expr])? = $default:expr),* $(,)? }) => {
+        $(#[$smeta])*
+        pub struct $name {
+            $($(#[$meta])* pub $field: $ty,)*
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self {
+                    $($field: $default,)*
+                }
+            }
+        }
+
+        impl $crate::util::StageConfig for $name {
+            fn set(&mut self, key: &str, value: &str) -> Option<Result<(), String>> {
+                match key {
+                    $(stringify!($field) => Some(
+                        <$ty as $crate::util::CfgValue>::parse_cfg(value).and_then(|v| {
+                            $(
+                                if !(v >= $min && v <= $max) {
+                                    return Err(format!("{} is out of range {:?}..{:?}", value, $min, $max));
+                                }
+                            )?
+                            self.$field = v;
+                            Ok(())
+                        }),
+                    ),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+pub(crate) use stage_config;
+
+/// How often a watched file is checked for changes.
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// A file in `resources/config`, watched for changes so it can be re-applied
+/// when it's saved. Shared by everything that hot reloads: `StageCfg`,
+/// `Routes`, `ModMatrix` and the fx presets.
+///
+/// However often it's asked, the file is only looked at every
+/// `WATCH_INTERVAL`.
+pub struct CfgFile {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    checked: Option<Instant>,
+}
+
+impl CfgFile {
+    pub fn new(cfg: &str) -> Self {
+        Self {
+            path: PathBuf::from("resources/config").join(cfg),
+            modified: None,
+            checked: None,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The file's contents if it changed since they were last returned,
+    /// always returning them the first time. A file that can't be read is
+    /// logged once, and read again once it changes.
+    pub fn changed(&mut self) -> Option<String> {
+        let now = Instant::now();
+        let first = self.checked.is_none();
+        if self.checked.map_or(false, |checked| now < checked + WATCH_INTERVAL) {
+            return None;
+        }
+        self.checked = Some(now);
+
+        let modified = std::fs::metadata(&self.path).and_then(|m| m.modified()).ok();
+        if !first && modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+
+        match std::fs::read_to_string(&self.path) {
+            Ok(src) => Some(src),
+            Err(e) => {
+                log::warn!("Failed to read {:?}: {}", self.path, e);
+                None
+            }
+        }
+    }
+}
+
+/// A stage's typed config, read from `resources/config/<cfg>`.
+///
+/// Keys missing from the file keep their defaults. Bad values and unknown
+/// keys are logged with the file and line and leave the field at its
+/// default. `route.` and `matrix.` lines belong to `Routes` and `ModMatrix`
+/// and are skipped.
+///
+/// The file is re-read when it changes on disk, starting over from the
+/// defaults, so a key deleted from the file goes back to its default. Fields
+/// are read straight through `Deref`, so values used every frame pick up
+/// edits on their own; settings copied out at startup should be re-applied
+/// when `reload` returns true.
+pub struct StageCfg<T> {
+    file: CfgFile,
+    value: T,
+}
+
+impl<T: StageConfig> StageCfg<T> {
+    pub fn load(cfg: &str) -> Self {
+        let mut cfg = Self {
+            file: CfgFile::new(cfg),
+            value: T::default(),
+        };
+        cfg.reload();
+        cfg
+    }
+
+    /// Re-read the file if it changed, returning whether it did.
+    pub fn reload(&mut self) -> bool {
+        let src = match self.file.changed() {
+            Some(src) => src,
+            None => return false,
+        };
+
+        let mut value = T::default();
+        for problem in parse(&mut value, &src) {
+            log::warn!("{}:{}", self.file.path().display(), problem);
+        }
+        self.value = value;
+
+        log::debug!("Loaded config from {:?}", self.file.path());
+        true
+    }
+}
+
+impl<T> Deref for StageCfg<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+/// Changes made at runtime last until the file next changes on disk.
+impl<T> DerefMut for StageCfg<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+/// Apply every line of a `.cfg` to `value`, returning `line: message` for each problem.
+pub fn parse<T: StageConfig>(value: &mut T, src: &str) -> Vec<String> {
+    let mut problems = vec![];
+
+    for (i, line) in src.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, v) = match line.split_once('=') {
+            Some((key, v)) => (key.trim(), v.trim()),
+            None => {
+                problems.push(format!("{}: expected key=value, got '{}'", i + 1, line));
+                continue;
+            }
+        };
+        if key.starts_with("route.") || key.starts_with("matrix.") {
+            continue;
+        }
+
+        match value.set(key, v) {
+            Some(Ok(())) => {}
+            Some(Err(e)) => problems.push(format!("{}: {}: {}", i + 1, key, e)),
+            None => problems.push(format!("{}: unknown key '{}'", i + 1, key)),
+        }
+    }
+
+    problems
+}
@@ -0,0 +1,387 @@
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+use rand::Rng;
+
+/// Easing curves, mapping 0..1 to 0..1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Ease {
+    Linear,
+    InQuad,
+    OutQuad,
+    InOutQuad,
+    InCubic,
+    OutCubic,
+    InOutCubic,
+    InExpo,
+    OutExpo,
+    InOutExpo,
+    InElastic,
+    OutElastic,
+    InBounce,
+    OutBounce,
+}
+
+impl Ease {
+    /// Parse a snake case name like `out_expo`.
+    pub fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "linear" => Ease::Linear,
+            "in_quad" => Ease::InQuad,
+            "out_quad" => Ease::OutQuad,
+            "in_out_quad" => Ease::InOutQuad,
+            "in_cubic" => Ease::InCubic,
+            "out_cubic" => Ease::OutCubic,
+            "in_out_cubic" => Ease::InOutCubic,
+            "in_expo" => Ease::InExpo,
+            "out_expo" => Ease::OutExpo,
+            "in_out_expo" => Ease::InOutExpo,
+            "in_elastic" => Ease::InElastic,
+            "out_elastic" => Ease::OutElastic,
+            "in_bounce" => Ease::InBounce,
+            "out_bounce" => Ease::OutBounce,
+            _ => return None,
+        })
+    }
+
+    pub fn apply(self, x: f32) -> f32 {
+        let x = x.clamp(0.0, 1.0);
+        match self {
+            Ease::Linear => x,
+            Ease::InQuad => x * x,
+            Ease::OutQuad => 1.0 - (1.0 - x) * (1.0 - x),
+            Ease::InOutQuad => match x < 0.5 {
+                true => 2.0 * x * x,
+                false => 1.0 - (-2.0 * x + 2.0).powi(2) / 2.0,
+            },
+            Ease::InCubic => x * x * x,
+            Ease::OutCubic => 1.0 - (1.0 - x).powi(3),
+            Ease::InOutCubic => match x < 0.5 {
+                true => 4.0 * x * x * x,
+                false => 1.0 - (-2.0 * x + 2.0).powi(3) / 2.0,
+            },
+            Ease::InExpo => match x {
+                x if x <= 0.0 => 0.0,
+                x => 2f32.powf(10.0 * x - 10.0),
+            },
+            Ease::OutExpo => match x {
+                x if x >= 1.0 => 1.0,
+                x => 1.0 - 2f32.powf(-10.0 * x),
+            },
+            Ease::InOutExpo => match x {
+                x if x <= 0.0 => 0.0,
+                x if x >= 1.0 => 1.0,
+                x if x < 0.5 => 2f32.powf(20.0 * x - 10.0) / 2.0,
+                x => (2.0 - 2f32.powf(-20.0 * x + 10.0)) / 2.0,
+            },
+            Ease::InElastic => match x {
+                x if x <= 0.0 || x >= 1.0 => x,
+                x => -(2f32.powf(10.0 * x - 10.0)) * ((x * 10.0 - 10.75) * (2.0 * PI / 3.0)).sin(),
+            },
+            Ease::OutElastic => match x {
+                x if x <= 0.0 || x >= 1.0 => x,
+                x => 2f32.powf(-10.0 * x) * ((x * 10.0 - 0.75) * (2.0 * PI / 3.0)).sin() + 1.0,
+            },
+            Ease::InBounce => 1.0 - Ease::OutBounce.apply(1.0 - x),
+            Ease::OutBounce => {
+                let (n, d) = (7.5625, 2.75);
+                if x < 1.0 / d {
+                    n * x * x
+                } else if x < 2.0 / d {
+                    let x = x - 1.5 / d;
+                    n * x * x + 0.75
+                } else if x < 2.5 / d {
+                    let x = x - 2.25 / d;
+                    n * x * x + 0.9375
+                } else {
+                    let x = x - 2.625 / d;
+                    n * x * x + 0.984375
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Phase {
+    Idle,
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+}
+
+/// Attack, decay, sustain, release envelope. Times are in seconds.
+pub struct Adsr {
+    attack: f32,
+    decay: f32,
+    sustain: f32,
+    release: f32,
+
+    phase: Phase,
+    t: f32,
+    v: f32,
+    /// Level the attack or release started from
+    from: f32,
+}
+
+impl Adsr {
+    pub fn new(attack: f32, decay: f32, sustain: f32, release: f32) -> Self {
+        Self {
+            attack,
+            decay,
+            sustain,
+            release,
+
+            phase: Phase::Idle,
+            t: 0.0,
+            v: 0.0,
+            from: 0.0,
+        }
+    }
+
+    /// Start the attack, holding at the sustain level until `off`.
+    pub fn on(&mut self) {
+        self.phase = Phase::Attack;
+        self.from = self.v;
+        self.t = 0.0;
+    }
+
+    /// Start the release.
+    pub fn off(&mut self) {
+        if self.phase != Phase::Idle {
+            self.phase = Phase::Release;
+            self.from = self.v;
+            self.t = 0.0;
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.t += dt;
+
+        let fr = |t: f32, len: f32| if len > 0.0 { (t / len).min(1.0) } else { 1.0 };
+        match self.phase {
+            Phase::Idle => self.v = 0.0,
+            Phase::Attack => {
+                // Attack from wherever the envelope is, so retriggers don't click
+                self.v = self.from + (1.0 - self.from) * fr(self.t, self.attack);
+                if self.t >= self.attack {
+                    self.phase = Phase::Decay;
+                    self.t = 0.0;
+                }
+            }
+            Phase::Decay => {
+                self.v = 1.0 + (self.sustain - 1.0) * fr(self.t, self.decay);
+                if self.t >= self.decay {
+                    self.phase = Phase::Sustain;
+                }
+            }
+            Phase::Sustain => self.v = self.sustain,
+            Phase::Release => {
+                self.v = self.from * (1.0 - fr(self.t, self.release));
+                if self.t >= self.release {
+                    self.phase = Phase::Idle;
+                }
+            }
+        }
+    }
+
+    pub fn v(&self) -> f32 {
+        self.v
+    }
+
+    pub fn reset(&mut self) {
+        self.phase = Phase::Idle;
+        self.t = 0.0;
+        self.v = 0.0;
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Wave {
+    Sine,
+    Saw,
+    Square,
+    /// Random sample and hold
+    Random,
+}
+
+/// Shortest LFO period in beats. Anything shorter, including 0, is clamped to it.
+const MIN_BEATS: f32 = 1.0 / 64.0;
+
+/// Tempo synced oscillator from 0 to 1.
+pub struct Lfo {
+    wave: Wave,
+    /// Period in beats
+    beats: f32,
+    phase: f32,
+    held: f32,
+}
+
+impl Lfo {
+    pub fn new(wave: Wave, beats: f32) -> Self {
+        if beats.is_nan() || beats < MIN_BEATS {
+            log::warn!("LFO period of {} beats is too short, using {}", beats, MIN_BEATS);
+        }
+        Self {
+            wave,
+            beats: beats.max(MIN_BEATS),
+            phase: 0.0,
+            held: 0.0,
+        }
+    }
+
+    pub fn update(&mut self, dt: f32, bpm: f32) {
+        self.phase += bpm / 60.0 * dt / self.beats;
+        if self.phase >= 1.0 {
+            self.phase = self.phase.fract();
+            self.held = rand::thread_rng().gen();
+        }
+    }
+
+    pub fn v(&self) -> f32 {
+        match self.wave {
+            Wave::Sine => 0.5 - 0.5 * (self.phase * 2.0 * PI).cos(),
+            Wave::Saw => self.phase,
+            Wave::Square => if self.phase < 0.5 { 1.0 } else { 0.0 },
+            Wave::Random => self.held,
+        }
+    }
+
+    /// Restart the cycle, e.g. on a downbeat.
+    pub fn sync(&mut self) {
+        self.phase = 0.0;
+    }
+}
+
+/// An eased move from one value to another.
+pub struct Tween {
+    ease: Ease,
+    from: f32,
+    to: f32,
+    t: f32,
+    len: f32,
+}
+
+impl Tween {
+    pub fn new(ease: Ease, v: f32) -> Self {
+        Self {
+            ease,
+            from: v,
+            to: v,
+            t: 0.0,
+            len: 0.0,
+        }
+    }
+
+    /// Move from the current value to `to` over `len` seconds.
+    pub fn go(&mut self, to: f32, len: f32) {
+        self.from = self.v();
+        self.to = to;
+        self.t = 0.0;
+        self.len = len;
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.t = (self.t + dt).min(self.len);
+    }
+
+    pub fn v(&self) -> f32 {
+        let fr = if self.len > 0.0 { self.t / self.len } else { 1.0 };
+        self.from + (self.to - self.from) * self.ease.apply(fr)
+    }
+}
+
+pub enum Envelope {
+    Adsr(Adsr),
+    Lfo(Lfo),
+    Tween(Tween),
+}
+
+/// Named ADSRs, LFOs and tweens, updated together.
+///
+/// ```ignore
+/// let env = EnvelopeEnv::default()
+///     .with_adsr("push", Adsr::new(0.05, 0.2, 0.6, 1.0))
+///     .with_lfo("pulse", Lfo::new(Wave::Sine, 2.0))
+///     .with_tween("zoom", Tween::new(Ease::OutElastic, 1.0));
+/// ```
+#[derive(Default)]
+pub struct EnvelopeEnv {
+    map: HashMap<String, Envelope>,
+}
+
+impl EnvelopeEnv {
+    pub fn with_adsr(mut self, key: &str, adsr: Adsr) -> Self {
+        self.map.insert(key.to_owned(), Envelope::Adsr(adsr));
+        self
+    }
+
+    pub fn with_lfo(mut self, key: &str, lfo: Lfo) -> Self {
+        self.map.insert(key.to_owned(), Envelope::Lfo(lfo));
+        self
+    }
+
+    pub fn with_tween(mut self, key: &str, tween: Tween) -> Self {
+        self.map.insert(key.to_owned(), Envelope::Tween(tween));
+        self
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.map.contains_key(key)
+    }
+
+    pub fn get(&self, key: &str) -> &Envelope {
+        self.map
+            .get(key)
+            .unwrap_or_else(|| panic!("no such envelope {}", key))
+    }
+    pub fn get_mut(&mut self, key: &str) -> &mut Envelope {
+        self.map
+            .get_mut(key)
+            .unwrap_or_else(|| panic!("no such envelope {}", key))
+    }
+
+    pub fn v(&self, key: &str) -> f32 {
+        match self.get(key) {
+            Envelope::Adsr(adsr) => adsr.v(),
+            Envelope::Lfo(lfo) => lfo.v(),
+            Envelope::Tween(tween) => tween.v(),
+        }
+    }
+
+    /// Start an ADSR's attack, or restart an LFO's cycle.
+    pub fn on(&mut self, key: &str) {
+        match self.get_mut(key) {
+            Envelope::Adsr(adsr) => adsr.on(),
+            Envelope::Lfo(lfo) => lfo.sync(),
+            Envelope::Tween(_) => log::warn!("Envelope {} is a tween, it can't be turned on", key),
+        }
+    }
+
+    /// Release an ADSR.
+    pub fn off(&mut self, key: &str) {
+        match self.get_mut(key) {
+            Envelope::Adsr(adsr) => adsr.off(),
+            _ => log::warn!("Envelope {} is not an adsr, it can't be turned off", key),
+        }
+    }
+
+    /// Start a tween towards `to`.
+    pub fn go(&mut self, key: &str, to: f32, len: f32) {
+        match self.get_mut(key) {
+            Envelope::Tween(tween) => tween.go(to, len),
+            _ => log::warn!("Envelope {} is not a tween, it can't go to {}", key, to),
+        }
+    }
+
+    pub fn update(&mut self, dt: f32, bpm: f32) {
+        for env in self.map.values_mut() {
+            match env {
+                Envelope::Adsr(adsr) => adsr.update(dt),
+                Envelope::Lfo(lfo) => lfo.update(dt, bpm),
+                Envelope::Tween(tween) => tween.update(dt),
+            }
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use super::{Adsr, Ease, EnvelopeEnv, Lfo, Tween, Wave};
+
+fn assert_close(a: f32, b: f32) {
+    assert!((a - b).abs() < 1e-4, "{} != {}", a, b);
+}
+
+#[test]
+fn test_ease_ends() {
+    let names = [
+        "linear", "in_quad", "out_quad", "in_out_quad", "in_cubic", "out_cubic", "in_out_cubic",
+        "in_expo", "out_expo", "in_out_expo", "in_elastic", "out_elastic", "in_bounce", "out_bounce",
+    ];
+    for name in names {
+        let ease = Ease::parse(name).unwrap();
+        assert_close(ease.apply(0.0), 0.0);
+        assert_close(ease.apply(1.0), 1.0);
+        // Outside 0..1 is clamped
+        assert_close(ease.apply(-1.0), 0.0);
+        assert_close(ease.apply(2.0), 1.0);
+    }
+    assert_close(Ease::InQuad.apply(0.5), 0.25);
+    assert_close(Ease::OutQuad.apply(0.5), 0.75);
+    assert_eq!(Ease::parse("sideways"), None);
+}
+
+#[test]
+fn test_adsr() {
+    let mut adsr = Adsr::new(0.1, 0.1, 0.5, 0.2);
+    adsr.update(0.05);
+    assert_close(adsr.v(), 0.0);
+
+    adsr.on();
+    adsr.update(0.05);
+    assert_close(adsr.v(), 0.5);
+    adsr.update(0.05);
+    assert_close(adsr.v(), 1.0);
+
+    // Decay to the sustain level, and hold there
+    adsr.update(0.05);
+    assert_close(adsr.v(), 0.75);
+    adsr.update(0.05);
+    assert_close(adsr.v(), 0.5);
+    adsr.update(1.0);
+    assert_close(adsr.v(), 0.5);
+
+    adsr.off();
+    adsr.update(0.1);
+    assert_close(adsr.v(), 0.25);
+    adsr.update(0.1);
+    assert_close(adsr.v(), 0.0);
+    adsr.update(0.1);
+    assert_close(adsr.v(), 0.0);
+}
+
+#[test]
+fn test_adsr_retrigger() {
+    let mut adsr = Adsr::new(0.1, 0.1, 0.5, 0.2);
+    adsr.on();
+    adsr.update(0.1);
+    adsr.off();
+    adsr.update(0.1);
+    assert_close(adsr.v(), 0.5);
+
+    // The attack starts from where the release got to
+    adsr.on();
+    adsr.update(0.05);
+    assert_close(adsr.v(), 0.75);
+}
+
+#[test]
+fn test_lfo() {
+    // Two beats at 120 bpm is one second
+    let mut saw = Lfo::new(Wave::Saw, 2.0);
+    let mut sine = Lfo::new(Wave::Sine, 2.0);
+    let mut square = Lfo::new(Wave::Square, 2.0);
+    for lfo in [&mut saw, &mut sine, &mut square] {
+        lfo.update(0.25, 120.0);
+    }
+    assert_close(saw.v(), 0.25);
+    assert_close(sine.v(), 0.5);
+    assert_close(square.v(), 1.0);
+
+    for lfo in [&mut saw, &mut sine, &mut square] {
+        lfo.update(0.25, 120.0);
+    }
+    assert_close(saw.v(), 0.5);
+    assert_close(sine.v(), 1.0);
+    assert_close(square.v(), 0.0);
+
+    // Wraps around
+    saw.update(0.75, 120.0);
+    assert_close(saw.v(), 0.25);
+    saw.sync();
+    assert_close(saw.v(), 0.0);
+}
+
+#[test]
+fn test_lfo_zero_beats() {
+    for beats in [0.0, -1.0, f32::NAN] {
+        let mut lfo = Lfo::new(Wave::Saw, beats);
+        lfo.update(0.1, 120.0);
+        assert!((0.0..1.0).contains(&lfo.v()), "{} beats gave {}", beats, lfo.v());
+    }
+}
+
+#[test]
+fn test_tween() {
+    let mut tween = Tween::new(Ease::Linear, 1.0);
+    assert_close(tween.v(), 1.0);
+
+    tween.go(3.0, 2.0);
+    tween.update(1.0);
+    assert_close(tween.v(), 2.0);
+    tween.update(5.0);
+    assert_close(tween.v(), 3.0);
+
+    // A zero length tween jumps straight there
+    tween.go(0.0, 0.0);
+    assert_close(tween.v(), 0.0);
+}
+
+#[test]
+fn test_wrong_envelope_type() {
+    let mut env = EnvelopeEnv::default()
+        .with_lfo("pulse", Lfo::new(Wave::Saw, 1.0))
+        .with_tween("zoom", Tween::new(Ease::Linear, 1.0));
+
+    env.off("pulse");
+    env.go("pulse", 1.0, 1.0);
+    env.on("zoom");
+    env.update(0.25, 120.0);
+
+    assert_close(env.v("pulse"), 0.5);
+    assert_close(env.v("zoom"), 1.0);
+}
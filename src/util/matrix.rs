@@ -0,0 +1,322 @@
+use std::collections::HashSet;
+
+use lib::prelude::*;
+
+use super::{CfgFile, CounterEnv, Ease, EnvelopeEnv};
+use crate::demo::Player;
+use crate::pipeline::{Fx, Phong, Spiral, Starfield};
+
+#[derive(Clone, Debug, PartialEq)]
+enum Source {
+    Rms,
+    Band(usize),
+    Decay(String),
+    Count(String),
+    Env(String),
+    Slider(u8),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Dest {
+    Fx(String),
+    Spiral(String),
+    Starfield(String),
+    /// Material name, and color channel or all of rgb
+    Material(String, Option<usize>),
+}
+
+#[derive(Clone, Debug)]
+struct Route {
+    source: Source,
+    depth: f32,
+    /// Sources are used as they are without one, and clamped to 0..1 with one
+    curve: Option<Ease>,
+    offset: f32,
+}
+
+/// Modulation routes from sources to parameters, read from `matrix.<dest>`
+/// lines of a stage's `.cfg`. Every route to a destination is summed.
+///
+/// ```text
+/// matrix.fx.shake=decay.kick
+/// matrix.fx.flash=decay.snare 0.8 out_expo; mod.2
+/// matrix.spiral.amount=band.0 2.0 linear 0.1
+/// matrix.material.ButtonMain.r=decay.hat 1.0 in_quad
+/// ```
+///
+/// A route is `<source> [depth] [curve] [offset]`, giving
+/// `offset + depth * curve(source)`. The curve is an easing like `out_expo`,
+/// or `raw` for none, which is the default.
+///
+/// Sources are `rms`, `band.<n>`, `decay.<name>`, `count.<name>`,
+/// `env.<name>` and `mod.<id>` for a Mod slider. Destinations are
+/// `fx.<field>`, `spiral.<field>`, `starfield.<field>` and
+/// `material.<name>[.r|.g|.b|.a]`.
+///
+/// Routes from a decay, counter or envelope the stage doesn't have are
+/// dropped with a warning. The file is re-read when it changes on disk.
+pub struct ModMatrix {
+    file: CfgFile,
+    dests: Vec<(Dest, Vec<Route>)>,
+    values: Vec<f32>,
+    warned: HashSet<String>,
+}
+
+impl ModMatrix {
+    pub fn load(cfg: &str, decay: &DecayEnv, count: &CounterEnv, env: Option<&EnvelopeEnv>) -> Self {
+        let mut matrix = Self {
+            file: CfgFile::new(cfg),
+            dests: vec![],
+            values: vec![],
+            warned: HashSet::new(),
+        };
+        matrix.reload(decay, count, env);
+        matrix
+    }
+
+    fn reload(&mut self, decay: &DecayEnv, count: &CounterEnv, env: Option<&EnvelopeEnv>) {
+        let src = match self.file.changed() {
+            Some(src) => src,
+            None => return,
+        };
+        let path = self.file.path().display();
+
+        let (dests, problems) = parse(&src);
+        for problem in problems {
+            log::warn!("{}:{}", path, problem);
+        }
+
+        self.dests.clear();
+        self.warned.clear();
+        for (dest, routes) in dests {
+            let routes = routes
+                .into_iter()
+                .filter(|(line, route)| match missing(&route.source, decay, count, env) {
+                    Some(what) => {
+                        log::warn!("{}:{}: no {} to modulate from", path, line, what);
+                        false
+                    }
+                    None => true,
+                })
+                .map(|(_, route)| route)
+                .collect();
+            self.dests.push((dest, routes));
+        }
+        self.values = vec![0.0; self.dests.len()];
+
+        log::debug!("Loaded {} matrix destinations from {}", self.dests.len(), path);
+    }
+
+    /// The Mod sliders a `.cfg`'s matrix reads, and `line: message` for each
+    /// bad destination or route, to check a stage's matrix without building it.
+    pub fn scan(src: &str) -> (Vec<u8>, Vec<String>) {
+        let (dests, problems) = parse(src);
+        let mut ids: Vec<u8> = dests
+            .iter()
+            .flat_map(|(_, routes)| routes.iter())
+            .filter_map(|(_, route)| match route.source {
+                Source::Slider(id) => Some(id),
+                _ => None,
+            })
+            .collect();
+        ids.sort();
+        ids.dedup();
+        (ids, problems)
+    }
+
+    /// Read every source.
+    pub fn update(&mut self, p: &Player, decay: &DecayEnv, count: &CounterEnv, env: Option<&EnvelopeEnv>) {
+        self.reload(decay, count, env);
+
+        for ((_, routes), value) in self.dests.iter().zip(self.values.iter_mut()) {
+            *value = routes
+                .iter()
+                .map(|route| {
+                    let v = match &route.source {
+                        Source::Rms => p.rms(),
+                        Source::Band(band) => p.band(*band),
+                        Source::Decay(name) => decay.v(name),
+                        Source::Count(name) => count.v(name) as f32,
+                        Source::Env(name) => env.map_or(0.0, |env| env.v(name)),
+                        Source::Slider(id) => p.slider(*id),
+                    };
+                    let v = route.curve.map(|curve| curve.apply(v)).unwrap_or(v);
+                    route.offset + route.depth * v
+                })
+                .sum();
+        }
+    }
+
+    fn warn(warned: &mut HashSet<String>, what: &str, name: &str) {
+        if warned.insert(format!("{}.{}", what, name)) {
+            log::warn!("Matrix destination {}.{} does not exist", what, name);
+        }
+    }
+
+    pub fn apply_fx(&mut self, fx: &mut Fx) {
+        for ((dest, _), v) in self.dests.iter().zip(self.values.iter()) {
+            if let Dest::Fx(field) = dest {
+                match fx.field(field) {
+                    Some(f) => *f = *v,
+                    None => Self::warn(&mut self.warned, "fx", field),
+                }
+            }
+        }
+    }
+
+    pub fn apply_spiral(&mut self, spiral: &mut Spiral) {
+        for ((dest, _), v) in self.dests.iter().zip(self.values.iter()) {
+            if let Dest::Spiral(field) = dest {
+                match spiral.field(field) {
+                    Some(f) => *f = *v,
+                    None => Self::warn(&mut self.warned, "spiral", field),
+                }
+            }
+        }
+    }
+
+    pub fn apply_starfield(&mut self, starfield: &mut Starfield) {
+        for ((dest, _), v) in self.dests.iter().zip(self.values.iter()) {
+            if let Dest::Starfield(field) = dest {
+                match starfield.field(field) {
+                    Some(f) => *f = *v,
+                    None => Self::warn(&mut self.warned, "starfield", field),
+                }
+            }
+        }
+    }
+
+    pub fn apply_scene(&mut self, scene: &mut Phong) {
+        for ((dest, _), v) in self.dests.iter().zip(self.values.iter()) {
+            if let Dest::Material(name, channel) = dest {
+                let mat = match scene.try_material(name) {
+                    Some(mat) => mat,
+                    None => {
+                        Self::warn(&mut self.warned, "material", name);
+                        continue;
+                    }
+                };
+                match channel {
+                    Some(0) => mat.color.x = *v,
+                    Some(1) => mat.color.y = *v,
+                    Some(2) => mat.color.z = *v,
+                    Some(_) => mat.color.w = *v,
+                    None => {
+                        mat.color.x = *v;
+                        mat.color.y = *v;
+                        mat.color.z = *v;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Read the destinations from the `matrix.` lines of a `.cfg`, with the line
+/// each route is on, and `line: message` for each bad one.
+fn parse(src: &str) -> (Vec<(Dest, Vec<(usize, Route)>)>, Vec<String>) {
+    let mut dests = vec![];
+    let mut problems = vec![];
+
+    for (i, line) in src.lines().enumerate() {
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => continue,
+        };
+        let dest = match key.strip_prefix("matrix.") {
+            Some(dest) => dest,
+            None => continue,
+        };
+        let dest = match parse_dest(dest) {
+            Some(dest) => dest,
+            None => {
+                problems.push(format!("{}: bad matrix destination '{}'", i + 1, dest));
+                continue;
+            }
+        };
+
+        let mut routes = vec![];
+        for route in value.split(';').map(str::trim).filter(|r| !r.is_empty()) {
+            match parse_route(route) {
+                Some(route) => routes.push((i + 1, route)),
+                None => problems.push(format!("{}: bad matrix route '{}'", i + 1, route)),
+            }
+        }
+        dests.push((dest, routes));
+    }
+
+    (dests, problems)
+}
+
+fn parse_dest(dest: &str) -> Option<Dest> {
+    let (kind, field) = dest.split_once('.')?;
+    let field = field.to_owned();
+
+    Some(match kind {
+        "fx" => Dest::Fx(field),
+        "spiral" => Dest::Spiral(field),
+        "starfield" => Dest::Starfield(field),
+        "material" => {
+            let channel = match field.rsplit_once('.') {
+                Some((name, "r")) => Some((name, 0)),
+                Some((name, "g")) => Some((name, 1)),
+                Some((name, "b")) => Some((name, 2)),
+                Some((name, "a")) => Some((name, 3)),
+                _ => None,
+            };
+            match channel {
+                Some((name, channel)) => Dest::Material(name.to_owned(), Some(channel)),
+                None => Dest::Material(field, None),
+            }
+        }
+        _ => return None,
+    })
+}
+
+fn parse_source(source: &str) -> Option<Source> {
+    if source == "rms" {
+        return Some(Source::Rms);
+    }
+
+    let (kind, name) = source.split_once('.')?;
+    Some(match kind {
+        "band" => Source::Band(name.parse().ok()?),
+        "decay" => Source::Decay(name.to_owned()),
+        "count" => Source::Count(name.to_owned()),
+        "env" => Source::Env(name.to_owned()),
+        "mod" => Source::Slider(name.parse().ok()?),
+        _ => return None,
+    })
+}
+
+/// The env a source reads from, if the stage doesn't have it.
+fn missing(source: &Source, decay: &DecayEnv, count: &CounterEnv, env: Option<&EnvelopeEnv>) -> Option<String> {
+    match source {
+        Source::Decay(name) if !decay.contains(name) => Some(format!("decay '{}'", name)),
+        Source::Count(name) if !count.contains(name) => Some(format!("counter '{}'", name)),
+        Source::Env(name) if !env.map_or(false, |env| env.contains(name)) => Some(format!("envelope '{}'", name)),
+        _ => None,
+    }
+}
+
+/// Parse `<source> [depth] [curve] [offset]`.
+fn parse_route(route: &str) -> Option<Route> {
+    let mut words = route.split_whitespace();
+
+    let source = parse_source(words.next()?)?;
+    let depth = match words.next() {
+        Some(depth) => depth.parse().ok()?,
+        None => 1.0,
+    };
+    let curve = match words.next() {
+        Some("raw") => None,
+        Some(curve) => Some(Ease::parse(curve)?),
+        None => None,
+    };
+    let offset = match words.next() {
+        Some(offset) => offset.parse().ok()?,
+        None => 0.0,
+    };
+
+    Some(Route { source, depth, curve, offset })
+}
@@ -1 +1,10 @@
 mod state; pub use state::*;
+mod route; pub use route::*;
+mod clock; pub use clock::*;
+mod sequence; pub use sequence::*;
+mod envelope; pub use envelope::*;
+mod matrix; pub use matrix::*;
+mod config; pub use config::*;
+
+#[cfg(test)]
+mod envelope_test;
@@ -0,0 +1,160 @@
+use lib::prelude::*;
+
+use super::{CfgFile, CounterEnv};
+use crate::demo::Event;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Action {
+    /// Restart the decay over the event's duration times the multiplier
+    Set,
+    /// Hold the decay at full while a toggle is on, then release over the multiplier
+    Hold,
+    /// Increment the counter
+    Inc,
+}
+
+#[derive(Clone, Debug)]
+struct Rule {
+    id: u8,
+    action: Action,
+    target: String,
+    mul: f32,
+}
+
+/// Event to decay/counter routing, read from `route.<id>` lines of a stage's `.cfg`.
+///
+/// ```text
+/// route.62=set hat 1.0
+/// route.72=set ah 1.25; set kick 1.25; inc ah; inc camjump
+/// route.40=hold strobe 0.5
+/// ```
+///
+/// Rules whose target isn't one of the stage's decays or counters are dropped
+/// with a warning. The file is re-read when it changes on disk, checked once
+/// a frame by `reload`.
+pub struct Routes {
+    file: CfgFile,
+    rules: Vec<Rule>,
+}
+
+impl Routes {
+    pub fn load(cfg: &str, decay: &DecayEnv, count: &CounterEnv) -> Self {
+        let mut routes = Self {
+            file: CfgFile::new(cfg),
+            rules: vec![],
+        };
+        routes.reload(decay, count);
+        routes
+    }
+
+    /// Re-read the file if it changed, checking targets against the stage's envs.
+    pub fn reload(&mut self, decay: &DecayEnv, count: &CounterEnv) {
+        let src = match self.file.changed() {
+            Some(src) => src,
+            None => return,
+        };
+        let path = self.file.path().display();
+
+        let (rules, problems) = parse(&src);
+        for problem in problems {
+            log::warn!("{}:{}", path, problem);
+        }
+
+        self.rules.clear();
+        for (line, rule) in rules {
+            match rule.action {
+                Action::Inc if !count.contains(&rule.target) => {
+                    log::warn!("{}:{}: no counter '{}' to route to", path, line, rule.target)
+                }
+                Action::Set | Action::Hold if !decay.contains(&rule.target) => {
+                    log::warn!("{}:{}: no decay '{}' to route to", path, line, rule.target)
+                }
+                _ => self.rules.push(rule),
+            }
+        }
+
+        log::debug!("Loaded {} routes from {}", self.rules.len(), path);
+    }
+
+    /// The ids a `.cfg` routes, and `line: message` for each bad rule, to
+    /// check a stage's routes without building it.
+    pub fn scan(src: &str) -> (Vec<u8>, Vec<String>) {
+        let (rules, problems) = parse(src);
+        let mut ids: Vec<u8> = rules.iter().map(|(_, rule)| rule.id).collect();
+        ids.sort();
+        ids.dedup();
+        (ids, problems)
+    }
+
+    /// Apply every rule matching the event.
+    pub fn apply(&self, ev: Event, decay: &mut DecayEnv, count: &mut CounterEnv) {
+        let (id, t, state) = match ev {
+            Event::Trigger { id } => (id, 1.0, true),
+            Event::Beat { id, t } => (id, t, true),
+            Event::Toggle { id, state } => (id, 1.0, state),
+            _ => return,
+        };
+
+        for rule in self.rules.iter().filter(|rule| rule.id == id) {
+            match (rule.action, state) {
+                (Action::Set, true) => decay.set_t(&rule.target, t * rule.mul),
+                (Action::Hold, true) => decay.set_t(&rule.target, f32::INFINITY),
+                (Action::Hold, false) => decay.set_t(&rule.target, rule.mul),
+                (Action::Inc, true) => {
+                    count.inc(&rule.target);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Read the rules from the `route.` lines of a `.cfg`, with the line each is
+/// on, and `line: message` for each bad one.
+fn parse(src: &str) -> (Vec<(usize, Rule)>, Vec<String>) {
+    let mut rules = vec![];
+    let mut problems = vec![];
+
+    for (i, line) in src.lines().enumerate() {
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => continue,
+        };
+        let id = match key.strip_prefix("route.").map(str::parse::<u8>) {
+            Some(Ok(id)) => id,
+            Some(Err(_)) => {
+                problems.push(format!("{}: bad route id '{}'", i + 1, key));
+                continue;
+            }
+            None => continue,
+        };
+
+        for rule in value.split(';').map(str::trim).filter(|r| !r.is_empty()) {
+            match parse_rule(id, rule) {
+                Some(rule) => rules.push((i + 1, rule)),
+                None => problems.push(format!("{}: bad route '{}'", i + 1, rule)),
+            }
+        }
+    }
+
+    (rules, problems)
+}
+
+/// Parse `<action> <target> [multiplier]`.
+fn parse_rule(id: u8, rule: &str) -> Option<Rule> {
+    let mut words = rule.split_whitespace();
+
+    let action = match words.next()? {
+        "set" => Action::Set,
+        "hold" => Action::Hold,
+        "inc" => Action::Inc,
+        _ => return None,
+    };
+    let target = words.next()?.to_owned();
+    let mul = match words.next() {
+        Some(mul) => mul.parse().ok()?,
+        None => 1.0,
+    };
+
+    Some(Rule { id, action, target, mul })
+}
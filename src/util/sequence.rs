@@ -0,0 +1,214 @@
+use crate::demo::{Event, Player};
+
+/// Beats in a bar, for `after_bars`.
+const BEATS_PER_BAR: f32 = 4.0;
+
+type Action<C> = Box<dyn FnMut(&mut C) + Send>;
+
+enum Trigger {
+    Note(u8),
+    Name(&'static str),
+}
+
+enum Advance {
+    Secs(f32),
+    Bars(f32),
+}
+
+struct Segment<S, C> {
+    id: S,
+    enter: Option<Action<C>>,
+    exit: Option<Action<C>>,
+    triggers: Vec<Trigger>,
+    advance: Option<Advance>,
+    next: Option<S>,
+}
+
+/// A stage's segments, stepped through by events or after a while.
+///
+/// Builder methods after `segment` apply to that segment. Enter and exit
+/// actions get whatever the stage passes to `update`/`event`, usually the
+/// scene or passes they change. A segment moves on to the one declared
+/// after it unless `then` says otherwise, and the last one wraps around.
+///
+/// ```ignore
+/// let seq = Sequencer::new()
+///     .segment(Segment::Intro).on_name("next_segment")
+///     .segment(Segment::Synth).on_name("next_segment")
+///         .enter(|scene: &mut Phong| scene.material("Cube").color = v4(1.0, 1.0, 0.0, 1.0))
+///     .segment(Segment::Outro).after_bars(8.0).then(Segment::Intro);
+/// ```
+pub struct Sequencer<S, C> {
+    segments: Vec<Segment<S, C>>,
+    current: usize,
+    secs: f32,
+    beats: f32,
+}
+
+impl<S: Copy + PartialEq, C> Default for Sequencer<S, C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: Copy + PartialEq, C> Sequencer<S, C> {
+    pub fn new() -> Self {
+        Self {
+            segments: vec![],
+            current: 0,
+            secs: 0.0,
+            beats: 0.0,
+        }
+    }
+
+    /// Declare a segment. The first one declared is where the sequence starts.
+    pub fn segment(mut self, id: S) -> Self {
+        self.segments.push(Segment {
+            id,
+            enter: None,
+            exit: None,
+            triggers: vec![],
+            advance: None,
+            next: None,
+        });
+        self
+    }
+
+    fn last(&mut self) -> &mut Segment<S, C> {
+        self.segments.last_mut().expect("declare a segment first")
+    }
+
+    pub fn enter(mut self, f: impl FnMut(&mut C) + Send + 'static) -> Self {
+        self.last().enter = Some(Box::new(f));
+        self
+    }
+
+    pub fn exit(mut self, f: impl FnMut(&mut C) + Send + 'static) -> Self {
+        self.last().exit = Some(Box::new(f));
+        self
+    }
+
+    /// Move on when a note event with this id arrives.
+    pub fn on_note(mut self, id: u8) -> Self {
+        self.last().triggers.push(Trigger::Note(id));
+        self
+    }
+
+    /// Move on when an event with this name, from the demo's `.names`, arrives.
+    pub fn on_name(mut self, name: &'static str) -> Self {
+        self.last().triggers.push(Trigger::Name(name));
+        self
+    }
+
+    /// Move on after some seconds of stage time.
+    pub fn after_secs(mut self, secs: f32) -> Self {
+        self.last().advance = Some(Advance::Secs(secs));
+        self
+    }
+
+    /// Move on after some bars at the song's tempo.
+    pub fn after_bars(mut self, bars: f32) -> Self {
+        self.last().advance = Some(Advance::Bars(bars));
+        self
+    }
+
+    /// The segment to move on to, instead of the next one declared.
+    pub fn then(mut self, next: S) -> Self {
+        self.last().next = Some(next);
+        self
+    }
+
+    /// The current segment.
+    pub fn current(&self) -> S {
+        self.segments[self.current].id
+    }
+
+    /// How far through the current segment we are, from 0 to 1.
+    ///
+    /// Segments that only move on from events stay at 0.
+    pub fn progress(&self) -> f32 {
+        let fr = match self.segments[self.current].advance {
+            Some(Advance::Secs(secs)) => self.secs / secs,
+            Some(Advance::Bars(bars)) => self.beats / (bars * BEATS_PER_BAR),
+            None => 0.0,
+        };
+        fr.clamp(0.0, 1.0)
+    }
+
+    /// Seconds spent in the current segment.
+    pub fn elapsed(&self) -> f32 {
+        self.secs
+    }
+
+    pub fn update(&mut self, p: &Player, dt: f32, ctx: &mut C) {
+        self.secs += dt;
+        self.beats += p.bpm() / 60.0 * dt;
+
+        let done = match self.segments[self.current].advance {
+            Some(Advance::Secs(secs)) => self.secs >= secs,
+            Some(Advance::Bars(bars)) => self.beats >= bars * BEATS_PER_BAR,
+            None => false,
+        };
+        if done {
+            self.next(ctx);
+        }
+    }
+
+    /// Move on if the event is one of the current segment's triggers.
+    ///
+    /// Returns whether the segment changed.
+    pub fn event(&mut self, p: &Player, ev: Event, ctx: &mut C) -> bool {
+        let name = p.name(&ev);
+        let id = match ev {
+            Event::Trigger { id } | Event::Beat { id, .. } | Event::Toggle { id, state: true } => Some(id),
+            _ => None,
+        };
+
+        let hit = self.segments[self.current].triggers.iter().any(|trigger| match *trigger {
+            Trigger::Note(note) => id == Some(note),
+            Trigger::Name(n) => name == Some(n),
+        });
+        if hit {
+            self.next(ctx);
+        }
+        hit
+    }
+
+    /// Move on to the next segment.
+    pub fn next(&mut self, ctx: &mut C) {
+        let segment = &self.segments[self.current];
+        let next = match segment.next {
+            Some(next) => next,
+            None => self.segments[(self.current + 1) % self.segments.len()].id,
+        };
+        self.go(next, ctx);
+    }
+
+    /// Jump to a segment, running the exit and enter actions.
+    pub fn go(&mut self, to: S, ctx: &mut C) {
+        let i = match self.segments.iter().position(|segment| segment.id == to) {
+            Some(i) => i,
+            None => {
+                log::warn!("No such segment to go to");
+                return;
+            }
+        };
+
+        if let Some(exit) = self.segments[self.current].exit.as_mut() {
+            exit(ctx);
+        }
+        self.current = i;
+        self.secs = 0.0;
+        self.beats = 0.0;
+        if let Some(enter) = self.segments[self.current].enter.as_mut() {
+            enter(ctx);
+        }
+    }
+
+    /// Back to the first segment, without running any actions.
+    pub fn reset(&mut self) {
+        self.current = 0;
+        self.secs = 0.0;
+        self.beats = 0.0;
+    }
+}
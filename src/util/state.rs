@@ -14,6 +14,10 @@ impl Counter {
         self.n
     }
 
+    pub fn reset(&mut self) {
+        self.n = 0;
+    }
+
     pub fn vv(&self) -> Option<usize> {
         if self.n == 0 {
             None
@@ -52,6 +56,10 @@ impl CounterEnv {
         self
     }
 
+    pub fn contains(&self, key: &str) -> bool {
+        self.map.contains_key(key)
+    }
+
     pub fn get(&self, key: &str) -> &Counter {
         self.map
             .get(key)
@@ -67,6 +75,13 @@ impl CounterEnv {
         self.get(key).v()
     }
 
+    /// Reset every counter back to 0.
+    pub fn reset(&mut self) {
+        for counter in self.map.values_mut() {
+            counter.reset();
+        }
+    }
+
     pub fn vv(&self, key: &str) -> Option<usize> {
         self.get(key).vv()
     }
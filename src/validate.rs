@@ -0,0 +1,149 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+
+use crate::demo::Stages;
+use crate::util::{ModMatrix, Routes};
+
+/// Check the stage graph and the resources stages use, before the show starts.
+///
+/// Each stage is checked against what it declares in its `StageInfo`: its
+/// configs are parsed with the stage's own `StageConfig`, its routes and
+/// matrix are parsed, and the scene names it uses are looked up in its
+/// scenes. Names built at runtime with `format!` can't be declared, and
+/// aren't checked.
+pub fn validate(stages: &Stages) -> Vec<String> {
+    let mut problems = vec![];
+
+    for name in stages.planned() {
+        if stages.find(name).is_none() {
+            problems.push(format!("setlist: stage '{}' is not registered", name));
+        }
+    }
+    for name in stages.registered() {
+        if stages.info(name).is_none() {
+            problems.push(format!("stage '{}' has no StageInfo", name));
+        }
+    }
+
+    for (stage, info) in stages.declared() {
+        let mut problem = |problem: String| problems.push(format!("{}: {}", stage, problem));
+
+        if stages.find(stage).is_none() {
+            problem("has a StageInfo but is not registered".to_owned());
+        }
+        for to in info.goes() {
+            if stages.find(to).is_none() {
+                problem(format!("go target '{}' is not registered", to));
+            }
+        }
+
+        for (cfg, check) in info.cfgs.iter() {
+            match read_cfg(cfg) {
+                Ok((path, src)) => check(&src).into_iter().for_each(|p| problem(format!("{}:{}", path, p))),
+                Err(e) => problem(format!("{:#}", e)),
+            }
+        }
+        for cfg in info.routes.iter() {
+            match read_cfg(cfg) {
+                Ok((path, src)) => Routes::scan(&src).1.into_iter().for_each(|p| problem(format!("{}:{}", path, p))),
+                Err(e) => problem(format!("{:#}", e)),
+            }
+        }
+        for cfg in info.matrices.iter() {
+            match read_cfg(cfg) {
+                Ok((path, src)) => ModMatrix::scan(&src).1.into_iter().for_each(|p| problem(format!("{}:{}", path, p))),
+                Err(e) => problem(format!("{:#}", e)),
+            }
+        }
+
+        // Scene node, material and light names
+        // If a scene can't be read, every name in it would be reported too
+        let mut names = SceneNames::default();
+        let mut scenes_ok = true;
+        for scene in info.scenes.iter() {
+            match scene_names(scene) {
+                Ok(n) => names.extend(n),
+                Err(e) => {
+                    problem(format!("{:#}", e));
+                    scenes_ok = false;
+                }
+            }
+        }
+        if !scenes_ok {
+            continue;
+        }
+        for node in info.nodes.iter().filter(|node| !names.nodes.contains(**node)) {
+            problem(format!("scene node '{}' does not exist", node));
+        }
+        for mat in info.materials.iter().filter(|mat| !names.materials.contains(**mat)) {
+            problem(format!("material '{}' does not exist", mat));
+        }
+        for light in info.lights.iter().filter(|l| !names.lights.contains(**l) && !names.nodes.contains(**l)) {
+            problem(format!("light '{}' does not exist", light));
+        }
+    }
+
+    problems
+}
+
+/// Read a file from `resources/config`, returning its path for messages.
+pub fn read_cfg(cfg: &str) -> Result<(String, String)> {
+    let path = Path::new("resources/config").join(cfg);
+    let src = std::fs::read_to_string(&path).with_context(|| format!("config {:?} is missing", path))?;
+    Ok((path.display().to_string(), src))
+}
+
+#[derive(Default)]
+struct SceneNames {
+    nodes: HashSet<String>,
+    materials: HashSet<String>,
+    lights: HashSet<String>,
+}
+
+impl SceneNames {
+    fn extend(&mut self, other: SceneNames) {
+        self.nodes.extend(other.nodes);
+        self.materials.extend(other.materials);
+        self.lights.extend(other.lights);
+    }
+}
+
+/// Read the node, material and light names out of a glTF scene's JSON.
+fn scene_names(scene: &str) -> Result<SceneNames> {
+    let path = Path::new("resources/scenes").join(scene);
+    let bytes = std::fs::read(&path).with_context(|| format!("scene {:?} is missing", path))?;
+
+    let json = if bytes.starts_with(b"glTF") {
+        // 12 byte header, then the JSON chunk's length and type
+        if bytes.len() < 20 || &bytes[16..20] != b"JSON" {
+            bail!("scene {:?} has no JSON chunk", path);
+        }
+        let len = u32::from_le_bytes(bytes[12..16].try_into().unwrap()) as usize;
+        bytes.get(20..20 + len).with_context(|| format!("scene {:?} is truncated", path))?
+    } else if bytes.starts_with(b"version https://git-lfs") {
+        bail!("scene {:?} is a git-lfs pointer, run `git lfs pull`", path);
+    } else {
+        &bytes[..]
+    };
+
+    let json: serde_json::Value = serde_json::from_slice(json).with_context(|| format!("scene {:?} is not valid glTF", path))?;
+    let names = |array: &serde_json::Value| -> HashSet<String> {
+        array
+            .as_array()
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|item| item["name"].as_str().map(str::to_owned))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    Ok(SceneNames {
+        nodes: names(&json["nodes"]),
+        materials: names(&json["materials"]),
+        lights: names(&json["extensions"]["KHR_lights_punctual"]["lights"]),
+    })
+}